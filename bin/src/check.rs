@@ -0,0 +1,45 @@
+use crate::error::Error;
+
+use std::convert::TryFrom;
+use std::path::PathBuf;
+
+use anyhow::Result;
+use clap::Parser;
+use kct_package::Package;
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(help = "directory with the package to check")]
+	package: PathBuf,
+}
+
+pub fn run(args: Args) -> Result<()> {
+	let package = Package::try_from(args.package.as_path())?;
+
+	let example = package.example.iter().map(|v| (String::from("example"), v));
+	let presets = package
+		.presets
+		.iter()
+		.map(|(name, v)| (format!("preset {name}"), v));
+
+	let mut checked = 0;
+	let mut invalid = vec![];
+	for (name, input) in example.chain(presets) {
+		checked += 1;
+
+		if let Err(violations) = package.validate(input) {
+			eprint!("The {name} doesn't match the schema:\n{violations}");
+			invalid.push(name);
+		}
+	}
+
+	if !invalid.is_empty() {
+		let reason = format!("the schema isn't matched by the {}", invalid.join(", "));
+
+		return Err(Error::InvalidInput(reason).into());
+	}
+
+	println!("{checked} inputs match the schema");
+
+	Ok(())
+}
@@ -0,0 +1,47 @@
+use crate::error::Error;
+use crate::operation::compile;
+use crate::redaction::{Concealer, Redact};
+
+use anyhow::Result;
+use clap::Parser;
+use kct_kube::{Diff, Manifest};
+
+#[derive(Parser)]
+pub struct Args {
+	#[arg(help = "compare two local renders instead of the cluster", long)]
+	offline: bool,
+	#[command(flatten)]
+	redact: Redact,
+	#[command(flatten)]
+	compile: compile::Params,
+	#[command(flatten)]
+	against: compile::Against,
+}
+
+pub fn run(args: Args, concealer: &Concealer) -> Result<()> {
+	if !args.offline {
+		let reason = "only --offline diffs are available".to_string();
+
+		return Err(Error::Unsupported(reason).into());
+	}
+
+	let against = args.compile.against(args.against);
+
+	let mut before: Vec<Manifest> = compile::run(args.compile, concealer)?.try_into()?;
+	let mut after: Vec<Manifest> = compile::run(against, concealer)?.try_into()?;
+
+	if args.redact.enabled(true) {
+		let redactor = concealer.redactor();
+		redactor.apply(&mut before);
+		redactor.apply(&mut after);
+	}
+
+	let diff = Diff::new(before, after)?;
+	if diff.is_empty() {
+		println!("No differences found");
+	} else {
+		print!("{diff}");
+	}
+
+	Ok(())
+}
@@ -7,6 +7,8 @@ use thiserror::Error;
 pub enum Error {
 	#[error("Invalid input: {0}")]
 	InvalidInput(String),
+	#[error("Unsupported operation: {0}")]
+	Unsupported(String),
 	#[error(transparent)]
 	IO(#[from] IOError),
 	#[error(transparent)]
@@ -1,4 +1,5 @@
 use crate::operation::compile;
+use crate::redaction::Concealer;
 
 use anyhow::Result;
 use clap::Parser;
@@ -9,8 +10,8 @@ pub struct Args {
 	compile: compile::Params,
 }
 
-pub async fn run(args: Args) -> Result<()> {
-	let kube = compile::run(args.compile)?;
+pub async fn run(args: Args, concealer: &Concealer) -> Result<()> {
+	let kube = compile::run(args.compile, concealer)?;
 	kube.install().await?;
 
 	Ok(())
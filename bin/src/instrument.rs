@@ -1,3 +1,5 @@
+use crate::redaction::{Concealer, Redacted};
+
 use tracing::error;
 use tracing_appender::non_blocking::WorkerGuard;
 use tracing_subscriber::filter::LevelFilter;
@@ -9,8 +11,8 @@ pub struct Guard {
 }
 
 #[must_use]
-pub fn init(level: u8) -> Guard {
-	let (writer, guard) = tracing_appender::non_blocking(std::io::stderr());
+pub fn init(level: u8, concealer: Concealer) -> Guard {
+	let (writer, guard) = tracing_appender::non_blocking(Redacted(concealer, std::io::stderr()));
 
 	let logs = fmt::layer()
 		.with_file(true)
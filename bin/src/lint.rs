@@ -0,0 +1,36 @@
+use crate::error::Error;
+use crate::operation::compile;
+use crate::redaction::Concealer;
+
+use anyhow::Result;
+use clap::Parser;
+use kct_kube::Error as KubeError;
+
+#[derive(Parser)]
+pub struct Args {
+	#[command(flatten)]
+	compile: compile::Params,
+}
+
+pub fn run(args: Args, concealer: &Concealer) -> Result<()> {
+	if !args.compile.reviews() {
+		let reason = "provide what to lint with --policies or --deprecations".to_string();
+
+		return Err(Error::InvalidInput(reason).into());
+	}
+
+	let kube = compile::run(args.compile, concealer)?;
+	let findings = kube.lint()?;
+
+	if findings.denied() {
+		return Err(Error::from(KubeError::Denied(findings)).into());
+	}
+
+	if findings.is_empty() {
+		println!("No violations found");
+	} else {
+		print!("{}", concealer.redact(&findings.to_string()));
+	}
+
+	Ok(())
+}
@@ -1,10 +1,16 @@
+mod check;
+mod diff;
 mod error;
 mod install;
 mod instrument;
+mod lint;
 mod operation;
+mod redaction;
 mod render;
 mod uninstall;
 
+use self::redaction::Concealer;
+
 use anyhow::Result;
 use clap::{ArgAction, Parser, Subcommand};
 
@@ -46,19 +52,41 @@ pub enum Command {
 		about = "Removes your objects from the current cluster"
 	)]
 	Uninstall(uninstall::Args),
+	#[command(
+		name = "diff",
+		alias = "d",
+		about = "Shows what changes between two renders of your package"
+	)]
+	Diff(diff::Args),
+	#[command(
+		name = "lint",
+		alias = "l",
+		about = "Reviews your objects against the policies"
+	)]
+	Lint(lint::Args),
+	#[command(
+		name = "check",
+		alias = "c",
+		about = "Validates the example and presets of your package against its schema"
+	)]
+	Check(check::Args),
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
 	let app = App::parse();
 
-	let _guard = instrument::init(app.verbose);
+	let concealer = Concealer::default();
+	let _guard = instrument::init(app.verbose, concealer.clone());
 
-	match app.command {
-		Command::Render(args) => render::run(args)?,
-		Command::Install(args) => install::run(args).await?,
-		Command::Uninstall(args) => uninstall::run(args).await?,
+	let result = match app.command {
+		Command::Render(args) => render::run(args, &concealer),
+		Command::Install(args) => install::run(args, &concealer).await,
+		Command::Uninstall(args) => uninstall::run(args, &concealer).await,
+		Command::Diff(args) => diff::run(args, &concealer),
+		Command::Lint(args) => lint::run(args, &concealer),
+		Command::Check(args) => check::run(args),
 	};
 
-	Ok(())
+	result.map_err(|err| concealer.error(err))
 }
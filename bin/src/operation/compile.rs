@@ -1,14 +1,16 @@
 use crate::error::Error;
+use crate::redaction::Concealer;
 
 use std::convert::TryFrom;
+use std::env;
 use std::path::PathBuf;
 
 use anyhow::Result;
 use clap::Parser;
-use kct_cli::{Input, Paths, Set};
+use kct_cli::{Error as CliError, Input, Merge, Pair, Paths, Set};
 use kct_compiler::Release;
-use kct_helper::json::merge;
-use kct_kube::Kube;
+use kct_helper::json::{layer, Strategy};
+use kct_kube::{Image, Kube, Patches, Policies, Schemas, Transformers, Version, BUNDLED};
 use kct_package::Package;
 use serde_json::{Map, Value};
 
@@ -18,38 +20,284 @@ pub struct Params {
 	package: PathBuf,
 	#[arg(help = "set multiple values for the package", long, short)]
 	input: Option<Vec<Input>>,
+	#[arg(
+		help = "use the example of the package as the base of the inputs",
+		long
+	)]
+	with_example: bool,
+	#[arg(
+		help = "layer the preset of the package under the inputs",
+		long,
+		value_name = "NAME"
+	)]
+	preset: Option<Vec<String>>,
+	#[arg(
+		help = "set parameters from environment variables with the prefix",
+		long,
+		value_name = "PREFIX"
+	)]
+	input_env: Option<Vec<String>>,
+	// sets are parsed along with the other inputs, so their errors are redacted like theirs
 	#[arg(help = "set specific parameters for the package", long, short)]
-	set: Option<Vec<Set>>,
+	set: Option<Vec<String>>,
+	#[arg(help = "set specific parameters as strings", long)]
+	set_string: Option<Vec<String>>,
+	#[arg(help = "set specific parameters to the contents of files", long)]
+	set_file: Option<Vec<String>>,
+	#[arg(help = "set specific parameters as JSON", long)]
+	set_json: Option<Vec<String>>,
+	#[arg(
+		help = "layer input arrays at the path with replace, append, or merge:key",
+		long,
+		value_name = "PATH=STRATEGY"
+	)]
+	merge_strategy: Option<Vec<Merge>>,
 	#[arg(help = "scope your package within a release", long)]
 	release: Option<String>,
 	#[arg(help = "comma separated paths to compile", long)]
 	only: Option<Paths>,
 	#[arg(help = "comma separated paths to not compile", long)]
 	except: Option<Paths>,
+	#[arg(
+		help = "allow the same object at different paths if they're equal",
+		long
+	)]
+	allow_identical_duplicates: bool,
+	#[arg(help = "validate objects against their schemas", long)]
+	validate: bool,
+	#[arg(
+		help = "kubernetes version for deprecations, --validate only supports 1.26",
+		long,
+		default_value_t = BUNDLED
+	)]
+	kube_version: Version,
+	#[arg(
+		help = "directory with CRDs to validate custom resources",
+		long,
+		requires = "validate"
+	)]
+	crds: Option<Vec<PathBuf>>,
+	#[arg(help = "directory with rules to review objects against", long)]
+	policies: Option<PathBuf>,
+	#[arg(
+		help = "flag apis deprecated or removed in the kubernetes version",
+		long
+	)]
+	deprecations: bool,
+	#[arg(help = "patch rendered objects with the file", long)]
+	patch: Option<Vec<PathBuf>>,
+	#[arg(help = "set the namespace of namespaced objects", long)]
+	namespace: Option<String>,
+	#[arg(help = "prefix the names of objects", long)]
+	name_prefix: Option<String>,
+	#[arg(help = "suffix the names of objects", long)]
+	name_suffix: Option<String>,
+	#[arg(help = "add a key=value label to objects", long)]
+	label: Option<Vec<Pair>>,
+	#[arg(help = "add the labels to selectors as well", long)]
+	include_selectors: bool,
+	#[arg(help = "add a key=value annotation to objects", long)]
+	annotation: Option<Vec<Pair>>,
+	#[arg(help = "override images as name=image, name:tag, or name@digest", long)]
+	image: Option<Vec<Image>>,
 }
 
-pub fn run(args: Params) -> Result<Kube> {
+/// Overrides applied on top of [`Params`] to render the other side of a comparison
+#[derive(Parser, Clone)]
+pub struct Against {
+	#[arg(help = "directory with the package to compare against", long)]
+	against_package: Option<PathBuf>,
+	#[arg(help = "layer more values on the compared package", long)]
+	against_input: Option<Vec<Input>>,
+	#[arg(help = "layer more parameters on the compared package", long)]
+	against_set: Option<Vec<String>>,
+	#[arg(help = "scope the compared package within another release", long)]
+	against_release: Option<String>,
+}
+
+impl Params {
+	/// Parameters for the compared render, the package and release are replaced while inputs and
+	/// sets are layered on top of the original ones
+	pub fn against(&self, against: Against) -> Params {
+		let mut params = self.clone();
+
+		if let Some(package) = against.against_package {
+			params.package = package;
+		}
+
+		if let Some(release) = against.against_release {
+			params.release = Some(release);
+		}
+
+		if let Some(input) = against.against_input {
+			params.input.get_or_insert_with(Vec::new).extend(input);
+		}
+
+		if let Some(set) = against.against_set {
+			params.set.get_or_insert_with(Vec::new).extend(set);
+		}
+
+		params
+	}
+
+	/// Whether there's anything to review the objects against
+	pub fn reviews(&self) -> bool {
+		self.policies.is_some() || self.deprecations
+	}
+}
+
+pub fn run(args: Params, concealer: &Concealer) -> Result<Kube> {
+	let package = Package::try_from(args.package.as_path())?;
+
+	// each layer is concealed once it's read, so errors from the next ones don't leak it
+	let conceal = |layer: &Value| concealer.conceal(package.sensitive(Some(layer)));
+
 	let input = {
-		let mut inputs = args
-			.input
+		let mut inputs = vec![];
+		for input in args.input.unwrap_or_default() {
+			let layers = match input {
+				Input::Values(values) => values,
+				Input::Jsonnet(file) => vec![package.evaluate(&file)?],
+			};
+
+			layers.iter().for_each(conceal);
+			inputs.extend(layers);
+		}
+
+		// presets go under the inputs, in order, and the example under all of them
+		let presets = args
+			.preset
 			.unwrap_or_default()
-			.into_iter()
-			.map(|input| input.into())
-			.collect::<Vec<Value>>();
+			.iter()
+			.map(|name| {
+				package.presets.get(name).cloned().ok_or_else(|| {
+					let known = package.presets.keys().cloned().collect::<Vec<_>>();
 
-		let sets = args
-			.set
+					Error::InvalidInput(format!(
+						"the package has no preset {name}, use one of [{}]",
+						known.join(", ")
+					))
+				})
+			})
+			.collect::<Result<Vec<Value>, _>>()?;
+		presets.iter().for_each(conceal);
+		inputs.splice(0..0, presets);
+
+		if args.with_example {
+			let example = package.example.clone().ok_or_else(|| {
+				Error::InvalidInput("the package has no example to use as base".to_string())
+			})?;
+			conceal(&example);
+			inputs.insert(0, example);
+		}
+
+		// variables that aren't unicode can't be meant for the package
+		let vars = || {
+			env::vars_os().filter_map(|(name, value)| {
+				Some((name.into_string().ok()?, value.into_string().ok()?))
+			})
+		};
+		let envs = args
+			.input_env
 			.unwrap_or_default()
-			.into_iter()
-			.map(|set| set.into())
-			.collect::<Vec<Value>>();
+			.iter()
+			.map(|prefix| Set::env(prefix, vars(), |path| package.input_properties(path)))
+			.collect::<Result<Vec<Set>, _>>()?;
 
-		inputs.extend(sets);
+		// environment variables go between inputs and sets, which follow the same order as helm
+		// so the more specific kinds win
+		let sets = [
+			(
+				args.set_json,
+				Set::json as fn(&str) -> Result<Set, CliError>,
+			),
+			(args.set, |text: &str| text.parse()),
+			(args.set_string, Set::string),
+			(args.set_file, Set::file),
+		]
+		.into_iter()
+		.flat_map(|(texts, parse)| {
+			let texts = texts.unwrap_or_default();
 
-		merge_inputs(&inputs)?
+			texts.into_iter().map(move |text| parse(&text))
+		})
+		.collect::<Result<Vec<Set>, _>>()?;
+		let sets = envs.into_iter().chain(sets).collect::<Vec<Set>>();
+
+		let rules = args.merge_strategy.unwrap_or_default();
+		let strategy = |path: &[String]| -> Strategy {
+			rules
+				.iter()
+				.find_map(|rule| rule.strategy(path))
+				.or_else(|| package.input_strategy(path))
+				.unwrap_or_default()
+		};
+
+		let mut input = merge_inputs(&inputs, &strategy)?;
+		if !sets.is_empty() {
+			let target = input.get_or_insert_with(|| Value::Object(Map::new()));
+			for set in sets {
+				set.apply(target, |path| package.input_types(path));
+			}
+		}
+
+		input
 	};
 
-	let package = Package::try_from(args.package.as_path())?;
+	if let Some(input) = &input {
+		conceal(input);
+	}
+
+	let patches = {
+		let base = match &package.patches {
+			Some(dir) => Patches::default().with_dir(dir)?,
+			None => Patches::default(),
+		};
+
+		args.patch
+			.unwrap_or_default()
+			.iter()
+			.try_fold(base, |patches, file| patches.with_file(file))?
+	};
+
+	let transformers = {
+		let base = match package.spec.transformers.clone() {
+			Some(value) => Transformers::try_from(value)?,
+			None => Transformers::default(),
+		};
+
+		let pairs = |pairs: Option<Vec<Pair>>| {
+			pairs
+				.unwrap_or_default()
+				.into_iter()
+				.map(|pair| pair.into())
+				.collect()
+		};
+
+		base.layer(Transformers {
+			namespace: args.namespace,
+			name_prefix: args.name_prefix,
+			name_suffix: args.name_suffix,
+			labels: pairs(args.label),
+			include_selectors: args.include_selectors,
+			annotations: pairs(args.annotation),
+			images: args.image.unwrap_or_default(),
+		})
+	};
+
+	// schemas are only bundled for one version, so other versions fail before compiling
+	let schemas = if args.validate {
+		let base = Schemas::new(args.kube_version)?;
+		let schemas = args
+			.crds
+			.unwrap_or_default()
+			.iter()
+			.try_fold(base, |schemas, dir| schemas.with_crds(dir))?;
+
+		Some(schemas)
+	} else {
+		None
+	};
 
 	let release = args.release.map(|name| Release { name });
 	let rendered = package.compile(input, release)?;
@@ -57,16 +305,28 @@ pub fn run(args: Params) -> Result<Kube> {
 	let only: Vec<PathBuf> = args.only.map(|v| v.into()).unwrap_or_default();
 	let except: Vec<PathBuf> = args.except.map(|v| v.into()).unwrap_or_default();
 
+	let policies = args.policies.map(|dir| Policies::load(&dir)).transpose()?;
+
 	let kube = Kube::builder()
 		.only(only)
 		.except(except)
+		.allow_identical(args.allow_identical_duplicates)
+		.schemas(schemas)
+		.policies(policies)
+		.deprecations(args.deprecations.then_some(args.kube_version))
+		.patches(patches)
+		.transformers(transformers)
+		.redactor(concealer.redactor())
 		.value(rendered)
 		.build()?;
 
 	Ok(kube)
 }
 
-fn merge_inputs(inputs: &[Value]) -> Result<Option<Value>, Error> {
+fn merge_inputs<F>(inputs: &[Value], strategy: &F) -> Result<Option<Value>, Error>
+where
+	F: Fn(&[String]) -> Strategy,
+{
 	if inputs.is_empty() {
 		return Ok(None);
 	}
@@ -75,7 +335,7 @@ fn merge_inputs(inputs: &[Value]) -> Result<Option<Value>, Error> {
 
 	for input in inputs {
 		match input {
-			Value::Object(_map) => merge(&mut base, input),
+			Value::Object(_map) => layer(&mut base, input, strategy),
 			_ => return Err(Error::InvalidInput("input is not object".to_string())),
 		}
 	}
@@ -0,0 +1,74 @@
+use std::io::{self, Write};
+use std::sync::{Arc, RwLock};
+
+use clap::Args;
+use kct_kube::Redactor;
+
+/// Option to mask secrets in what's printed, shared by the commands that print objects
+#[derive(Args, Clone)]
+pub struct Redact {
+	#[arg(
+		help = "mask secrets, on by default unless they're sealed or written to files",
+		long,
+		num_args = 0..=1,
+		require_equals = true,
+		default_missing_value = "true"
+	)]
+	redact_secrets: Option<bool>,
+}
+
+impl Redact {
+	/// Whether to mask secrets, falling back to the default of the command
+	pub fn enabled(&self, default: bool) -> bool {
+		self.redact_secrets.unwrap_or(default)
+	}
+}
+
+/// Sensitive values found so far, shared by the commands with the logs and errors they raise
+#[derive(Clone, Default)]
+pub struct Concealer(Arc<RwLock<Redactor>>);
+
+impl Concealer {
+	/// Masks the values in logs, errors, and redacted outputs from now on
+	pub fn conceal(&self, values: Vec<String>) {
+		let mut redactor = self.0.write().unwrap();
+		let current = std::mem::take(&mut *redactor);
+
+		*redactor = current.with_sensitive(values);
+	}
+
+	pub fn redactor(&self) -> Redactor {
+		self.0.read().unwrap().clone()
+	}
+
+	pub fn redact(&self, text: &str) -> String {
+		self.0.read().unwrap().text(text)
+	}
+
+	/// Masks each layer of the error on its own, keeping the causes apart
+	pub fn error(&self, err: anyhow::Error) -> anyhow::Error {
+		let mut layers = err
+			.chain()
+			.map(|layer| self.redact(&layer.to_string()))
+			.rev();
+		let root = anyhow::Error::msg(layers.next().unwrap_or_default());
+
+		layers.fold(root, |cause, layer| cause.context(layer))
+	}
+}
+
+/// Writer masking the sensitive values before they reach the inner one
+pub struct Redacted<W>(pub Concealer, pub W);
+
+impl<W: Write> Write for Redacted<W> {
+	fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+		let text = String::from_utf8_lossy(buf);
+		self.1.write_all(self.0.redact(&text).as_bytes())?;
+
+		Ok(buf.len())
+	}
+
+	fn flush(&mut self) -> io::Result<()> {
+		self.1.flush()
+	}
+}
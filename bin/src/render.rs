@@ -1,31 +1,103 @@
 use crate::error::Error;
 use crate::operation::compile;
+use crate::redaction::{Concealer, Redact};
 
+use std::fs;
 use std::path::PathBuf;
 
 use anyhow::Result;
 use clap::Parser;
 use kct_cli::Output;
 use kct_helper::io::{self, Location};
-use kct_kube::Manifest;
+use kct_kube::seal::Scope;
+use kct_kube::{Format, Graph, Manifest, Sealer};
 
 #[derive(Parser)]
 pub struct Args {
 	#[arg(help = "directory to save compiled manifests", long, short)]
 	output: Option<Output>,
+	#[arg(
+		help = "explain the order in which objects are applied",
+		long,
+		conflicts_with = "output"
+	)]
+	plan: bool,
+	#[arg(
+		help = "draw objects and their references in either dot or mermaid",
+		long,
+		conflicts_with_all = ["output", "plan"]
+	)]
+	graph: Option<Format>,
+	#[command(flatten)]
+	redact: Redact,
+	#[arg(
+		help = "seal secrets into SealedSecrets with the controller certificate",
+		long,
+		value_name = "FILE"
+	)]
+	seal_cert: Option<PathBuf>,
+	#[arg(
+		help = "where sealed secrets can be unsealed: strict, namespace-wide, or cluster-wide",
+		long,
+		default_value = "strict",
+		requires = "seal_cert"
+	)]
+	seal_scope: Scope,
+	#[arg(
+		help = "encrypt the values of secrets with SOPS for the age recipient",
+		long,
+		value_name = "RECIPIENT",
+		conflicts_with = "seal_cert"
+	)]
+	seal_age: Vec<String>,
 	#[command(flatten)]
 	compile: compile::Params,
 }
 
-pub fn run(args: Args) -> Result<()> {
-	let kube = compile::run(args.compile)?;
-	let manifests: Vec<Manifest> = kube.try_into()?;
+pub fn run(args: Args, concealer: &Concealer) -> Result<()> {
+	let kube = compile::run(args.compile, concealer)?;
+	if args.plan {
+		print!("{}", kube.plan()?);
+
+		return Ok(());
+	}
+
+	let sealer = match (&args.seal_cert, args.seal_age.is_empty()) {
+		(Some(path), _) => {
+			let pem = fs::read(path).map_err(|err| {
+				Error::InvalidInput(format!("unable to read {}: {err}", path.display()))
+			})?;
+
+			Some(Sealer::certificate(&pem, args.seal_scope)?)
+		}
+		(None, false) => Some(Sealer::sops(&args.seal_age)?),
+		(None, true) => None,
+	};
+
+	let mut manifests: Vec<Manifest> = kube.try_into()?;
+	let output = ensure_output_exists(&args.output)?;
+
+	if let Some(sealer) = &sealer {
+		sealer.apply(&mut manifests)?;
+	}
+
+	// sealed secrets are safe to print as they are
+	let redact = matches!(output, Location::Standard) && sealer.is_none();
+	if args.redact.enabled(redact) {
+		concealer.redactor().apply(&mut manifests);
+	}
+
+	if let Some(format) = args.graph {
+		print!("{}", Graph::new(&manifests).render(format));
+
+		return Ok(());
+	}
+
 	let documents: Vec<(PathBuf, String)> = manifests
 		.into_iter()
 		.map(|manifest| manifest.into())
 		.collect();
 
-	let output = ensure_output_exists(&args.output)?;
 	match output {
 		out @ Location::Standard => out.write(documents)?,
 
@@ -5,6 +5,17 @@ use thiserror::Error;
 pub enum Error {
 	#[error("Invalid input: {0}")]
 	InvalidInput(String),
+	#[error("Invalid input at {file}:{line}:{column}: {reason}")]
+	Syntax {
+		file: String,
+		line: usize,
+		column: usize,
+		reason: String,
+	},
+	#[error("Invalid set \"{0}\": {1}")]
+	InvalidSet(String, String),
+	#[error("Invalid environment variable {0}: {1}")]
+	InvalidEnv(String, String),
 	#[error("Invalid stream: {0}")]
 	InvalidStream(String),
 	#[error("Invalid output: {0}")]
@@ -1,66 +1,217 @@
 mod error;
+mod set;
 
-use crate::error::Error;
+pub use crate::error::Error;
+pub use crate::set::Set;
 
 use std::convert::Infallible;
+use std::fmt;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+use kct_helper::crypt::{self, Keys, Ordered};
 use kct_helper::io::{self, Location};
-use kct_helper::json::set_in;
+use kct_helper::json::Strategy;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use serde_json::Value;
 
+/// Values for the package, either read from JSON or YAML, or from Jsonnet files that are only
+/// evaluated once the package is known, so they can import its libraries
+///
+/// The documents of YAML files are kept apart, so they're layered in order the same way as
+/// separate inputs.
 #[derive(Clone)]
-pub struct Input(Value);
+pub enum Input {
+	Values(Vec<Value>),
+	Jsonnet(PathBuf),
+}
 
 impl FromStr for Input {
 	type Err = Error;
 
 	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-		let location = Location::from_str(s).map_err(|err| Error::InvalidInput(err.to_string()))?;
-		let contents = location
-			.read()
-			.map_err(|err| Error::InvalidInput(err.to_string()))?;
-		let parsed: Value =
-			serde_json::from_str(&contents).map_err(|err| Error::InvalidInput(err.to_string()))?;
-
-		Ok(Self(parsed))
+		let invalid = |err: &dyn std::fmt::Display| Error::InvalidInput(err.to_string());
+
+		let location = Location::from_str(s).map_err(|err| invalid(&err))?;
+		if let Some(path) = location.path() {
+			if path.extension().and_then(|e| e.to_str()) == Some("jsonnet") {
+				return Ok(Self::Jsonnet(path.to_path_buf()));
+			}
+		}
+
+		let mut contents = location.clone().read_bytes().map_err(|err| invalid(&err))?;
+
+		if crypt::is_age(&contents) {
+			let keys = Keys::from_env().map_err(|err| invalid(&err))?;
+			contents = crypt::decrypt_age(&contents, &keys).map_err(|err| invalid(&err))?;
+		}
+
+		let contents = String::from_utf8(contents).map_err(|err| invalid(&err))?;
+		let format = Format::detect(&location, &contents);
+		let mut documents: Vec<Value> = format.parse(&location, &contents)?;
+
+		// the MAC follows the order of the fields in the file, which values don't keep
+		if let [document] = documents.as_mut_slice() {
+			if crypt::is_sops(document) {
+				let keys = Keys::from_env().map_err(|err| invalid(&err))?;
+
+				if let Some(encrypted) = format.parse::<Ordered>(&location, &contents)?.pop() {
+					*document =
+						crypt::decrypt_sops(encrypted, &keys).map_err(|err| invalid(&err))?;
+				}
+			}
+		}
+
+		Ok(Self::Values(documents))
 	}
 }
 
-impl From<Input> for Value {
-	fn from(val: Input) -> Self {
-		val.0
+/// Formats inputs can be written in
+#[derive(Clone, Copy)]
+enum Format {
+	Json,
+	Yaml,
+}
+
+impl Format {
+	/// Trusts the extension of files, otherwise looks at the contents as with STDIN
+	fn detect(location: &Location, contents: &str) -> Self {
+		let extension = location
+			.path()
+			.and_then(|path| path.extension())
+			.and_then(|extension| extension.to_str());
+
+		match extension {
+			Some("json") => Format::Json,
+			Some("yaml" | "yml") => Format::Yaml,
+			_ if contents.trim_start().starts_with(['{', '[']) => Format::Json,
+			_ => Format::Yaml,
+		}
+	}
+
+	/// Parses the contents into its documents, leaving out the empty ones of YAML
+	fn parse<T: DeserializeOwned>(
+		self,
+		location: &Location,
+		contents: &str,
+	) -> Result<Vec<T>, Error> {
+		let file = location
+			.path()
+			.map(|path| path.display().to_string())
+			.unwrap_or_else(|| String::from("stdin"));
+
+		// both parsers end their messages with the position, which is reported on its own
+		let syntax = |err: &dyn fmt::Display, position: Option<(usize, usize)>| {
+			let message = err.to_string();
+
+			match position {
+				Some((line, column)) => {
+					let suffix = format!(" at line {line} column {column}");
+
+					Error::Syntax {
+						file: file.clone(),
+						line,
+						column,
+						reason: message
+							.strip_suffix(&suffix)
+							.unwrap_or(&message)
+							.to_string(),
+					}
+				}
+				None => Error::InvalidInput(format!("{file}: {message}")),
+			}
+		};
+
+		match self {
+			Format::Json => {
+				let value = serde_json::from_str(contents).map_err(|err| {
+					let position = (err.line() > 0).then(|| (err.line(), err.column()));
+
+					syntax(&err, position)
+				})?;
+
+				Ok(vec![value])
+			}
+			Format::Yaml => {
+				let mut documents = vec![];
+
+				for document in serde_yaml::Deserializer::from_str(contents) {
+					let value = Option::<T>::deserialize(document).map_err(|err| {
+						// documents share the errors of the stream, which don't keep their location,
+						// so it's taken from loading the contents at once, failing at the same place
+						let location = err.location().or_else(|| {
+							serde_yaml::from_str::<serde_yaml::Value>(contents)
+								.err()?
+								.location()
+						});
+						let position = location.map(|at| (at.line(), at.column()));
+
+						syntax(&err, position)
+					})?;
+
+					documents.extend(value);
+				}
+
+				Ok(documents)
+			}
+		}
 	}
 }
 
 #[derive(Clone)]
-pub struct Set(Value);
+pub struct Pair(String, String);
 
-impl FromStr for Set {
+impl FromStr for Pair {
 	type Err = Error;
 
 	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
-		let (name, value) = {
-			let parts = s.split('=').collect::<Vec<&str>>();
+		match s.split_once('=') {
+			Some((key, value)) if !key.trim().is_empty() => {
+				Ok(Self(key.trim().to_string(), value.to_string()))
+			}
+			_ => Err(Error::InvalidInput(format!("\"{s}\" should be key=value"))),
+		}
+	}
+}
 
-			(parts[0], parts[1])
-		};
+impl From<Pair> for (String, String) {
+	fn from(val: Pair) -> Self {
+		(val.0, val.1)
+	}
+}
+
+/// Strategy for layering inputs at the arrays matching a dotted path, where `*` matches any key or
+/// index
+#[derive(Clone)]
+pub struct Merge(Vec<String>, Strategy);
 
-		let mut result = Value::Null;
-		let path = name.split('.').collect::<Vec<&str>>();
-		let value =
-			serde_json::from_str(value).map_err(|err| Error::InvalidInput(err.to_string()))?;
+impl FromStr for Merge {
+	type Err = Error;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		let (path, strategy) = s
+			.split_once('=')
+			.ok_or_else(|| Error::InvalidInput(format!("\"{s}\" should be path=strategy")))?;
 
-		set_in(&mut result, &path, value);
+		let path = path.split('.').map(String::from).collect();
+		let strategy = strategy.parse().map_err(Error::InvalidInput)?;
 
-		Ok(Self(result))
+		Ok(Self(path, strategy))
 	}
 }
 
-impl From<Set> for Value {
-	fn from(val: Set) -> Self {
-		val.0
+impl Merge {
+	/// The strategy, if the path matches
+	pub fn strategy(&self, path: &[String]) -> Option<Strategy> {
+		let matches = self.0.len() == path.len()
+			&& self
+				.0
+				.iter()
+				.zip(path)
+				.all(|(pattern, segment)| pattern == "*" || pattern == segment);
+
+		matches.then(|| self.1.clone())
 	}
 }
 
@@ -0,0 +1,458 @@
+use crate::error::Error;
+
+use std::fs;
+use std::str::FromStr;
+
+use serde_json::{Map, Number, Value};
+
+/// Largest array index accepted, so a typo doesn't allocate a huge array of nulls
+const MAX_INDEX: usize = 65536;
+
+/// Separator of the keys in the names of environment variables
+const ENV_SEPARATOR: &str = "__";
+
+/// Step into the input, either a field of an object or a position in an array
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+	Key(String),
+	Index(usize),
+}
+
+/// Value as written on the command line, before the schema decides its type
+#[derive(Clone, Debug)]
+enum Raw {
+	Infer(String),
+	Text(String),
+	Json(Value),
+	List(Vec<Raw>),
+}
+
+#[derive(Clone, Debug)]
+struct Assignment {
+	path: Vec<Segment>,
+	value: Raw,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+	Infer,
+	Text,
+	File,
+	Json,
+}
+
+/// Parameters set on the command line with the same grammar as Helm
+///
+/// A set holds comma separated `path=value` assignments, where the path is made of dot
+/// separated keys and `[n]` indices, with `\` escaping dots, commas, and brackets. Values within
+/// braces are lists, like `{a,b}`. Plain sets have their types decided by the schema, or
+/// guessed from the text when the schema doesn't tell, while the others are always strings, file
+/// contents, or JSON.
+#[derive(Clone, Debug)]
+pub struct Set(Vec<Assignment>);
+
+impl FromStr for Set {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		Parser::new(s).parse(Mode::Infer)
+	}
+}
+
+impl Set {
+	/// Parses assignments whose values are always strings
+	pub fn string(s: &str) -> Result<Self, Error> {
+		Parser::new(s).parse(Mode::Text)
+	}
+
+	/// Parses assignments whose values are paths to files with the contents to set
+	pub fn file(s: &str) -> Result<Self, Error> {
+		Parser::new(s).parse(Mode::File)
+	}
+
+	/// Parses assignments whose values are JSON
+	pub fn json(s: &str) -> Result<Self, Error> {
+		Parser::new(s).parse(Mode::Json)
+	}
+
+	/// Reads the environment variables starting with the prefix, where the rest of the name is the
+	/// path with keys separated by `__`
+	///
+	/// Keys match the properties declared at their path regardless of case and underscores, so
+	/// `IMAGE_PULL_POLICY` sets `imagePullPolicy`, and are lowercased otherwise. Numbers are
+	/// indices into arrays.
+	pub fn env<V, F>(prefix: &str, vars: V, properties: F) -> Result<Self, Error>
+	where
+		V: IntoIterator<Item = (String, String)>,
+		F: Fn(&[String]) -> Vec<String>,
+	{
+		let normalize = |key: &str| key.replace(['_', '-'], "").to_lowercase();
+
+		let mut vars: Vec<(String, String)> = vars
+			.into_iter()
+			.filter(|(name, _)| name.len() > prefix.len() && name.starts_with(prefix))
+			.collect();
+		vars.sort();
+
+		let mut assignments = vec![];
+		for (name, value) in vars {
+			let mut path = vec![];
+			let mut keys: Vec<String> = vec![];
+
+			for segment in name[prefix.len()..].split(ENV_SEPARATOR) {
+				if segment.is_empty() {
+					return Err(Error::InvalidEnv(name, String::from("it has an empty key")));
+				}
+
+				let step = match segment.parse::<usize>() {
+					Ok(index) if index <= MAX_INDEX => Segment::Index(index),
+					_ => {
+						let key = properties(&keys)
+							.into_iter()
+							.find(|property| normalize(property) == normalize(segment))
+							.unwrap_or_else(|| segment.to_lowercase());
+
+						Segment::Key(key)
+					}
+				};
+
+				keys.push(match &step {
+					Segment::Key(key) => key.clone(),
+					Segment::Index(index) => index.to_string(),
+				});
+				path.push(step);
+			}
+
+			let value = Raw::Infer(value);
+			assignments.push(Assignment { path, value });
+		}
+
+		Ok(Set(assignments))
+	}
+
+	/// Writes the values into the target, asking for the types declared at each path
+	///
+	/// Like Helm, a plain `null` deletes the key instead, unless the schema declares `null` at the
+	/// path, while items of arrays become `null` so the others keep their positions. JSON values
+	/// are always set as they are.
+	pub fn apply<F>(&self, target: &mut Value, types: F)
+	where
+		F: Fn(&[String]) -> Vec<String>,
+	{
+		for assignment in &self.0 {
+			let value = resolve(&assignment.value, &assignment.path, &types);
+
+			let deletes = matches!(assignment.value, Raw::Infer(_))
+				&& value.is_null()
+				&& !types(&keys(&assignment.path)).iter().any(|t| t == "null");
+			match (deletes, assignment.path.last()) {
+				(true, Some(Segment::Key(_))) => remove(target, &assignment.path),
+				_ => insert(target, &assignment.path, value),
+			}
+		}
+	}
+}
+
+fn keys(path: &[Segment]) -> Vec<String> {
+	path.iter()
+		.map(|segment| match segment {
+			Segment::Key(key) => key.clone(),
+			Segment::Index(index) => index.to_string(),
+		})
+		.collect()
+}
+
+fn resolve<F>(raw: &Raw, path: &[Segment], types: &F) -> Value
+where
+	F: Fn(&[String]) -> Vec<String>,
+{
+	match raw {
+		Raw::Text(text) => Value::String(text.clone()),
+		Raw::Json(value) => value.clone(),
+		Raw::List(items) => {
+			let items = items.iter().enumerate().map(|(index, item)| {
+				let mut path = path.to_vec();
+				path.push(Segment::Index(index));
+
+				resolve(item, &path, types)
+			});
+
+			Value::Array(items.collect())
+		}
+		Raw::Infer(text) => typed(text, &types(&keys(path))),
+	}
+}
+
+/// Converts the text to the first type declared that accepts it, or guesses like Helm when none
+/// are declared, keeping it as a string if nothing fits
+fn typed(text: &str, types: &[String]) -> Value {
+	let declared = |kind: &str| types.is_empty() || types.iter().any(|t| t == kind);
+
+	if declared("null") && text == "null" {
+		return Value::Null;
+	}
+
+	if declared("boolean") {
+		match text {
+			"true" => return Value::Bool(true),
+			"false" => return Value::Bool(false),
+			_ => (),
+		}
+	}
+
+	// without a schema, leading zeros are kept as they're usually meant as strings, like zip codes
+	let padded =
+		types.is_empty() && text.len() > 1 && text.trim_start_matches('-').starts_with('0');
+	if (declared("integer") || declared("number")) && !padded {
+		if let Ok(number) = text.parse::<i64>() {
+			return Value::Number(number.into());
+		}
+	}
+
+	if !types.is_empty() && declared("number") {
+		if let Some(number) = text.parse::<f64>().ok().and_then(Number::from_f64) {
+			return Value::Number(number);
+		}
+	}
+
+	Value::String(text.to_string())
+}
+
+fn insert(target: &mut Value, path: &[Segment], value: Value) {
+	match path.split_first() {
+		None => *target = value,
+		Some((Segment::Key(key), rest)) => {
+			if !target.is_object() {
+				*target = Value::Object(Map::new());
+			}
+
+			let map = target.as_object_mut().unwrap();
+			insert(map.entry(key.clone()).or_insert(Value::Null), rest, value);
+		}
+		Some((Segment::Index(index), rest)) => {
+			if !target.is_array() {
+				*target = Value::Array(vec![]);
+			}
+
+			let items = target.as_array_mut().unwrap();
+			if items.len() <= *index {
+				items.resize(index + 1, Value::Null);
+			}
+
+			insert(&mut items[*index], rest, value);
+		}
+	}
+}
+
+/// Deletes the key at the end of the path, leaving the target alone when the path isn't there
+fn remove(target: &mut Value, path: &[Segment]) {
+	let (last, parents) = match path.split_last() {
+		Some(split) => split,
+		None => return,
+	};
+
+	let mut current = target;
+	for segment in parents {
+		let next = match segment {
+			Segment::Key(key) => current.get_mut(key.as_str()),
+			Segment::Index(index) => current.get_mut(*index),
+		};
+
+		current = match next {
+			Some(next) => next,
+			None => return,
+		};
+	}
+
+	if let (Segment::Key(key), Some(map)) = (last, current.as_object_mut()) {
+		map.remove(key);
+	}
+}
+
+struct Parser<'a> {
+	text: &'a str,
+	position: usize,
+}
+
+impl<'a> Parser<'a> {
+	fn new(text: &'a str) -> Self {
+		Parser { text, position: 0 }
+	}
+
+	fn peek(&self) -> Option<char> {
+		self.text[self.position..].chars().next()
+	}
+
+	fn next(&mut self) -> Option<char> {
+		let next = self.peek()?;
+		self.position += next.len_utf8();
+
+		Some(next)
+	}
+
+	fn fail(&self, reason: impl Into<String>) -> Error {
+		Error::InvalidSet(self.text.to_string(), reason.into())
+	}
+
+	/// Character after a backslash, taken as it is
+	fn escaped(&mut self) -> Result<char, Error> {
+		self.next()
+			.ok_or_else(|| self.fail("it ends with an unfinished escape"))
+	}
+
+	fn parse(mut self, mode: Mode) -> Result<Set, Error> {
+		let mut assignments = vec![];
+
+		loop {
+			let path = self.path()?;
+			let value = self.value(mode)?;
+			assignments.push(Assignment { path, value });
+
+			match self.next() {
+				None => break,
+				Some(',') => continue,
+				Some(other) => return Err(self.fail(format!("unexpected \"{other}\""))),
+			}
+		}
+
+		Ok(Set(assignments))
+	}
+
+	fn path(&mut self) -> Result<Vec<Segment>, Error> {
+		let mut path = vec![];
+		let mut key = String::new();
+		// an index closes its segment, so only separators can follow it
+		let mut indexed = false;
+
+		loop {
+			let next = self
+				.next()
+				.ok_or_else(|| self.fail("the last key has no value, use key=value"))?;
+
+			match next {
+				'.' | '[' | '=' => {
+					if !key.is_empty() {
+						path.push(Segment::Key(std::mem::take(&mut key)));
+					} else if !indexed {
+						return Err(self.fail("it has an empty key"));
+					}
+
+					indexed = false;
+					match next {
+						'[' => {
+							path.push(self.index()?);
+							indexed = true;
+						}
+						'=' => break,
+						_ => (),
+					}
+				}
+				',' => return Err(self.fail("a key has no value, use key=value")),
+				_ if indexed => {
+					return Err(self.fail("indices should be followed by \".\", \"[\", or \"=\""))
+				}
+				'\\' => key.push(self.escaped()?),
+				other => key.push(other),
+			}
+		}
+
+		Ok(path)
+	}
+
+	fn index(&mut self) -> Result<Segment, Error> {
+		let mut digits = String::new();
+		loop {
+			match self.next() {
+				Some(']') => break,
+				Some(digit) if digit.is_ascii_digit() => digits.push(digit),
+				_ => return Err(self.fail("indices should be numbers like [0]")),
+			}
+		}
+
+		let index: usize = digits
+			.parse()
+			.map_err(|_err| self.fail("indices should be numbers like [0]"))?;
+		if index > MAX_INDEX {
+			return Err(self.fail(format!("indices can't be over {MAX_INDEX}")));
+		}
+
+		Ok(Segment::Index(index))
+	}
+
+	fn value(&mut self, mode: Mode) -> Result<Raw, Error> {
+		match mode {
+			Mode::Json => self.json(),
+			Mode::File => {
+				let path = self.text_until(&[','])?;
+				let contents = fs::read_to_string(&path)
+					.map_err(|err| self.fail(format!("unable to read {path}: {err}")))?;
+
+				Ok(Raw::Text(contents))
+			}
+			Mode::Infer | Mode::Text if self.peek() == Some('{') => {
+				self.next();
+
+				let mut items = vec![];
+				if self.peek() == Some('}') {
+					self.next();
+
+					return Ok(Raw::List(items));
+				}
+
+				loop {
+					let item = self.text_until(&[',', '}'])?;
+					items.push(scalar(mode, item));
+
+					match self.next() {
+						Some('}') => break,
+						Some(',') => continue,
+						_ => return Err(self.fail("a list is missing its closing \"}\"")),
+					}
+				}
+
+				Ok(Raw::List(items))
+			}
+			Mode::Infer | Mode::Text => Ok(scalar(mode, self.text_until(&[','])?)),
+		}
+	}
+
+	/// Text up to one of the unescaped stops, which is left to be read
+	fn text_until(&mut self, stops: &[char]) -> Result<String, Error> {
+		let mut text = String::new();
+
+		while let Some(next) = self.peek() {
+			if stops.contains(&next) {
+				break;
+			}
+
+			self.next();
+			match next {
+				'\\' => text.push(self.escaped()?),
+				other => text.push(other),
+			}
+		}
+
+		Ok(text)
+	}
+
+	fn json(&mut self) -> Result<Raw, Error> {
+		let rest = &self.text[self.position..];
+		let mut stream = serde_json::Deserializer::from_str(rest).into_iter::<Value>();
+
+		let value = match stream.next() {
+			Some(Ok(value)) => value,
+			Some(Err(err)) => return Err(self.fail(format!("invalid JSON, {err}"))),
+			None => return Err(self.fail("a key has no JSON value")),
+		};
+		self.position += stream.byte_offset();
+
+		Ok(Raw::Json(value))
+	}
+}
+
+fn scalar(mode: Mode, text: String) -> Raw {
+	match mode {
+		Mode::Text => Raw::Text(text),
+		_ => Raw::Infer(text),
+	}
+}
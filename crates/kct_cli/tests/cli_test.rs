@@ -0,0 +1,420 @@
+use std::fs;
+use std::str::FromStr;
+
+use assert_matches::assert_matches;
+use kct_cli::{Error, Set};
+use kct_testing::dir;
+use serde_json::{json, Value};
+
+/// Applies the set to an empty input, with the types the schema would declare for every path
+fn apply_typed(set: Result<Set, Error>, types: &[&str]) -> Value {
+	let mut target = json!({});
+	set.unwrap().apply(&mut target, |_path| {
+		types.iter().map(|kind| kind.to_string()).collect()
+	});
+
+	target
+}
+
+fn apply(set: Result<Set, Error>) -> Value {
+	apply_typed(set, &[])
+}
+
+mod set {
+	use super::*;
+
+	#[test]
+	fn nests_dotted_keys() {
+		assert_eq!(
+			apply(Set::from_str("image.tag=1.0,image.name=app")),
+			json!({"image": {"tag": "1.0", "name": "app"}})
+		);
+	}
+
+	#[test]
+	fn escapes_separators_in_keys() {
+		assert_eq!(
+			apply(Set::from_str(r"annotations.app\.kubernetes\.io/name=app")),
+			json!({"annotations": {"app.kubernetes.io/name": "app"}})
+		);
+		assert_eq!(
+			apply(Set::from_str(r"key\[0\]=value")),
+			json!({"key[0]": "value"})
+		);
+	}
+
+	#[test]
+	fn escapes_commas_in_values() {
+		assert_eq!(
+			apply(Set::from_str(r"hosts=a\,b,port=80")),
+			json!({"hosts": "a,b", "port": 80})
+		);
+	}
+
+	#[test]
+	fn keeps_equals_in_values() {
+		assert_eq!(
+			apply(Set::from_str("url=http://host/?a=b&c=d")),
+			json!({"url": "http://host/?a=b&c=d"})
+		);
+	}
+
+	#[test]
+	fn sets_array_indices() {
+		assert_eq!(
+			apply(Set::from_str("ports[1].name=http,matrix[0][1]=2")),
+			json!({"ports": [null, {"name": "http"}], "matrix": [[null, 2]]})
+		);
+	}
+
+	#[test]
+	fn limits_array_indices() {
+		let largest = apply(Set::from_str("items[65536]=last"));
+		assert_eq!(largest["items"].as_array().unwrap().len(), 65537);
+
+		assert_matches!(
+			Set::from_str("items[65537]=last"),
+			Err(Error::InvalidSet(_, reason)) if reason.contains("65536")
+		);
+	}
+
+	#[test]
+	fn sets_lists_within_braces() {
+		assert_eq!(
+			apply(Set::from_str("tags={a,b},ports={80,443},empty={}")),
+			json!({"tags": ["a", "b"], "ports": [80, 443], "empty": []})
+		);
+	}
+
+	#[test]
+	fn keeps_strings_as_strings() {
+		assert_eq!(
+			apply(Set::string("enabled=true,replicas=3,tags={1,2}")),
+			json!({"enabled": "true", "replicas": "3", "tags": ["1", "2"]})
+		);
+	}
+
+	#[test]
+	fn reads_files() {
+		let dir = dir::tmp();
+		let path = dir.path().join("cert.pem");
+		fs::write(&path, "-----BEGIN CERTIFICATE-----\n").unwrap();
+
+		let set = Set::file(&format!("tls.cert={}", path.display()));
+
+		assert_eq!(
+			apply(set),
+			json!({"tls": {"cert": "-----BEGIN CERTIFICATE-----\n"}})
+		);
+	}
+
+	#[test]
+	fn reads_json_followed_by_more_assignments() {
+		assert_eq!(
+			apply(Set::json(
+				r#"db={"hosts": ["a", "b"], "port": 5432},tls=true"#
+			)),
+			json!({"db": {"hosts": ["a", "b"], "port": 5432}, "tls": true})
+		);
+	}
+
+	#[test]
+	fn guesses_types_without_a_schema() {
+		assert_eq!(
+			apply(Set::from_str(
+				"on=true,off=false,none=null,count=42,ratio=1.5,zip=007,name=app"
+			)),
+			json!({
+				"on": true,
+				"off": false,
+				"count": 42,
+				"ratio": "1.5",
+				"zip": "007",
+				"name": "app"
+			})
+		);
+	}
+
+	#[test]
+	fn deletes_keys_set_to_null() {
+		let mut target = json!({"image": {"tag": "1.0", "name": "app"}, "ports": [80, 443]});
+		let set = Set::from_str("image.tag=null,ports[0]=null,missing.key=null").unwrap();
+		set.apply(&mut target, |_path| vec![]);
+
+		assert_eq!(
+			target,
+			json!({"image": {"name": "app"}, "ports": [null, 443]})
+		);
+	}
+
+	#[test]
+	fn sets_null_when_declared_or_as_json() {
+		let set = Set::from_str("image.tag=null");
+		assert_eq!(
+			apply_typed(set, &["string", "null"]),
+			json!({"image": {"tag": null}})
+		);
+
+		assert_eq!(
+			apply(Set::json("image.tag=null")),
+			json!({"image": {"tag": null}})
+		);
+	}
+
+	#[test]
+	fn follows_the_types_of_the_schema() {
+		let set = || Set::from_str("value=007");
+
+		assert_eq!(apply_typed(set(), &["string"]), json!({"value": "007"}));
+		assert_eq!(apply_typed(set(), &["integer"]), json!({"value": 7}));
+		assert_eq!(
+			apply_typed(Set::from_str("value=1.5"), &["number"]),
+			json!({"value": 1.5})
+		);
+		assert_eq!(
+			apply_typed(Set::from_str("value=true"), &["string"]),
+			json!({"value": "true"})
+		);
+		assert_eq!(
+			apply_typed(Set::from_str("value=yes"), &["boolean"]),
+			json!({"value": "yes"})
+		);
+	}
+
+	#[test]
+	fn fails_on_invalid_assignments() {
+		let reason = |text: &str| match Set::from_str(text) {
+			Err(Error::InvalidSet(set, reason)) if set == text => reason,
+			other => panic!("expected {text} to be invalid, got {other:?}"),
+		};
+
+		assert!(reason("replicas").contains("has no value"));
+		assert!(reason("a,b=1").contains("has no value"));
+		assert!(reason("=1").contains("empty key"));
+		assert!(reason("a..b=1").contains("empty key"));
+		assert!(reason("a[x]=1").contains("numbers"));
+		assert!(reason("a[0]b=1").contains("followed by"));
+		assert!(reason("tags={a,b").contains("closing"));
+		assert!(reason(r"a=b\").contains("escape"));
+	}
+
+	#[test]
+	fn fails_on_invalid_json_and_files() {
+		assert_matches!(
+			Set::json("db="),
+			Err(Error::InvalidSet(_, reason)) if reason.contains("no JSON value")
+		);
+		assert_matches!(
+			Set::json("db={"),
+			Err(Error::InvalidSet(_, reason)) if reason.contains("invalid JSON")
+		);
+		assert_matches!(
+			Set::json("db={},"),
+			Err(Error::InvalidSet(_, reason)) if reason.contains("has no value")
+		);
+		assert_matches!(
+			Set::file("tls.cert=/missing/cert.pem"),
+			Err(Error::InvalidSet(_, reason)) if reason.contains("/missing/cert.pem")
+		);
+	}
+}
+
+mod env {
+	use super::*;
+
+	fn vars(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+		vars.iter()
+			.map(|(name, value)| (name.to_string(), value.to_string()))
+			.collect()
+	}
+
+	fn none(_path: &[String]) -> Vec<String> {
+		vec![]
+	}
+
+	#[test]
+	fn nests_keys_separated_by_underscores() {
+		let set = Set::env(
+			"APP_",
+			vars(&[("APP_DB__HOST", "db"), ("APP_DB__PORT", "5432")]),
+			none,
+		);
+
+		assert_eq!(apply(set), json!({"db": {"host": "db", "port": 5432}}));
+	}
+
+	#[test]
+	fn keeps_single_underscores_within_keys() {
+		let set = Set::env("APP_", vars(&[("APP_DB_HOST", "db")]), none);
+
+		assert_eq!(apply(set), json!({"db_host": "db"}));
+	}
+
+	#[test]
+	fn takes_numbers_as_indices() {
+		let set = Set::env("APP_", vars(&[("APP_PORTS__1__NAME", "http")]), none);
+
+		assert_eq!(apply(set), json!({"ports": [null, {"name": "http"}]}));
+	}
+
+	#[test]
+	fn matches_the_properties_of_the_schema() {
+		let properties = |path: &[String]| match path {
+			[] => vec![String::from("image")],
+			[image] if image == "image" => vec![String::from("imagePullPolicy")],
+			_ => vec![],
+		};
+		let set = Set::env(
+			"APP_",
+			vars(&[
+				("APP_IMAGE__IMAGE_PULL_POLICY", "Always"),
+				("APP_IMAGE__EXTRA_ARGS", "-v"),
+			]),
+			properties,
+		);
+
+		assert_eq!(
+			apply(set),
+			json!({"image": {"imagePullPolicy": "Always", "extra_args": "-v"}})
+		);
+	}
+
+	#[test]
+	fn ignores_other_variables() {
+		let set = Set::env(
+			"APP_",
+			vars(&[
+				("APP_", "empty"),
+				("OTHER_NAME", "other"),
+				("APP_NAME", "app"),
+			]),
+			none,
+		);
+
+		assert_eq!(apply(set), json!({"name": "app"}));
+	}
+
+	#[test]
+	fn fails_on_empty_keys() {
+		let set = Set::env("APP_", vars(&[("APP_DB____HOST", "db")]), none);
+
+		assert_matches!(
+			set,
+			Err(Error::InvalidEnv(name, reason))
+				if name == "APP_DB____HOST" && reason.contains("empty key")
+		);
+	}
+
+	#[test]
+	fn goes_between_inputs_and_sets() {
+		// the order the compile command applies them in
+		let mut target = json!({"replicas": 1, "image": {"tag": "1.0"}, "debug": false});
+		let env = Set::env(
+			"APP_",
+			vars(&[("APP_REPLICAS", "2"), ("APP_IMAGE__TAG", "2.0")]),
+			none,
+		);
+		let set = Set::from_str("replicas=3");
+
+		env.unwrap().apply(&mut target, none);
+		set.unwrap().apply(&mut target, none);
+
+		assert_eq!(
+			target,
+			json!({"replicas": 3, "image": {"tag": "2.0"}, "debug": false})
+		);
+	}
+}
+
+mod input {
+	use super::*;
+
+	use kct_cli::Input;
+	use kct_helper::json::{layer, Strategy};
+	use kct_testing::dir::TempDir;
+
+	/// Reads the contents from a file with the name, returning its documents
+	fn read(name: &str, contents: &str) -> (Result<Vec<Value>, Error>, TempDir) {
+		let dir = dir::tmp();
+		let path = dir.path().join(name);
+		fs::write(&path, contents).unwrap();
+
+		let documents = Input::from_str(&path.display().to_string()).map(|input| match input {
+			Input::Values(documents) => documents,
+			Input::Jsonnet(path) => panic!("expected values, got {}", path.display()),
+		});
+
+		(documents, dir)
+	}
+
+	#[test]
+	fn trusts_extensions() {
+		let (json, _dir) = read("values.json", "a: 1");
+		assert_matches!(json, Err(Error::Syntax { .. }));
+
+		let (yaml, _dir) = read("values.yaml", "{}");
+		assert_eq!(yaml.unwrap(), vec![json!({})]);
+
+		let (yml, _dir) = read("values.yml", "[]");
+		assert_eq!(yml.unwrap(), vec![json!([])]);
+	}
+
+	#[test]
+	fn looks_at_contents_otherwise() {
+		let (json, _dir) = read("values", "  {\"a\": 1}");
+		assert_eq!(json.unwrap(), vec![json!({"a": 1})]);
+
+		let (yaml, _dir) = read("values", "a: 1");
+		assert_eq!(yaml.unwrap(), vec![json!({"a": 1})]);
+	}
+
+	#[test]
+	fn keeps_yaml_documents_apart() {
+		let contents = "a: 1\nb: {c: 1}\n---\n---\nb: {d: 2}\na: 2\n";
+
+		let (documents, _dir) = read("values.yaml", contents);
+
+		assert_eq!(
+			documents.unwrap(),
+			vec![
+				json!({"a": 1, "b": {"c": 1}}),
+				json!({"b": {"d": 2}, "a": 2})
+			]
+		);
+	}
+
+	#[test]
+	fn layers_yaml_documents_like_inputs() {
+		let contents = "ports: [80]\nname: app\n---\nports: [443]\nname: null\n";
+		let (documents, _dir) = read("values.yaml", contents);
+
+		let mut input = json!({});
+		for document in documents.unwrap() {
+			layer(&mut input, &document, &|_path| Strategy::Append);
+		}
+
+		assert_eq!(input, json!({"ports": [80, 443]}));
+	}
+
+	#[test]
+	fn locates_json_errors() {
+		let (parsed, _dir) = read("values.json", "{\n  \"a\": }");
+
+		assert_matches!(parsed, Err(Error::Syntax { file, line, column, reason }) => {
+			assert!(file.ends_with("values.json"));
+			assert_eq!((line, column), (2, 8));
+			assert_eq!(reason, "expected value");
+		});
+	}
+
+	#[test]
+	fn locates_yaml_errors() {
+		let (parsed, _dir) = read("values.yaml", "a: 1\nb: [1\n");
+
+		assert_matches!(parsed, Err(Error::Syntax { file, line, reason, .. }) => {
+			assert!(file.ends_with("values.yaml"));
+			assert_eq!(line, 3);
+			assert!(!reason.contains(" at line "));
+		});
+	}
+}
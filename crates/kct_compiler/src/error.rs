@@ -1,3 +1,5 @@
+use crate::validator::Violations;
+
 use kct_jsonnet::Error as JsonnetError;
 use thiserror::Error;
 
@@ -9,8 +11,8 @@ pub enum Error {
 	NoValidator,
 	#[error("No input was provided")]
 	NoInput,
-	#[error("The input provided is invalid: {0}")]
-	InvalidInput(String),
+	#[error("The input provided is invalid:\n{0}")]
+	InvalidInput(Violations),
 	#[error("Your template couldn't be parsed as JSON")]
 	InvalidOutput,
 	#[error("Your context is invalid")]
@@ -11,7 +11,7 @@ use self::property::{Name, Prop};
 pub use self::context::Context;
 pub use self::error::Error;
 pub use self::target::{Target, TargetBuilder};
-pub use self::validator::Validator;
+pub use self::validator::{Validator, Violation, Violations};
 
 use std::collections::HashMap;
 
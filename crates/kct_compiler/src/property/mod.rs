@@ -12,6 +12,8 @@ pub use kct_jsonnet::property::{Callback, Function, Property};
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
 pub enum Name {
 	Files,
+	Generate,
+	Generated,
 	Include,
 	Input,
 	Package,
@@ -19,10 +21,10 @@ pub enum Name {
 }
 
 impl Name {
-	pub fn all() -> [Name; 5] {
+	pub fn all() -> [Name; 7] {
 		use Name::*;
 
-		[Files, Include, Input, Package, Release]
+		[Files, Generate, Generated, Include, Input, Package, Release]
 	}
 
 	pub fn as_str(&self) -> &str {
@@ -30,6 +32,8 @@ impl Name {
 
 		match self {
 			Files => "files",
+			Generate => "generate",
+			Generated => "generated",
 			Include => "include",
 			Package => "package",
 			Release => "release",
@@ -1,7 +1,38 @@
+use std::fmt;
+
 use serde_json::Value;
 
-pub trait Predicate: Fn(&Value) -> Result<(), String> {}
-impl<T: Fn(&Value) -> Result<(), String>> Predicate for T {}
+pub trait Predicate: Fn(&Value) -> Result<(), Violations> {}
+impl<T: Fn(&Value) -> Result<(), Violations>> Predicate for T {}
+
+/// Part of the input that fails a check, located by its JSON pointer
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Violation {
+	pub pointer: String,
+	pub keyword: String,
+	pub message: String,
+}
+
+impl fmt::Display for Violation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let pointer = if self.pointer.is_empty() {
+			"/"
+		} else {
+			&self.pointer
+		};
+
+		write!(f, "{pointer}: {} ({})", self.message, self.keyword)
+	}
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Violations(pub Vec<Violation>);
+
+impl fmt::Display for Violations {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0.iter().try_for_each(|v| writeln!(f, "\t- {v}"))
+	}
+}
 
 pub struct Validator {
 	predicate: Box<dyn Predicate>,
@@ -12,7 +43,7 @@ impl Validator {
 		Self { predicate }
 	}
 
-	pub fn run(&self, value: &Value) -> Result<(), String> {
+	pub fn run(&self, value: &Value) -> Result<(), Violations> {
 		(self.predicate)(value)
 	}
 }
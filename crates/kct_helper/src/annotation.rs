@@ -0,0 +1,2 @@
+/// Annotation holding the name given to a generated object before the hash was appended
+pub const GENERATED_FROM: &str = "kct.io/generated-from";
@@ -0,0 +1,628 @@
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::str::FromStr;
+
+use aes_gcm::aead::consts::U32;
+use aes_gcm::aead::rand_core::RngCore;
+use aes_gcm::aead::{Aead, KeyInit, OsRng, Payload};
+use aes_gcm::aes::Aes256;
+use aes_gcm::{AesGcm, Nonce};
+use age::armor::{ArmoredReader, ArmoredWriter, Format};
+use age::{x25519, Decryptor, Encryptor, IdentityFile, IdentityFileEntry};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{SecondsFormat, Utc};
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde_json::{json, Map, Number, Value};
+use sha2::{Digest, Sha512};
+use thiserror::Error;
+
+/// Variable holding the age keys, following SOPS
+pub const KEY_VAR: &str = "SOPS_AGE_KEY";
+
+/// Variable pointing to the file with the age keys, following SOPS
+pub const KEY_FILE_VAR: &str = "SOPS_AGE_KEY_FILE";
+
+const AGE_HEADER: &str = "age-encryption.org/v1";
+const AGE_ARMOR: &str = "-----BEGIN AGE ENCRYPTED FILE-----";
+
+/// Metadata SOPS adds to the documents it encrypts
+const SOPS_FIELD: &str = "sops";
+
+/// Version of SOPS whose format the encrypted documents follow
+const SOPS_VERSION: &str = "3.7.3";
+
+/// Size of the authentication tag SOPS keeps apart from the encrypted data
+const TAG_SIZE: usize = 16;
+
+/// SOPS uses AES-GCM with 256 bits nonces instead of the usual 96
+type Cipher = AesGcm<Aes256, U32>;
+
+#[derive(Error, Debug)]
+pub enum Error {
+	#[error("No age keys found, provide them with {KEY_VAR} or {KEY_FILE_VAR}")]
+	NoKeys,
+	#[error("Invalid age keys: {0}")]
+	InvalidKeys(String),
+	#[error("Unable to decrypt: {0}")]
+	Decryption(String),
+	#[error("Invalid age recipient: {0}")]
+	InvalidRecipient(String),
+	#[error("Unable to encrypt: {0}")]
+	Encryption(String),
+}
+
+/// Age identities able to decrypt the files encrypted to their recipients
+pub struct Keys(Vec<x25519::Identity>);
+
+impl Keys {
+	/// Reads the keys from `SOPS_AGE_KEY` or from the file at `SOPS_AGE_KEY_FILE`
+	pub fn from_env() -> Result<Self, Error> {
+		let contents = match (env::var(KEY_VAR), env::var(KEY_FILE_VAR)) {
+			(Ok(keys), _) => keys,
+			(_, Ok(path)) => fs::read_to_string(&path)
+				.map_err(|err| Error::InvalidKeys(format!("{path}: {err}")))?,
+			_ => return Err(Error::NoKeys),
+		};
+
+		Self::parse(&contents)
+	}
+
+	/// Parses the keys in the format of `age-keygen`, one per line with comments allowed
+	pub fn parse(contents: &str) -> Result<Self, Error> {
+		let file = IdentityFile::from_buffer(contents.as_bytes())
+			.map_err(|err| Error::InvalidKeys(err.to_string()))?;
+
+		let identities: Vec<x25519::Identity> = file
+			.into_identities()
+			.into_iter()
+			.map(|entry| match entry {
+				IdentityFileEntry::Native(identity) => identity,
+			})
+			.collect();
+
+		if identities.is_empty() {
+			Err(Error::NoKeys)
+		} else {
+			Ok(Keys(identities))
+		}
+	}
+
+	fn identities(&self) -> impl Iterator<Item = &dyn age::Identity> {
+		self.0.iter().map(|identity| identity as &dyn age::Identity)
+	}
+}
+
+/// Age public keys the files are encrypted to
+#[derive(Clone)]
+pub struct Recipients(Vec<x25519::Recipient>);
+
+impl Recipients {
+	pub fn parse(recipients: &[String]) -> Result<Self, Error> {
+		let recipients = recipients
+			.iter()
+			.map(|r| x25519::Recipient::from_str(r).map_err(|_| Error::InvalidRecipient(r.clone())))
+			.collect::<Result<Vec<_>, _>>()?;
+
+		Ok(Recipients(recipients))
+	}
+}
+
+/// Whether the contents are an age file, either binary or armored
+pub fn is_age(contents: &[u8]) -> bool {
+	let start = contents
+		.iter()
+		.position(|b| !b.is_ascii_whitespace())
+		.unwrap_or(contents.len());
+	let contents = &contents[start..];
+
+	contents.starts_with(AGE_HEADER.as_bytes()) || contents.starts_with(AGE_ARMOR.as_bytes())
+}
+
+pub fn decrypt_age(contents: &[u8], keys: &Keys) -> Result<Vec<u8>, Error> {
+	let failed = |err: &dyn std::fmt::Display| Error::Decryption(err.to_string());
+
+	let decryptor = match Decryptor::new(ArmoredReader::new(contents)).map_err(|e| failed(&e))? {
+		Decryptor::Recipients(decryptor) => decryptor,
+		Decryptor::Passphrase(_) => {
+			return Err(Error::Decryption(String::from(
+				"files encrypted with a passphrase aren't supported",
+			)))
+		}
+	};
+
+	let mut plain = vec![];
+	decryptor
+		.decrypt(keys.identities())
+		.map_err(|e| failed(&e))?
+		.read_to_end(&mut plain)
+		.map_err(|e| failed(&e))?;
+
+	Ok(plain)
+}
+
+/// Encrypts the contents into an armored age file
+pub fn encrypt_age(plain: &[u8], recipients: &Recipients) -> Result<Vec<u8>, Error> {
+	let failed = |err: &dyn std::fmt::Display| Error::Encryption(err.to_string());
+	let recipients = recipients
+		.0
+		.iter()
+		.map(|r| Box::new(r.clone()) as Box<dyn age::Recipient + Send>)
+		.collect();
+
+	let encryptor = Encryptor::with_recipients(recipients)
+		.ok_or_else(|| Error::Encryption(String::from("no recipients given")))?;
+
+	let mut encrypted = vec![];
+	let armor =
+		ArmoredWriter::wrap_output(&mut encrypted, Format::AsciiArmor).map_err(|e| failed(&e))?;
+	let mut writer = encryptor.wrap_output(armor).map_err(|e| failed(&e))?;
+	writer.write_all(plain).map_err(|e| failed(&e))?;
+	writer
+		.finish()
+		.and_then(|armor| armor.finish())
+		.map_err(|e| failed(&e))?;
+
+	Ok(encrypted)
+}
+
+/// Document with its fields in the order they were written, which the MAC of SOPS depends on
+///
+/// Values don't keep the order of their fields, so encrypted documents are read again into this
+/// before being decrypted.
+pub struct Ordered(Tree);
+
+enum Tree {
+	Map(Vec<(String, Tree)>),
+	List(Vec<Tree>),
+	Scalar(Value),
+}
+
+impl Tree {
+	fn into_value(self) -> Value {
+		match self {
+			Tree::Map(fields) => Value::Object(
+				fields
+					.into_iter()
+					.map(|(key, value)| (key, value.into_value()))
+					.collect(),
+			),
+			Tree::List(items) => Value::Array(items.into_iter().map(Tree::into_value).collect()),
+			Tree::Scalar(value) => value,
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for Ordered {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_any(TreeVisitor).map(Ordered)
+	}
+}
+
+impl<'de> Deserialize<'de> for Tree {
+	fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_any(TreeVisitor)
+	}
+}
+
+struct TreeVisitor;
+
+impl<'de> Visitor<'de> for TreeVisitor {
+	type Value = Tree;
+
+	fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "a JSON or YAML document")
+	}
+
+	fn visit_bool<E>(self, v: bool) -> Result<Tree, E> {
+		Ok(Tree::Scalar(Value::Bool(v)))
+	}
+
+	fn visit_i64<E>(self, v: i64) -> Result<Tree, E> {
+		Ok(Tree::Scalar(Value::from(v)))
+	}
+
+	fn visit_u64<E>(self, v: u64) -> Result<Tree, E> {
+		Ok(Tree::Scalar(Value::from(v)))
+	}
+
+	fn visit_f64<E>(self, v: f64) -> Result<Tree, E> {
+		Ok(Tree::Scalar(Value::from(v)))
+	}
+
+	fn visit_str<E>(self, v: &str) -> Result<Tree, E> {
+		Ok(Tree::Scalar(Value::from(v)))
+	}
+
+	fn visit_string<E>(self, v: String) -> Result<Tree, E> {
+		Ok(Tree::Scalar(Value::String(v)))
+	}
+
+	fn visit_unit<E>(self) -> Result<Tree, E> {
+		Ok(Tree::Scalar(Value::Null))
+	}
+
+	fn visit_none<E>(self) -> Result<Tree, E> {
+		Ok(Tree::Scalar(Value::Null))
+	}
+
+	fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Tree, D::Error> {
+		Tree::deserialize(deserializer)
+	}
+
+	fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Tree, A::Error> {
+		let mut items = vec![];
+		while let Some(item) = seq.next_element()? {
+			items.push(item);
+		}
+
+		Ok(Tree::List(items))
+	}
+
+	fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Tree, A::Error> {
+		let mut fields = vec![];
+		// YAML keys can be other scalars, which are written as the text they were given in
+		while let Some((key, value)) = map.next_entry::<Value, Tree>()? {
+			let key = match key {
+				Value::String(key) => key,
+				other => other.to_string(),
+			};
+
+			fields.push((key, value));
+		}
+
+		Ok(Tree::Map(fields))
+	}
+}
+
+/// Whether the document was encrypted by SOPS, with its metadata holding an encrypted MAC and at
+/// least one of its values encrypted
+pub fn is_sops(document: &Value) -> bool {
+	fn encrypted(value: &Value) -> bool {
+		match value {
+			Value::String(text) => text.starts_with("ENC["),
+			Value::Array(items) => items.iter().any(encrypted),
+			Value::Object(map) => map.values().any(encrypted),
+			_ => false,
+		}
+	}
+
+	let mac = document
+		.get(SOPS_FIELD)
+		.and_then(|metadata| metadata.get("mac"));
+
+	match (document.as_object(), mac) {
+		(Some(map), Some(mac)) => {
+			encrypted(mac)
+				&& map
+					.iter()
+					.any(|(key, value)| key != SOPS_FIELD && encrypted(value))
+		}
+		_ => false,
+	}
+}
+
+/// Decrypts the values of a SOPS document with the data key encrypted to its age recipients
+///
+/// Each value is bound to its path, so they can't be moved around without failing, and the MAC
+/// over the values, encrypted or not, has to match so no value is changed, added, or removed.
+pub fn decrypt_sops(document: Ordered, keys: &Keys) -> Result<Value, Error> {
+	let no_metadata = || Error::Decryption(String::from("the document has no SOPS metadata"));
+
+	let mut fields = match document.0 {
+		Tree::Map(fields) => fields,
+		_ => return Err(no_metadata()),
+	};
+	let position = fields
+		.iter()
+		.position(|(key, _)| key == SOPS_FIELD)
+		.ok_or_else(no_metadata)?;
+	let (_, metadata) = fields.remove(position);
+	let metadata = metadata.into_value();
+
+	let key = data_key(&metadata, keys)?;
+	let cipher = Cipher::new_from_slice(&key)
+		.map_err(|_err| Error::Decryption(String::from("the data key is invalid")))?;
+
+	let only_encrypted = metadata
+		.get("mac_only_encrypted")
+		.and_then(|m| m.as_bool())
+		.unwrap_or(false);
+	let mut hash = Sha512::new();
+	let document = decrypt_tree(
+		Tree::Map(fields),
+		&cipher,
+		&mut hash,
+		only_encrypted,
+		&mut vec![],
+	)?;
+
+	let missing = || Error::Decryption(String::from("the document has no MAC"));
+	let mac = metadata
+		.get("mac")
+		.and_then(|m| m.as_str())
+		.ok_or_else(missing)?;
+	let modified = metadata
+		.get("lastmodified")
+		.and_then(|m| m.as_str())
+		.ok_or_else(missing)?;
+
+	let expected = decrypt_value(mac, &cipher, modified, "sops.mac")?;
+	if expected.as_str() != Some(format!("{:X}", hash.finalize()).as_str()) {
+		return Err(Error::Decryption(String::from(
+			"the MAC doesn't match, the document was changed",
+		)));
+	}
+
+	Ok(document)
+}
+
+/// Encrypts the values under the fields of the document as SOPS would with `--encrypted-regex`
+///
+/// The data key is encrypted to the age recipients, and a MAC over every value, encrypted or
+/// not, lets SOPS detect changes to the document.
+pub fn encrypt_sops(
+	mut document: Value,
+	recipients: &Recipients,
+	fields: &[&str],
+) -> Result<Value, Error> {
+	let mut key = [0u8; 32];
+	OsRng.fill_bytes(&mut key);
+	let cipher = Cipher::new_from_slice(&key)
+		.map_err(|_err| Error::Encryption(String::from("the data key is invalid")))?;
+
+	let mut hash = Sha512::new();
+	encrypt_tree(
+		&mut document,
+		&cipher,
+		fields,
+		&mut hash,
+		&mut vec![],
+		false,
+	)?;
+
+	let modified = Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true);
+	let mac = format!("{:X}", hash.finalize());
+	let mac = encrypt_value(&Value::String(mac), &cipher, &modified)?;
+
+	let enc = encrypt_age(&key, recipients)?;
+	let age: Vec<Value> = recipients
+		.0
+		.iter()
+		.map(|recipient| {
+			json!({
+				"recipient": recipient.to_string(),
+				"enc": String::from_utf8_lossy(&enc),
+			})
+		})
+		.collect();
+
+	let metadata = json!({
+		"age": age,
+		"lastmodified": modified,
+		"mac": mac,
+		"encrypted_regex": format!("^({})$", fields.join("|")),
+		"version": SOPS_VERSION,
+	});
+
+	match &mut document {
+		Value::Object(map) => map.insert(String::from(SOPS_FIELD), metadata),
+		_ => {
+			return Err(Error::Encryption(String::from(
+				"the document isn't an object",
+			)))
+		}
+	};
+
+	Ok(document)
+}
+
+fn encrypt_tree(
+	value: &mut Value,
+	cipher: &Cipher,
+	fields: &[&str],
+	hash: &mut Sha512,
+	path: &mut Vec<String>,
+	selected: bool,
+) -> Result<(), Error> {
+	match value {
+		Value::Object(map) => {
+			for (key, value) in map.iter_mut() {
+				let selected = selected || fields.contains(&key.as_str());
+
+				path.push(key.clone());
+				encrypt_tree(value, cipher, fields, hash, path, selected)?;
+				path.pop();
+			}
+		}
+		Value::Array(items) => {
+			for item in items {
+				encrypt_tree(item, cipher, fields, hash, path, selected)?;
+			}
+		}
+		Value::Null => (),
+		scalar => {
+			hash.update(hashed(scalar).as_bytes());
+
+			let empty = scalar.as_str().is_some_and(str::is_empty);
+			if selected && !empty {
+				let aad = format!("{}:", path.join(":"));
+				*scalar = Value::String(encrypt_value(scalar, cipher, &aad)?);
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Text of a scalar as SOPS writes it before encrypting
+fn plain(value: &Value) -> String {
+	match value {
+		Value::String(text) => text.clone(),
+		Value::Number(n) => match n.as_f64() {
+			Some(float) if n.is_f64() => float.to_string(),
+			_ => n.to_string(),
+		},
+		other => other.to_string(),
+	}
+}
+
+/// Text of a scalar as SOPS adds it to the MAC, which differs from the encrypted one for booleans
+fn hashed(value: &Value) -> String {
+	match value {
+		Value::Bool(true) => String::from("True"),
+		Value::Bool(false) => String::from("False"),
+		other => plain(other),
+	}
+}
+
+fn encrypt_value(value: &Value, cipher: &Cipher, aad: &str) -> Result<String, Error> {
+	let kind = match value {
+		Value::String(_) => "str",
+		Value::Bool(_) => "bool",
+		Value::Number(n) if n.is_f64() => "float",
+		_ => "int",
+	};
+
+	let mut iv = [0u8; 32];
+	OsRng.fill_bytes(&mut iv);
+
+	let mut data = cipher
+		.encrypt(
+			Nonce::from_slice(&iv),
+			Payload {
+				msg: plain(value).as_bytes(),
+				aad: aad.as_bytes(),
+			},
+		)
+		.map_err(|_err| Error::Encryption(String::from("the value couldn't be encrypted")))?;
+	let tag = data.split_off(data.len() - TAG_SIZE);
+
+	Ok(format!(
+		"ENC[AES256_GCM,data:{},iv:{},tag:{},type:{kind}]",
+		STANDARD.encode(data),
+		STANDARD.encode(iv),
+		STANDARD.encode(tag)
+	))
+}
+
+fn data_key(metadata: &Value, keys: &Keys) -> Result<Vec<u8>, Error> {
+	let recipients = metadata
+		.get("age")
+		.and_then(|a| a.as_array())
+		.ok_or_else(|| Error::Decryption(String::from("the document has no age recipients")))?;
+
+	recipients
+		.iter()
+		.filter_map(|recipient| recipient.get("enc").and_then(|e| e.as_str()))
+		.find_map(|enc| decrypt_age(enc.as_bytes(), keys).ok())
+		.ok_or_else(|| {
+			Error::Decryption(String::from(
+				"none of the keys match the age recipients of the document",
+			))
+		})
+}
+
+/// Decrypts the values while hashing them, in the order of the document as SOPS did, for the MAC
+fn decrypt_tree(
+	tree: Tree,
+	cipher: &Cipher,
+	hash: &mut Sha512,
+	only_encrypted: bool,
+	path: &mut Vec<String>,
+) -> Result<Value, Error> {
+	match tree {
+		Tree::Map(fields) => {
+			let mut map = Map::new();
+			for (key, value) in fields {
+				path.push(key.clone());
+				map.insert(
+					key,
+					decrypt_tree(value, cipher, hash, only_encrypted, path)?,
+				);
+				path.pop();
+			}
+
+			Ok(Value::Object(map))
+		}
+		// elements of lists share the path of the list itself
+		Tree::List(items) => items
+			.into_iter()
+			.map(|item| decrypt_tree(item, cipher, hash, only_encrypted, path))
+			.collect::<Result<Vec<Value>, Error>>()
+			.map(Value::Array),
+		Tree::Scalar(Value::Null) => Ok(Value::Null),
+		Tree::Scalar(Value::String(text)) if text.starts_with("ENC[") => {
+			let aad = format!("{}:", path.join(":"));
+			let decrypted = decrypt_value(&text, cipher, &aad, &path.join("."))?;
+
+			hash.update(hashed(&decrypted).as_bytes());
+			Ok(decrypted)
+		}
+		Tree::Scalar(scalar) => {
+			if !only_encrypted {
+				hash.update(hashed(&scalar).as_bytes());
+			}
+
+			Ok(scalar)
+		}
+	}
+}
+
+/// Decrypts a value in the `ENC[AES256_GCM,data:...,iv:...,tag:...,type:...]` format
+fn decrypt_value(text: &str, cipher: &Cipher, aad: &str, at: &str) -> Result<Value, Error> {
+	let invalid = |reason: &str| Error::Decryption(format!("{reason} at {at}"));
+
+	let fields = text
+		.strip_prefix("ENC[AES256_GCM,")
+		.and_then(|t| t.strip_suffix(']'))
+		.ok_or_else(|| invalid("unknown encryption"))?;
+
+	let mut data = None;
+	let mut iv = None;
+	let mut tag = None;
+	let mut kind = None;
+	for field in fields.split(',') {
+		let decode = |value: &str| {
+			STANDARD
+				.decode(value)
+				.map_err(|_err| invalid("bad encoding"))
+		};
+
+		match field.split_once(':') {
+			Some(("data", value)) => data = Some(decode(value)?),
+			Some(("iv", value)) => iv = Some(decode(value)?),
+			Some(("tag", value)) => tag = Some(decode(value)?),
+			Some(("type", value)) => kind = Some(value),
+			_ => return Err(invalid("unknown field")),
+		}
+	}
+
+	let (mut data, iv, tag, kind) = match (data, iv, tag, kind) {
+		(Some(data), Some(iv), Some(tag), Some(kind)) if iv.len() == 32 => (data, iv, tag, kind),
+		_ => return Err(invalid("missing fields")),
+	};
+
+	data.extend(tag);
+	let plain = cipher
+		.decrypt(
+			Nonce::from_slice(&iv),
+			Payload {
+				msg: &data,
+				aad: aad.as_bytes(),
+			},
+		)
+		.map_err(|_err| invalid("wrong key or tampered value"))?;
+	let plain = String::from_utf8_lossy(&plain).to_string();
+
+	let number = |n: Option<Number>| n.map(Value::Number).ok_or_else(|| invalid("bad number"));
+	match kind {
+		"str" | "bytes" | "comment" => Ok(Value::String(plain)),
+		"int" => number(plain.parse::<i64>().ok().map(Number::from)),
+		"float" => number(plain.parse::<f64>().ok().and_then(Number::from_f64)),
+		"bool" => Ok(Value::Bool(plain.eq_ignore_ascii_case("true"))),
+		_ => Err(invalid("unknown type")),
+	}
+}
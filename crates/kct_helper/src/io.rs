@@ -63,6 +63,27 @@ impl Location {
 		}
 	}
 
+	/// Reads the contents without requiring them to be text, as encrypted files are binary
+	pub fn read_bytes(self) -> Result<Vec<u8>, Error> {
+		match self {
+			Self::Standard => {
+				let mut contents = vec![];
+				io::stdin()
+					.read_to_end(&mut contents)
+					.map_err(|_err| Error::UnableToRead)?;
+
+				Ok(contents)
+			}
+			Self::Path(path) => {
+				if !path.is_file() {
+					return Err(Error::NotFile);
+				}
+
+				fs::read(&path).map_err(|_err| Error::UnableToRead)
+			}
+		}
+	}
+
 	pub fn write(self, documents: Vec<(PathBuf, String)>) -> Result<(), Error> {
 		match self {
 			Self::Standard => {
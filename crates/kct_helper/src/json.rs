@@ -1,5 +1,43 @@
+use std::fmt;
+use std::str::FromStr;
+
 use serde_json::{json, Map, Value};
 
+/// How an array from a later layer combines with the one it's laid over
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum Strategy {
+	#[default]
+	Replace,
+	Append,
+	/// Items with the same value at the key are layered, the others are appended
+	MergeBy(String),
+}
+
+impl FromStr for Strategy {
+	type Err = String;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s.split_once(':') {
+			None if s == "replace" => Ok(Strategy::Replace),
+			None if s == "append" => Ok(Strategy::Append),
+			Some(("merge", key)) if !key.is_empty() => Ok(Strategy::MergeBy(key.to_string())),
+			_ => Err(format!(
+				"Unknown strategy \"{s}\", use replace, append, or merge:<key>"
+			)),
+		}
+	}
+}
+
+impl fmt::Display for Strategy {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Strategy::Replace => write!(f, "replace"),
+			Strategy::Append => write!(f, "append"),
+			Strategy::MergeBy(key) => write!(f, "merge:{key}"),
+		}
+	}
+}
+
 pub fn merge(left: &mut Value, right: &Value) {
 	match (left, right) {
 		(&mut Value::Object(ref mut left), Value::Object(right)) => {
@@ -13,6 +51,86 @@ pub fn merge(left: &mut Value, right: &Value) {
 	}
 }
 
+/// Lays right over left as a JSON Merge Patch (RFC 7386), where nulls remove keys, except that
+/// arrays are combined with the strategy given for their path
+pub fn layer<F>(left: &mut Value, right: &Value, strategy: &F)
+where
+	F: Fn(&[String]) -> Strategy,
+{
+	layer_at(left, right, strategy, &mut vec![]);
+}
+
+fn layer_at<F>(left: &mut Value, right: &Value, strategy: &F, path: &mut Vec<String>)
+where
+	F: Fn(&[String]) -> Strategy,
+{
+	match (left, right) {
+		(left, Value::Object(right)) => {
+			if !left.is_object() {
+				*left = Value::Object(Map::new());
+			}
+
+			let left = left.as_object_mut().unwrap();
+			for (key, value) in right {
+				if value.is_null() {
+					left.remove(key);
+					continue;
+				}
+
+				path.push(key.clone());
+				layer_at(
+					left.entry(key.clone()).or_insert(Value::Null),
+					value,
+					strategy,
+					path,
+				);
+				path.pop();
+			}
+		}
+		(Value::Array(left), Value::Array(right)) => match strategy(path) {
+			Strategy::Replace => *left = right.clone(),
+			Strategy::Append => {
+				for item in right {
+					push(left, item, strategy, path);
+				}
+			}
+			Strategy::MergeBy(key) => {
+				for item in right {
+					let found = item.get(&key).filter(|id| !id.is_null()).and_then(|id| {
+						left.iter()
+							.position(|existing| existing.get(&key) == Some(id))
+					});
+
+					match found {
+						Some(index) => {
+							path.push(index.to_string());
+							layer_at(&mut left[index], item, strategy, path);
+							path.pop();
+						}
+						None => push(left, item, strategy, path),
+					}
+				}
+			}
+		},
+		(left, right) => *left = right.clone(),
+	}
+}
+
+/// Adds the item to the end of the array as if it were layered on nothing, so the nulls within it
+/// are removed like in any other patch
+fn push<F>(left: &mut Vec<Value>, item: &Value, strategy: &F, path: &mut Vec<String>)
+where
+	F: Fn(&[String]) -> Strategy,
+{
+	let mut pushed = Value::Null;
+
+	path.push(left.len().to_string());
+	layer_at(&mut pushed, item, strategy, path);
+	path.pop();
+
+	left.push(pushed);
+}
+
 pub fn get_in<'a>(obj: &'a Value, path: &[&str]) -> Option<&'a Value> {
 	if !obj.is_object() {
 		return None;
@@ -97,7 +215,7 @@ fn build_path(path: &[&str], value: Value) -> Value {
 mod test {
 	use serde_json::{json, Value};
 
-	use super::{get_in, merge, set_in};
+	use super::{get_in, layer, merge, set_in, Strategy};
 
 	mod get_path {
 		use super::*;
@@ -210,4 +328,109 @@ mod test {
 			assert_eq!(left, expected)
 		}
 	}
+	mod layer {
+		use super::*;
+
+		fn replace(_path: &[String]) -> Strategy {
+			Strategy::Replace
+		}
+
+		#[test]
+		fn removes_keys_with_null() {
+			let mut left = json!({"a": 1, "b": {"c": 1, "d": 2}});
+			let right = json!({"a": null, "b": {"c": null, "e": 3}});
+
+			layer(&mut left, &right, &replace);
+
+			assert_eq!(left, json!({"b": {"d": 2, "e": 3}}));
+		}
+
+		#[test]
+		fn replaces_arrays_by_default() {
+			let mut left = json!({"a": [1, 2]});
+
+			layer(&mut left, &json!({"a": [3]}), &replace);
+
+			assert_eq!(left, json!({"a": [3]}));
+		}
+
+		#[test]
+		fn uses_the_strategy_of_the_path() {
+			let strategy = |path: &[String]| match path.join(".").as_str() {
+				"tags" => Strategy::Append,
+				"containers" | "containers.0.env" => Strategy::MergeBy(String::from("name")),
+				_ => Strategy::Replace,
+			};
+
+			let mut left = json!({
+				"tags": ["a"],
+				"containers": [
+					{"name": "app", "image": "app:1", "env": [{"name": "A", "value": "1"}]},
+					{"name": "sidecar", "image": "proxy:1"}
+				]
+			});
+			let right = json!({
+				"tags": ["b"],
+				"containers": [
+					{"name": "app", "image": "app:2", "env": [{"name": "B", "value": "2"}]},
+					{"name": "init", "image": "init:1"}
+				]
+			});
+
+			layer(&mut left, &right, &strategy);
+
+			assert_eq!(
+				left,
+				json!({
+					"tags": ["a", "b"],
+					"containers": [
+						{
+							"name": "app",
+							"image": "app:2",
+							"env": [{"name": "A", "value": "1"}, {"name": "B", "value": "2"}]
+						},
+						{"name": "sidecar", "image": "proxy:1"},
+						{"name": "init", "image": "init:1"}
+					]
+				})
+			);
+		}
+
+		#[test]
+		fn removes_nulls_from_pushed_items() {
+			let strategy = |path: &[String]| match path.join(".").as_str() {
+				"tags" => Strategy::Append,
+				"containers" => Strategy::MergeBy(String::from("name")),
+				_ => Strategy::Replace,
+			};
+
+			let mut left = json!({"tags": [], "containers": []});
+			let right = json!({
+				"tags": [{"name": "a", "color": null}, null],
+				"containers": [{"name": "init", "args": null, "env": {"A": null, "B": "2"}}]
+			});
+
+			layer(&mut left, &right, &strategy);
+
+			assert_eq!(
+				left,
+				json!({
+					"tags": [{"name": "a"}, null],
+					"containers": [{"name": "init", "env": {"B": "2"}}]
+				})
+			);
+		}
+
+		#[test]
+		fn parses_strategies() {
+			assert_eq!("replace".parse(), Ok(Strategy::Replace));
+			assert_eq!("append".parse(), Ok(Strategy::Append));
+			assert_eq!(
+				"merge:name".parse(),
+				Ok(Strategy::MergeBy(String::from("name")))
+			);
+			assert!("merge:".parse::<Strategy>().is_err());
+			assert!("prepend".parse::<Strategy>().is_err());
+		}
+	}
 }
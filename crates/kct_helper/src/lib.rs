@@ -1,2 +1,4 @@
+pub mod annotation;
+pub mod crypt;
 pub mod io;
 pub mod json;
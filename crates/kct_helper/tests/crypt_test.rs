@@ -0,0 +1,193 @@
+use std::fs;
+use std::path::PathBuf;
+
+use age::secrecy::ExposeSecret;
+use age::x25519;
+use kct_helper::crypt::{
+	decrypt_age, decrypt_sops, encrypt_age, encrypt_sops, is_age, is_sops, Keys, Ordered,
+	Recipients,
+};
+use serde_json::{json, Value};
+
+fn keys() -> (Keys, Recipients) {
+	let identity = x25519::Identity::generate();
+	let recipients = Recipients::parse(&[identity.to_public().to_string()]).unwrap();
+	let keys = Keys::parse(identity.to_string().expose_secret()).unwrap();
+
+	(keys, recipients)
+}
+
+fn document() -> Value {
+	json!({
+		"kind": "Secret",
+		"metadata": {"name": "db"},
+		"data": {"host": "postgres", "port": 5432, "replicas": [true], "empty": ""}
+	})
+}
+
+/// Decrypts a document encrypted here, whose values were hashed in the order they're iterated
+fn decrypt(document: Value, keys: &Keys) -> Result<Value, kct_helper::crypt::Error> {
+	let ordered: Ordered = serde_json::from_value(document).unwrap();
+
+	decrypt_sops(ordered, keys)
+}
+
+mod age_files {
+	use super::*;
+
+	#[test]
+	fn decrypts_age_files() {
+		let (keys, recipients) = keys();
+		let encrypted = encrypt_age(b"{\"a\": 1}", &recipients).unwrap();
+
+		assert!(is_age(&encrypted));
+		assert_eq!(decrypt_age(&encrypted, &keys).unwrap(), b"{\"a\": 1}");
+		assert!(!is_age(b"{\"a\": 1}"));
+	}
+}
+
+mod sops {
+	use super::*;
+
+	#[test]
+	fn encrypts_only_the_fields() {
+		let (_, recipients) = keys();
+		let encrypted = encrypt_sops(document(), &recipients, &["data"]).unwrap();
+
+		assert!(is_sops(&encrypted));
+		assert_eq!(encrypted["metadata"], json!({"name": "db"}));
+		assert_eq!(encrypted["data"]["empty"], "");
+		assert!(encrypted["data"]["host"]
+			.as_str()
+			.unwrap()
+			.starts_with("ENC[AES256_GCM,"));
+		assert_eq!(encrypted["sops"]["encrypted_regex"], "^(data)$");
+	}
+
+	#[test]
+	fn decrypts_sops_documents() {
+		let (keys, recipients) = keys();
+		let encrypted = encrypt_sops(document(), &recipients, &["data"]).unwrap();
+
+		assert_eq!(decrypt(encrypted, &keys).unwrap(), document());
+	}
+
+	#[test]
+	fn fails_with_other_keys() {
+		let (_, recipients) = keys();
+		let (others, _) = keys();
+		let encrypted = encrypt_sops(document(), &recipients, &["data"]).unwrap();
+
+		assert!(decrypt(encrypted, &others).is_err());
+	}
+
+	#[test]
+	fn fails_on_moved_values() {
+		let (keys, recipients) = keys();
+		let mut encrypted = encrypt_sops(document(), &recipients, &["data"]).unwrap();
+		encrypted["data"]["user"] = encrypted["data"]["host"].clone();
+
+		assert!(decrypt(encrypted, &keys).is_err());
+	}
+
+	#[test]
+	fn fails_on_changed_values() {
+		let (keys, recipients) = keys();
+		let encrypted = encrypt_sops(document(), &recipients, &["data"]).unwrap();
+
+		let mut value = encrypted.clone();
+		value["data"]["host"] = json!("attacker");
+		let error = decrypt(value, &keys).unwrap_err();
+		assert!(error.to_string().contains("MAC"));
+
+		let mut field = encrypted.clone();
+		field["metadata"]["name"] = json!("other");
+		let error = decrypt(field, &keys).unwrap_err();
+		assert!(error.to_string().contains("MAC"));
+
+		let mut removed = encrypted.clone();
+		removed["data"].as_object_mut().unwrap().remove("host");
+		assert!(decrypt(removed, &keys).is_err());
+
+		let mut unsigned = encrypted;
+		unsigned["sops"].as_object_mut().unwrap().remove("mac");
+		assert!(decrypt(unsigned, &keys).is_err());
+	}
+
+	#[test]
+	fn recognizes_only_encrypted_documents() {
+		let (_, recipients) = keys();
+		let encrypted = encrypt_sops(document(), &recipients, &["data"]).unwrap();
+		assert!(is_sops(&encrypted));
+
+		let mut unsigned = encrypted.clone();
+		unsigned["sops"].as_object_mut().unwrap().remove("mac");
+		assert!(!is_sops(&unsigned));
+
+		let plain = encrypt_sops(document(), &recipients, &["missing"]).unwrap();
+		assert!(!is_sops(&plain));
+
+		let input = json!({"sops": {"mac": "none", "version": "3.7.3"}, "name": "app"});
+		assert!(!is_sops(&input));
+	}
+}
+
+/// Files encrypted by SOPS from `plain.yaml`, along with the age key they were encrypted to
+mod fixtures {
+	use super::*;
+
+	fn fixture(name: &str) -> String {
+		let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "sops", name]
+			.iter()
+			.collect();
+
+		fs::read_to_string(path).unwrap()
+	}
+
+	fn keys() -> Keys {
+		Keys::parse(&fixture("key.txt")).unwrap()
+	}
+
+	fn plain() -> Value {
+		serde_yaml::from_str(&fixture("plain.yaml")).unwrap()
+	}
+
+	#[test]
+	fn decrypts_yaml_files() {
+		let contents = fixture("encrypted.yaml");
+		assert!(is_sops(&serde_yaml::from_str(&contents).unwrap()));
+
+		let ordered: Ordered = serde_yaml::from_str(&contents).unwrap();
+
+		assert_eq!(decrypt_sops(ordered, &keys()).unwrap(), plain());
+	}
+
+	#[test]
+	fn decrypts_json_files() {
+		let contents = fixture("encrypted.json");
+		assert!(is_sops(&serde_json::from_str(&contents).unwrap()));
+
+		let ordered: Ordered = serde_json::from_str(&contents).unwrap();
+
+		assert_eq!(decrypt_sops(ordered, &keys()).unwrap(), plain());
+	}
+
+	#[test]
+	fn fails_on_reordered_fields() {
+		// writing the fields in reverse hashes the values in another order than SOPS did
+		let document: Value = serde_json::from_str(&fixture("encrypted.json")).unwrap();
+		let fields: Vec<String> = document
+			.as_object()
+			.unwrap()
+			.iter()
+			.rev()
+			.map(|(key, value)| format!("{}: {value}", Value::from(key.as_str())))
+			.collect();
+		let reordered = format!("{{{}}}", fields.join(", "));
+
+		let ordered: Ordered = serde_json::from_str(&reordered).unwrap();
+		let error = decrypt_sops(ordered, &keys()).unwrap_err();
+
+		assert!(error.to_string().contains("MAC"));
+	}
+}
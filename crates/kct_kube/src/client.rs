@@ -1,12 +1,10 @@
+use crate::plan::Pass;
 use crate::{Manifest, Tracked};
 
-pub use crate::error::Root as Error;
-
 use std::time::Duration;
 
 use anyhow::Result;
 use async_trait::async_trait;
-use either::Either;
 use futures::TryFutureExt;
 use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition as CRD;
 use kube::api::{Api, DynamicObject as Dynamic, Patch, PatchParams, ResourceExt};
@@ -32,7 +30,7 @@ impl Client {
 	}
 
 	pub async fn apply(&mut self, manifests: Vec<Manifest>) -> Result<()> {
-		let plan = Plan::try_new(manifests)?;
+		let plan = Batch::try_new(manifests)?;
 
 		let ssapply = PatchParams::apply("kct-crds").force();
 		let crds = plan
@@ -59,7 +57,7 @@ impl Client {
 	pub async fn delete(&mut self, mut manifests: Vec<Manifest>) -> Result<()> {
 		manifests.reverse();
 
-		let plan = Plan::try_new(manifests)?;
+		let plan = Batch::try_new(manifests)?;
 
 		let dynamics = plan
 			.dynamics
@@ -87,33 +85,32 @@ impl Client {
 	}
 }
 
-struct Plan {
+struct Batch {
 	crds: Vec<Tracked<CRD>>,
 	dynamics: Vec<Tracked<Dynamic>>,
 }
 
-impl Plan {
+impl Batch {
 	fn try_new(manifests: Vec<Manifest>) -> Result<Self> {
 		let mut crds = vec![];
 		let mut dynamics = vec![];
 
 		for Tracked(path, doc) in manifests {
-			let obj: Dynamic = serde_json::from_value(doc)?;
+			match Pass::from(&doc) {
+				Pass::Crds => {
+					let crd: CRD = serde_json::from_value(doc)?;
+
+					crds.push((path, crd).into())
+				}
+				Pass::Dynamics => {
+					let obj: Dynamic = serde_json::from_value(doc)?;
 
-			match try_crd(obj) {
-				Either::Right(crd) => crds.push((path, crd).into()),
-				Either::Left(obj) => dynamics.push((path, obj).into()),
+					dynamics.push((path, obj).into())
+				}
 			}
 		}
 
-		Ok(Plan { crds, dynamics })
-	}
-}
-
-fn try_crd(obj: Dynamic) -> Either<Dynamic, CRD> {
-	match obj.clone().try_parse() {
-		Ok(crd) => Either::Right(crd),
-		Err(_) => Either::Left(obj),
+		Ok(Batch { crds, dynamics })
 	}
 }
 
@@ -172,10 +169,12 @@ impl Object for Tracked<Dynamic> {
 		))?;
 
 		let client = client.internal.clone();
-		if caps.scope == Scope::Cluster {
-			Ok(Api::all_with(client, &ar))
-		} else {
-			Ok(Api::default_namespaced_with(client, &ar))
+		match (caps.scope, obj.namespace()) {
+			(Scope::Cluster, _) => Ok(Api::all_with(client, &ar)),
+			(Scope::Namespaced, Some(namespace)) => {
+				Ok(Api::namespaced_with(client, &namespace, &ar))
+			}
+			(Scope::Namespaced, None) => Ok(Api::default_namespaced_with(client, &ar)),
 		}
 	}
 }
@@ -0,0 +1,122 @@
+use crate::policy::{Finding, Severity};
+use crate::{Identity, Manifest, Version};
+
+/// Name of the rule reported for deprecated and removed APIs
+pub const RULE: &str = "deprecated-api";
+
+struct Entry {
+	api_version: &'static str,
+	kinds: &'static [&'static str],
+	deprecated: Version,
+	removed: Version,
+	replacement: Option<&'static str>,
+}
+
+macro_rules! table {
+	($(
+		$api_version:literal [$($kind:literal),*]
+		($dmajor:literal, $dminor:literal) => ($rmajor:literal, $rminor:literal)
+		$(: $replacement:literal)?;
+	)*) => {
+		&[$(Entry {
+			api_version: $api_version,
+			kinds: &[$($kind),*],
+			deprecated: Version::new($dmajor, $dminor),
+			removed: Version::new($rmajor, $rminor),
+			replacement: table!(@replacement $($replacement)?),
+		}),*]
+	};
+	(@replacement $replacement:literal) => { Some($replacement) };
+	(@replacement) => { None };
+}
+
+/// APIs served by past or upcoming Kubernetes versions
+///
+/// Kinds are always listed, as a group version can serve new kinds after the old ones are gone,
+/// like `ValidatingAdmissionPolicy` in `admissionregistration.k8s.io/v1beta1`.
+const TABLE: &[Entry] = table! {
+	"extensions/v1beta1" ["Deployment", "DaemonSet", "ReplicaSet"] (1, 8) => (1, 16): "apps/v1";
+	"extensions/v1beta1" ["NetworkPolicy"] (1, 9) => (1, 16): "networking.k8s.io/v1";
+	"extensions/v1beta1" ["PodSecurityPolicy"] (1, 11) => (1, 16): "policy/v1beta1";
+	"extensions/v1beta1" ["Ingress"] (1, 14) => (1, 22): "networking.k8s.io/v1";
+	"apps/v1beta1" ["Deployment", "StatefulSet", "ControllerRevision"] (1, 9) => (1, 16): "apps/v1";
+	"apps/v1beta2" ["Deployment", "StatefulSet", "DaemonSet", "ReplicaSet", "ControllerRevision"] (1, 9) => (1, 16): "apps/v1";
+	"networking.k8s.io/v1beta1" ["Ingress", "IngressClass"] (1, 19) => (1, 22): "networking.k8s.io/v1";
+	"apiextensions.k8s.io/v1beta1" ["CustomResourceDefinition"] (1, 16) => (1, 22): "apiextensions.k8s.io/v1";
+	"admissionregistration.k8s.io/v1beta1" ["MutatingWebhookConfiguration", "ValidatingWebhookConfiguration"] (1, 16) => (1, 22): "admissionregistration.k8s.io/v1";
+	"apiregistration.k8s.io/v1beta1" ["APIService"] (1, 19) => (1, 22): "apiregistration.k8s.io/v1";
+	"authentication.k8s.io/v1beta1" ["TokenReview"] (1, 19) => (1, 22): "authentication.k8s.io/v1";
+	"authorization.k8s.io/v1beta1" ["LocalSubjectAccessReview", "SelfSubjectAccessReview", "SelfSubjectRulesReview", "SubjectAccessReview"] (1, 19) => (1, 22): "authorization.k8s.io/v1";
+	"certificates.k8s.io/v1beta1" ["CertificateSigningRequest"] (1, 19) => (1, 22): "certificates.k8s.io/v1";
+	"coordination.k8s.io/v1beta1" ["Lease"] (1, 19) => (1, 22): "coordination.k8s.io/v1";
+	"rbac.authorization.k8s.io/v1alpha1" ["ClusterRole", "ClusterRoleBinding", "Role", "RoleBinding"] (1, 17) => (1, 22): "rbac.authorization.k8s.io/v1";
+	"rbac.authorization.k8s.io/v1beta1" ["ClusterRole", "ClusterRoleBinding", "Role", "RoleBinding"] (1, 17) => (1, 22): "rbac.authorization.k8s.io/v1";
+	"scheduling.k8s.io/v1beta1" ["PriorityClass"] (1, 14) => (1, 22): "scheduling.k8s.io/v1";
+	"storage.k8s.io/v1beta1" ["CSIDriver", "CSINode", "StorageClass", "VolumeAttachment"] (1, 19) => (1, 22): "storage.k8s.io/v1";
+	"batch/v1beta1" ["CronJob"] (1, 21) => (1, 25): "batch/v1";
+	"discovery.k8s.io/v1beta1" ["EndpointSlice"] (1, 21) => (1, 25): "discovery.k8s.io/v1";
+	"events.k8s.io/v1beta1" ["Event"] (1, 22) => (1, 25): "events.k8s.io/v1";
+	"autoscaling/v2beta1" ["HorizontalPodAutoscaler"] (1, 22) => (1, 25): "autoscaling/v2";
+	"policy/v1beta1" ["PodDisruptionBudget"] (1, 21) => (1, 25): "policy/v1";
+	"policy/v1beta1" ["PodSecurityPolicy"] (1, 21) => (1, 25);
+	"node.k8s.io/v1beta1" ["RuntimeClass"] (1, 20) => (1, 25): "node.k8s.io/v1";
+	"flowcontrol.apiserver.k8s.io/v1beta1" ["FlowSchema", "PriorityLevelConfiguration"] (1, 23) => (1, 26): "flowcontrol.apiserver.k8s.io/v1beta3";
+	"autoscaling/v2beta2" ["HorizontalPodAutoscaler"] (1, 23) => (1, 26): "autoscaling/v2";
+	"storage.k8s.io/v1beta1" ["CSIStorageCapacity"] (1, 24) => (1, 27): "storage.k8s.io/v1";
+	"flowcontrol.apiserver.k8s.io/v1beta2" ["FlowSchema", "PriorityLevelConfiguration"] (1, 26) => (1, 29): "flowcontrol.apiserver.k8s.io/v1";
+	"flowcontrol.apiserver.k8s.io/v1beta3" ["FlowSchema", "PriorityLevelConfiguration"] (1, 29) => (1, 32): "flowcontrol.apiserver.k8s.io/v1";
+};
+
+impl Entry {
+	fn matches(&self, identity: &Identity) -> bool {
+		self.api_version == identity.api_version && self.kinds.contains(&identity.kind.as_str())
+	}
+
+	fn message(&self, identity: &Identity, version: &Version) -> String {
+		let api = format!("{} {}", identity.api_version, identity.kind);
+		let replacement = match self.replacement {
+			Some(replacement) => format!("use {replacement} instead"),
+			None => String::from("there's no replacement"),
+		};
+
+		if self.removed <= *version {
+			format!("{api} was removed in {}, {replacement}", self.removed)
+		} else {
+			format!(
+				"{api} is deprecated since {} and will be removed in {}, {replacement}",
+				self.deprecated, self.removed
+			)
+		}
+	}
+}
+
+/// Flags the objects whose API is deprecated or gone in the target version
+///
+/// Deprecated APIs still work, so they only warn, while removed ones deny as the cluster would
+/// reject them anyway.
+pub fn review(manifests: &[Manifest], version: &Version) -> Vec<Finding> {
+	manifests
+		.iter()
+		.filter_map(|manifest| {
+			let identity: Identity = manifest.value().into();
+			let entry = TABLE
+				.iter()
+				.find(|entry| entry.matches(&identity) && entry.deprecated <= *version)?;
+
+			let severity = if entry.removed <= *version {
+				Severity::Deny
+			} else {
+				Severity::Warn
+			};
+
+			Some(Finding {
+				rule: RULE.to_string(),
+				severity,
+				path: manifest.path().to_path_buf(),
+				message: entry.message(&identity, version),
+				pointer: Some(String::from("/apiVersion")),
+				identity,
+			})
+		})
+		.collect()
+}
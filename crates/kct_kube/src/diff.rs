@@ -0,0 +1,160 @@
+use crate::error::{Object, Root as Error};
+use crate::identity::Key;
+use crate::{Identity, Manifest};
+
+use std::collections::HashMap;
+use std::fmt;
+
+use similar::TextDiff;
+
+/// What happened to an object between two renders
+///
+/// Objects are matched by what the cluster stores them by, so moving an object to another version
+/// of its group is a modification, and their tracking paths are kept around to point the reader
+/// to the place in the package that changed.
+#[derive(Debug)]
+pub enum Change {
+	Added(Manifest),
+	Removed(Manifest),
+	Modified { before: Manifest, after: Manifest },
+}
+
+impl Change {
+	pub fn identity(&self) -> Identity {
+		match self {
+			Change::Added(manifest) | Change::Removed(manifest) => manifest.value().into(),
+			Change::Modified { after, .. } => after.value().into(),
+		}
+	}
+}
+
+impl fmt::Display for Change {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let identity = self.identity();
+
+		match self {
+			Change::Added(manifest) => {
+				writeln!(f, "+ {} ({identity})", manifest.path().display())?;
+				prefixed(f, "+", &yaml(manifest)?)
+			}
+			Change::Removed(manifest) => {
+				writeln!(f, "- {} ({identity})", manifest.path().display())?;
+				prefixed(f, "-", &yaml(manifest)?)
+			}
+			Change::Modified { before, after } => {
+				let previous: Identity = before.value().into();
+				let identity = if previous == identity {
+					identity.to_string()
+				} else {
+					format!("{previous} -> {identity}")
+				};
+
+				if before.path() == after.path() {
+					writeln!(f, "~ {} ({identity})", after.path().display())?;
+				} else {
+					writeln!(
+						f,
+						"~ {} -> {} ({identity})",
+						before.path().display(),
+						after.path().display()
+					)?;
+				}
+
+				let (before, after) = (yaml(before)?, yaml(after)?);
+				let diff = TextDiff::from_lines(&before, &after);
+
+				write!(f, "{}", diff.unified_diff().header("before", "after"))
+			}
+		}
+	}
+}
+
+/// Object level differences between two renders of a package
+#[derive(Debug, Default)]
+pub struct Diff(Vec<Change>);
+
+impl Diff {
+	/// Matches the objects of both renders, where the same object can't be rendered twice on the
+	/// same side unless both copies are equal
+	pub fn new(before: Vec<Manifest>, after: Vec<Manifest>) -> Result<Self, Error> {
+		let (mut previous, order) = index(before)?;
+		let (mut current, rendered) = index(after)?;
+
+		let mut changes = vec![];
+		for key in rendered {
+			let manifest = current.remove(&key).unwrap();
+
+			match previous.remove(&key) {
+				None => changes.push(Change::Added(manifest)),
+				Some(before) if before.value() != manifest.value() => {
+					changes.push(Change::Modified {
+						before,
+						after: manifest,
+					})
+				}
+				Some(_) => (),
+			}
+		}
+
+		for key in order {
+			if let Some(manifest) = previous.remove(&key) {
+				changes.push(Change::Removed(manifest));
+			}
+		}
+
+		Ok(Diff(changes))
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	pub fn changes(&self) -> &[Change] {
+		&self.0
+	}
+}
+
+impl fmt::Display for Diff {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0.iter().try_for_each(|change| writeln!(f, "{change}"))
+	}
+}
+
+/// Objects of a render by their keys, along with the order they were rendered in
+fn index(manifests: Vec<Manifest>) -> Result<(HashMap<Key, Manifest>, Vec<Key>), Error> {
+	let mut indexed: HashMap<Key, Manifest> = HashMap::new();
+	let mut order: Vec<Key> = vec![];
+
+	for manifest in manifests {
+		let identity: Identity = manifest.value().into();
+		let key = identity.key();
+
+		match indexed.get(&key) {
+			Some(first) if first.value() == manifest.value() => (),
+			Some(first) => {
+				return Err(Object::Duplicate {
+					identity: Box::new(identity),
+					first: first.path().to_path_buf(),
+					second: manifest.path().to_path_buf(),
+				}
+				.into())
+			}
+			None => {
+				order.push(key.clone());
+				indexed.insert(key, manifest);
+			}
+		}
+	}
+
+	Ok((indexed, order))
+}
+
+fn yaml(manifest: &Manifest) -> Result<String, fmt::Error> {
+	serde_yaml::to_string(manifest.value()).map_err(|_err| fmt::Error)
+}
+
+fn prefixed(f: &mut fmt::Formatter<'_>, prefix: &str, contents: &str) -> fmt::Result {
+	contents
+		.lines()
+		.try_for_each(|line| writeln!(f, "{prefix}{line}"))
+}
@@ -1,9 +1,52 @@
+use crate::openapi::Violations;
+use crate::policy::Findings;
+use crate::{Identity, Version};
+
+use std::error::Error as StdError;
+use std::path::PathBuf;
+
+use jsonschema::ValidationError;
 use thiserror::Error;
 
+/// Why a file or rule couldn't be used, as there are many ways to fail reading them
+pub(crate) type Reason = Box<dyn StdError + Send + Sync>;
+
 #[derive(Error, Debug)]
 pub enum Root {
 	#[error("value is required")]
 	MissingValue,
+	#[error("Unknown graph format \"{0}\", use either dot or mermaid")]
+	GraphFormat(String),
+	#[error("Invalid Kubernetes version \"{0}\", it should look like 1.26")]
+	Version(String),
+	#[error("There are no schemas for Kubernetes {0}, only the ones bundled for {1}")]
+	Unbundled(Version, Version),
+	#[error("Unable to read schemas from {}", .0.display())]
+	Schemas(PathBuf, #[source] Box<dyn StdError + Send + Sync>),
+	#[error("The schema for {0} {1} is invalid")]
+	InvalidSchema(String, String, #[source] Box<ValidationError<'static>>),
+	#[error("Your objects don't match their schemas:\n{0}")]
+	Invalid(Violations),
+	#[error("Unable to read policies from {}", .0.display())]
+	Policies(PathBuf),
+	#[error("The rule {0} is invalid")]
+	Rule(String, #[source] Box<dyn StdError + Send + Sync>),
+	#[error("Your objects were denied:\n{0}")]
+	Denied(Findings),
+	#[error("Invalid image override \"{0}\", use name=image, name:tag, or name@digest")]
+	Image(String),
+	#[error("Invalid transformers: {0}")]
+	Transformers(String),
+	#[error("Unable to read patches from {}", .0.display())]
+	Patches(PathBuf),
+	#[error("Unable to patch with {}: {1}", .0.display())]
+	Patch(PathBuf, String),
+	#[error("The {1} of {0} is invalid, it should be {2}")]
+	InvalidData(Box<Identity>, String, &'static str),
+	#[error("{0} holds {1} bytes of data, over the limit of 1 MiB")]
+	TooLarge(Box<Identity>, usize),
+	#[error("Unable to seal secrets: {0}")]
+	Seal(String),
 	#[error("The rendered json is invalid")]
 	Output(#[from] Output),
 	#[error("Your object is invalid")]
@@ -24,6 +67,12 @@ pub enum Object {
 	NoKind,
 	#[error("Your tracking fields are invalid")]
 	Tracking(#[from] Tracking),
+	#[error("{identity} is rendered at both {} and {}", .first.display(), .second.display())]
+	Duplicate {
+		identity: Box<Identity>,
+		first: PathBuf,
+		second: PathBuf,
+	},
 }
 
 #[derive(Error, Debug)]
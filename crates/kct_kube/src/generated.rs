@@ -0,0 +1,62 @@
+use crate::reference;
+use crate::{Identity, Manifest};
+
+use kct_helper::annotation::GENERATED_FROM;
+use serde_json::Value;
+
+struct Generated {
+	kind: String,
+	namespace: Option<String>,
+	original: String,
+	name: String,
+}
+
+/// Points the references made through the original names of generated objects to their hashed
+/// names, so packages can refer to them without knowing their contents
+pub fn link(manifests: &mut [Manifest]) {
+	let annotation = format!(
+		"/metadata/annotations/{}",
+		GENERATED_FROM.replace('/', "~1")
+	);
+	let generated: Vec<Generated> = manifests
+		.iter()
+		.filter_map(|manifest| {
+			let value = manifest.value();
+			let original = value
+				.pointer(&annotation)
+				.and_then(|o| o.as_str())?
+				.to_string();
+			let identity = Identity::from(value);
+
+			Some(Generated {
+				kind: identity.kind,
+				namespace: identity.namespace,
+				name: identity.name?,
+				original,
+			})
+		})
+		.collect();
+
+	if generated.is_empty() {
+		return;
+	}
+
+	for manifest in manifests.iter_mut() {
+		let object = manifest.value_mut();
+
+		for slot in reference::slots(object) {
+			let current = object.pointer(&slot.pointer).and_then(|n| n.as_str());
+			let target = generated.iter().find(|g| {
+				g.kind == slot.kind
+					&& Some(g.original.as_str()) == current
+					&& (g.namespace.is_none()
+						|| slot.namespace.is_none()
+						|| g.namespace == slot.namespace)
+			});
+
+			if let Some(target) = target {
+				*object.pointer_mut(&slot.pointer).unwrap() = Value::String(target.name.clone());
+			}
+		}
+	}
+}
@@ -0,0 +1,217 @@
+use crate::error::Root as Error;
+use crate::reference;
+use crate::Manifest;
+
+use std::fmt::Write;
+use std::path::Component;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+	Dot,
+	Mermaid,
+}
+
+impl FromStr for Format {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"dot" => Ok(Format::Dot),
+			"mermaid" => Ok(Format::Mermaid),
+			other => Err(Error::GraphFormat(other.to_string())),
+		}
+	}
+}
+
+struct Node {
+	kind: String,
+	name: String,
+}
+
+#[derive(Default)]
+struct Cluster {
+	field: String,
+	clusters: Vec<Cluster>,
+	nodes: Vec<usize>,
+}
+
+impl Cluster {
+	fn child(&mut self, field: &str) -> &mut Cluster {
+		let position = self.clusters.iter().position(|c| c.field == field);
+
+		let index = match position {
+			Some(index) => index,
+			None => {
+				self.clusters.push(Cluster {
+					field: field.to_string(),
+					..Default::default()
+				});
+
+				self.clusters.len() - 1
+			}
+		};
+
+		&mut self.clusters[index]
+	}
+}
+
+struct Edge {
+	from: usize,
+	to: usize,
+	label: &'static str,
+}
+
+/// The package tree with objects as leaves and the references between them
+///
+/// Intermediate fields become clusters, which keeps the organization chosen by the package
+/// author, while the edges cross those clusters to show how objects depend on each other.
+pub struct Graph {
+	root: Cluster,
+	nodes: Vec<Node>,
+	edges: Vec<Edge>,
+}
+
+impl Graph {
+	pub fn new(manifests: &[Manifest]) -> Self {
+		let mut root = Cluster::default();
+		let mut nodes = vec![];
+
+		for (index, manifest) in manifests.iter().enumerate() {
+			let fields: Vec<String> = manifest
+				.path()
+				.components()
+				.filter_map(|c| match c {
+					Component::Normal(field) => Some(field.to_string_lossy().to_string()),
+					_ => None,
+				})
+				.collect();
+
+			let parents = fields.len().saturating_sub(1);
+			let cluster = fields[..parents]
+				.iter()
+				.fold(&mut root, |cluster, field| cluster.child(field));
+			cluster.nodes.push(index);
+
+			let value = manifest.value();
+			let text = |v: Option<&serde_json::Value>| {
+				v.and_then(|v| v.as_str()).unwrap_or_default().to_string()
+			};
+
+			nodes.push(Node {
+				kind: text(value.get("kind")),
+				name: text(value.get("metadata").and_then(|m| m.get("name"))),
+			});
+		}
+
+		let mut edges = vec![];
+		for (from, manifest) in manifests.iter().enumerate() {
+			let value = manifest.value();
+
+			for (to, target) in manifests.iter().enumerate() {
+				if reference::selects(value, target.value()) {
+					edges.push(Edge {
+						from,
+						to,
+						label: "selector",
+					});
+				}
+			}
+
+			for reference in reference::references(value) {
+				let targets = manifests
+					.iter()
+					.enumerate()
+					.filter(|(_, target)| reference.targets(target.value()));
+
+				for (to, _) in targets {
+					edges.push(Edge {
+						from,
+						to,
+						label: reference.via,
+					});
+				}
+			}
+		}
+
+		Graph { root, nodes, edges }
+	}
+
+	pub fn render(&self, format: Format) -> String {
+		let mut out = String::new();
+		let mut ids = 0;
+
+		match format {
+			Format::Dot => {
+				out.push_str("digraph kct {\n\tcompound=true;\n\tnode [shape=box];\n");
+				self.dot(&mut out, &self.root, 1, &mut ids);
+				for edge in &self.edges {
+					let _ = writeln!(
+						out,
+						"\tn{} -> n{} [label=\"{}\"];",
+						edge.from, edge.to, edge.label
+					);
+				}
+				out.push_str("}\n");
+			}
+			Format::Mermaid => {
+				out.push_str("flowchart LR\n");
+				self.mermaid(&mut out, &self.root, 1, &mut ids);
+				for edge in &self.edges {
+					let _ = writeln!(out, "\tn{} -->|{}| n{}", edge.from, edge.label, edge.to);
+				}
+			}
+		}
+
+		out
+	}
+
+	fn dot(&self, out: &mut String, cluster: &Cluster, depth: usize, ids: &mut usize) {
+		let indent = "\t".repeat(depth);
+
+		for index in &cluster.nodes {
+			let node = &self.nodes[*index];
+			let label = escape(&format!("{}\\n{}", node.kind, node.name));
+
+			let _ = writeln!(out, "{indent}n{index} [label=\"{label}\"];");
+		}
+
+		for child in &cluster.clusters {
+			let _ = writeln!(out, "{indent}subgraph cluster_{ids} {{");
+			let _ = writeln!(out, "{indent}\tlabel=\"{}\";", escape(&child.field));
+			*ids += 1;
+
+			self.dot(out, child, depth + 1, ids);
+
+			let _ = writeln!(out, "{indent}}}");
+		}
+	}
+
+	fn mermaid(&self, out: &mut String, cluster: &Cluster, depth: usize, ids: &mut usize) {
+		let indent = "\t".repeat(depth);
+
+		for index in &cluster.nodes {
+			let node = &self.nodes[*index];
+			let label = escape(&format!("{}<br/>{}", node.kind, node.name));
+
+			let _ = writeln!(out, "{indent}n{index}[\"{label}\"]");
+		}
+
+		for child in &cluster.clusters {
+			let _ = writeln!(
+				out,
+				"{indent}subgraph c{ids} [\"{}\"]",
+				escape(&child.field)
+			);
+			*ids += 1;
+
+			self.mermaid(out, child, depth + 1, ids);
+
+			let _ = writeln!(out, "{indent}end");
+		}
+	}
+}
+
+fn escape(label: &str) -> String {
+	label.replace('"', "'")
+}
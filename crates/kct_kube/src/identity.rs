@@ -0,0 +1,70 @@
+use std::fmt;
+
+use serde_json::Value;
+
+/// How the cluster knows an object
+///
+/// Two objects with the same identity are the same object as far as the API server is concerned,
+/// no matter where they're placed in the package tree. The namespace is optional because
+/// cluster-scoped objects don't have one, and so is the name, as objects can rely on
+/// `generateName`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Identity {
+	pub api_version: String,
+	pub kind: String,
+	pub namespace: Option<String>,
+	pub name: Option<String>,
+}
+
+impl From<&Value> for Identity {
+	fn from(value: &Value) -> Self {
+		let text = |v: Option<&Value>| v.and_then(|v| v.as_str()).map(String::from);
+		let metadata = value.get("metadata");
+
+		Identity {
+			api_version: text(value.get("apiVersion")).unwrap_or_default(),
+			kind: text(value.get("kind")).unwrap_or_default(),
+			namespace: text(metadata.and_then(|m| m.get("namespace"))),
+			name: text(metadata.and_then(|m| m.get("name"))),
+		}
+	}
+}
+
+/// Group, kind, namespace, and name of an object
+pub(crate) type Key = (String, String, Option<String>, Option<String>);
+
+impl Identity {
+	/// Group of the API, which is empty for the core one
+	pub fn group(&self) -> &str {
+		match self.api_version.rsplit_once('/') {
+			Some((group, _version)) => group,
+			None => "",
+		}
+	}
+
+	/// What the API server stores the object by, where the versions of a group are views of the
+	/// same objects
+	///
+	/// Objects without a namespace stay unscoped, as cluster-scoped kinds don't belong to the
+	/// default namespace.
+	pub(crate) fn key(&self) -> Key {
+		(
+			self.group().to_string(),
+			self.kind.clone(),
+			self.namespace.clone(),
+			self.name.clone(),
+		)
+	}
+}
+
+impl fmt::Display for Identity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let name = self.name.as_deref().unwrap_or("<unnamed>");
+
+		write!(f, "{}/{} ", self.api_version, self.kind)?;
+		match &self.namespace {
+			Some(namespace) => write!(f, "{namespace}/{name}"),
+			None => write!(f, "{name}"),
+		}
+	}
+}
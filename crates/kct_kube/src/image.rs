@@ -0,0 +1,141 @@
+use crate::error::Root as Error;
+use crate::reference;
+
+use std::fmt;
+use std::str::FromStr;
+
+use serde::Deserialize;
+use serde_json::Value;
+
+const CONTAINERS: [&str; 3] = ["initContainers", "containers", "ephemeralContainers"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Change {
+	Replace(String),
+	Tag(String),
+	Digest(String),
+}
+
+/// Override for the images of containers with the given name
+///
+/// It's either `name=image` to replace the whole reference, `name:tag` to keep the name with
+/// another tag, or `name@sha256:...` to pin it to a digest. The name is the image without its tag
+/// or digest, e.g. `nginx` or `registry.io/team/app`.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Image {
+	name: String,
+	change: Change,
+}
+
+impl FromStr for Image {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || Error::Image(s.to_string());
+
+		let (name, change) = match s.split_once('=') {
+			Some((name, image)) => (name.to_string(), Change::Replace(image.to_string())),
+			None => {
+				let (name, tag, digest) = split(s);
+
+				match (tag, digest) {
+					(_, Some(digest)) => (name.to_string(), Change::Digest(digest.to_string())),
+					(Some(tag), None) => (name.to_string(), Change::Tag(tag.to_string())),
+					(None, None) => return Err(invalid()),
+				}
+			}
+		};
+
+		let empty = match &change {
+			Change::Replace(v) | Change::Tag(v) | Change::Digest(v) => v.is_empty(),
+		};
+		if name.is_empty() || empty {
+			return Err(invalid());
+		}
+
+		Ok(Image { name, change })
+	}
+}
+
+impl TryFrom<String> for Image {
+	type Error = Error;
+
+	fn try_from(value: String) -> Result<Self, Self::Error> {
+		value.parse()
+	}
+}
+
+impl fmt::Display for Image {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match &self.change {
+			Change::Replace(image) => write!(f, "{}={image}", self.name),
+			Change::Tag(tag) => write!(f, "{}:{tag}", self.name),
+			Change::Digest(digest) => write!(f, "{}@{digest}", self.name),
+		}
+	}
+}
+
+impl Image {
+	/// Rewrites the containers in the pod template of the object, returning if any matched
+	pub fn apply(&self, object: &mut Value) -> bool {
+		let spec = match reference::pod_spec_mut(object) {
+			Some(spec) => spec,
+			None => return false,
+		};
+
+		let mut matched = false;
+		for field in CONTAINERS {
+			let containers = spec
+				.get_mut(field)
+				.and_then(|c| c.as_array_mut())
+				.into_iter()
+				.flatten();
+
+			for container in containers {
+				let current = container.get("image").and_then(|i| i.as_str());
+				let replaced = current.and_then(|current| self.replace(current));
+
+				if let Some(image) = replaced {
+					container["image"] = Value::String(image);
+					matched = true;
+				}
+			}
+		}
+
+		matched
+	}
+
+	fn replace(&self, current: &str) -> Option<String> {
+		let (name, _, _) = split(current);
+		if name != self.name {
+			return None;
+		}
+
+		let image = match &self.change {
+			Change::Replace(image) => image.clone(),
+			Change::Tag(tag) => format!("{name}:{tag}"),
+			Change::Digest(digest) => format!("{name}@{digest}"),
+		};
+
+		Some(image)
+	}
+}
+
+/// Splits an image reference into its name, tag, and digest
+fn split(reference: &str) -> (&str, Option<&str>, Option<&str>) {
+	let (rest, digest) = match reference.split_once('@') {
+		Some((rest, digest)) => (rest, Some(digest)),
+		None => (reference, None),
+	};
+
+	let slash = rest.rfind('/').map_or(0, |i| i + 1);
+	match rest[slash..].rfind(':') {
+		Some(colon) => {
+			let colon = slash + colon;
+
+			(&rest[..colon], Some(&rest[colon + 1..]), digest)
+		}
+		None => (rest, None, digest),
+	}
+}
@@ -2,13 +2,16 @@ mod order;
 mod path;
 
 use crate::error::{self, Root as Error};
-use crate::Manifest;
+use crate::identity::Key;
+use crate::{Identity, Manifest};
 
-use self::order::{Kind, Order, Track, Tracking};
+use self::order::Order;
+
+pub use self::order::{Kind, Track, Tracking};
 
 pub use self::path::Filter;
 
-use std::collections::BinaryHeap;
+use std::collections::{BinaryHeap, HashMap};
 use std::path::PathBuf;
 
 use anyhow::Result;
@@ -32,6 +35,33 @@ impl From<Food> for Manifest {
 	}
 }
 
+/// Something found in the output, which knows where it was found
+pub(crate) trait Found {
+	fn place(&self) -> PathBuf;
+
+	fn object(&self) -> &Value;
+}
+
+impl Found for Food {
+	fn place(&self) -> PathBuf {
+		self.into()
+	}
+
+	fn object(&self) -> &Value {
+		&self.1
+	}
+}
+
+impl Found for Manifest {
+	fn place(&self) -> PathBuf {
+		self.path().to_path_buf()
+	}
+
+	fn object(&self) -> &Value {
+		self.value()
+	}
+}
+
 impl Eq for Food {}
 impl PartialEq for Food {
 	fn eq(&self, other: &Self) -> bool {
@@ -53,16 +83,33 @@ impl PartialOrd for Food {
 
 pub struct Ingestor {
 	filter: Filter,
+	identical: bool,
 }
 
 impl Ingestor {
-	pub fn new(only: Vec<PathBuf>, except: Vec<PathBuf>) -> Self {
+	/// Creates an ingestor that only lets through the objects passing the filter. The `identical`
+	/// flag allows the same object to be rendered more than once as long as all copies are equal,
+	/// in which case only the first copy is kept.
+	pub fn new(only: Vec<PathBuf>, except: Vec<PathBuf>, identical: bool) -> Self {
 		let filter = Filter { only, except };
 
-		Self { filter }
+		Self { filter, identical }
+	}
+
+	pub fn ingest(&self, json: &Value) -> Result<Vec<Manifest>, Error> {
+		let found = self.digest(json)?;
+
+		Ok(found.into_iter().map(|food| food.into()).collect())
+	}
+
+	/// Same as [`Ingestor::ingest`] but keeps the whole tracking that ordered each object
+	pub fn trace(&self, json: &Value) -> Result<Vec<(Tracking, Value)>, Error> {
+		let found = self.digest(json)?;
+
+		Ok(found.into_iter().map(|food| (food.0, food.1)).collect())
 	}
 
-	pub fn ingest<'a>(&'_ self, json: &'a Value) -> Result<Vec<Manifest>, Error> {
+	fn digest<'a>(&'_ self, json: &'a Value) -> Result<Vec<Food>, Error> {
 		let mut manifests = BinaryHeap::new();
 
 		let root = vec![(Tracking::default(), json)];
@@ -92,11 +139,46 @@ impl Ingestor {
 			}
 		}
 
-		Ok(manifests
-			.into_sorted_vec()
-			.into_iter()
-			.map(|food| food.into())
-			.collect())
+		self.deduplicate(manifests.into_sorted_vec())
+	}
+
+	/// Objects are applied by their identity, so two of them with the same identity would
+	/// silently overwrite each other in the cluster. Changes to names and namespaces after the
+	/// ingestion can make objects collide, so they're checked again.
+	pub(crate) fn deduplicate<T: Found>(&self, found: Vec<T>) -> Result<Vec<T>, Error> {
+		let mut seen: HashMap<Key, usize> = HashMap::new();
+		let mut unique: Vec<T> = Vec::with_capacity(found.len());
+
+		for item in found {
+			let identity: Identity = item.object().into();
+			if identity.name.is_none() {
+				unique.push(item);
+				continue;
+			}
+
+			let key = identity.key();
+			match seen.get(&key) {
+				None => {
+					seen.insert(key, unique.len());
+					unique.push(item);
+				}
+				Some(&index) => {
+					let first = &unique[index];
+					if self.identical && first.object() == item.object() {
+						continue;
+					}
+
+					return Err(error::Object::Duplicate {
+						identity: Box::new(identity),
+						first: first.place(),
+						second: item.place(),
+					}
+					.into());
+				}
+			}
+		}
+
+		Ok(unique)
 	}
 
 	fn on_found(&self, tracking: Tracking, json: &Value) -> Result<Food, Error> {
@@ -128,7 +210,7 @@ impl Ingestor {
 
 				members.push((tracking.track(track), v))
 			} else {
-				return Err(error::Output::Path(k.to_string()))?;
+				return Err(error::Output::Path(k.to_string()).into());
 			}
 		}
 
@@ -3,6 +3,7 @@ use super::path;
 use crate::error;
 
 use std::cmp::Ordering;
+use std::fmt;
 use std::path::PathBuf;
 
 use anyhow::Result;
@@ -56,6 +57,10 @@ const KIND_ORDER: [&str; 35] = [
 pub struct Kind(String);
 
 impl Kind {
+	pub fn name(&self) -> &str {
+		&self.0
+	}
+
 	pub fn priority(&self) -> usize {
 		KIND_ORDER
 			.iter()
@@ -108,15 +113,15 @@ pub struct Track {
 	pub kind: Option<Kind>,
 }
 
-impl ToString for Track {
-	fn to_string(&self) -> String {
+impl fmt::Display for Track {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
 		let kind = self
 			.kind
 			.clone()
 			.map(|k| k.0)
 			.unwrap_or_else(|| String::from("Kind"));
 
-		format!("{}({}:{}:{})", kind, self.field, self.depth, self.order)
+		write!(f, "{}({}:{}:{})", kind, self.field, self.depth, self.order)
 	}
 }
 
@@ -225,6 +230,10 @@ impl TryFrom<&Value> for Order {
 pub struct Tracking(Vec<Track>);
 
 impl Tracking {
+	pub fn tracks(&self) -> &[Track] {
+		&self.0
+	}
+
 	pub fn depth(&self) -> usize {
 		let vec = &self.0;
 		let index = vec.len().saturating_sub(1);
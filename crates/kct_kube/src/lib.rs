@@ -1,19 +1,49 @@
 mod client;
+mod deprecation;
+mod diff;
+mod generated;
+mod graph;
+mod identity;
+mod image;
 mod ingestor;
+mod normalize;
+mod openapi;
+mod patch;
+mod plan;
+mod policy;
+mod redact;
+mod transform;
+mod version;
 
 pub mod error;
+pub mod reference;
+pub mod seal;
 
 use self::client::Client;
 use self::ingestor::Ingestor;
 
+pub use crate::diff::{Change, Diff};
 pub use crate::error::Root as Error;
-
+pub use crate::graph::{Format, Graph};
+pub use crate::identity::Identity;
+pub use crate::image::Image;
+pub use crate::ingestor::{Kind, Track, Tracking};
+pub use crate::openapi::{Schemas, Violation, Violations, BUNDLED};
+pub use crate::patch::Patches;
+pub use crate::plan::{Pass, Plan, Step};
+pub use crate::policy::{Finding, Findings, Policies, Severity};
+pub use crate::redact::Redactor;
+pub use crate::seal::Sealer;
+pub use crate::transform::Transformers;
+pub use crate::version::Version;
+
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
+use jsonschema::Validator;
 use once_cell::sync::Lazy;
 use serde_json::Value;
-use valico::json_schema::Scope;
 
 pub type Manifest = Tracked<Value>;
 
@@ -43,12 +73,15 @@ impl<T> Tracked<T> {
 	pub fn value(&self) -> &T {
 		&self.1
 	}
+
+	pub(crate) fn value_mut(&mut self) -> &mut T {
+		&mut self.1
+	}
 }
 
-static SCHEMA: Lazy<Value> = Lazy::new(|| {
-	serde_json::from_str(
+static SCHEMA: Lazy<Validator> = Lazy::new(|| {
+	let schema = serde_json::from_str(
 		r#"{
-		"$schema": "http://json-schema.org/schema#",
 		"type": "object",
 		"additionalProperties": true,
 		"required": ["kind", "apiVersion"],
@@ -62,15 +95,14 @@ static SCHEMA: Lazy<Value> = Lazy::new(|| {
 		}
 	}"#,
 	)
-	.unwrap()
+	.unwrap();
+
+	jsonschema::draft7::new(&schema).unwrap()
 });
 
 impl Manifest {
 	fn conforms(obj: &Value) -> bool {
-		let mut scope = Scope::new();
-		let validator = scope.compile_and_return(SCHEMA.clone(), false).unwrap();
-
-		validator.validate(obj).is_strictly_valid()
+		SCHEMA.is_valid(obj)
 	}
 }
 
@@ -86,6 +118,12 @@ impl From<Manifest> for (PathBuf, String) {
 pub struct Kube {
 	value: Value,
 	ingestor: Ingestor,
+	schemas: Option<Schemas>,
+	policies: Option<Policies>,
+	deprecations: Option<Version>,
+	patches: Patches,
+	transformers: Transformers,
+	redactor: Redactor,
 }
 
 impl Kube {
@@ -94,7 +132,79 @@ impl Kube {
 	}
 
 	pub fn render(&self) -> Result<Vec<Manifest>, Error> {
-		self.ingestor.ingest(&self.value)
+		let manifests = self.transformed()?;
+
+		if let Some(schemas) = &self.schemas {
+			schemas.validate(&manifests)?;
+		}
+
+		let findings = self.review(&manifests)?;
+		if findings.denied() {
+			return Err(Error::Denied(findings));
+		}
+
+		if !findings.is_empty() {
+			let warnings = format!("Your objects have warnings:\n{findings}");
+
+			eprint!("{}", self.redactor.text(&warnings));
+		}
+
+		Ok(manifests)
+	}
+
+	/// Reviews the objects against the policies and deprecated APIs without stopping at the ones
+	/// that deny
+	pub fn lint(&self) -> Result<Findings, Error> {
+		let manifests = self.transformed()?;
+
+		self.review(&manifests)
+	}
+
+	fn transformed(&self) -> Result<Vec<Manifest>, Error> {
+		let mut manifests = self.ingestor.ingest(&self.value)?;
+		generated::link(&mut manifests);
+		self.patches.apply(&mut manifests)?;
+		self.transformers.apply(&mut manifests);
+		let mut manifests = self.ingestor.deduplicate(manifests)?;
+		normalize::apply(&mut manifests)?;
+
+		Ok(manifests)
+	}
+
+	fn review(&self, manifests: &[Manifest]) -> Result<Findings, Error> {
+		let mut findings = match &self.policies {
+			Some(policies) => policies.check(manifests)?,
+			None => Findings::default(),
+		};
+
+		if let Some(version) = &self.deprecations {
+			findings.0.extend(deprecation::review(manifests, version));
+		}
+
+		Ok(findings)
+	}
+
+	/// Explains the order of the objects as installed, so they go through the same patches and
+	/// transformers while keeping the tracking that ordered them
+	pub fn plan(&self) -> Result<Plan, Error> {
+		let mut trackings: HashMap<PathBuf, Tracking> = self
+			.ingestor
+			.trace(&self.value)?
+			.into_iter()
+			.map(|(tracking, _)| ((&tracking).into(), tracking))
+			.collect();
+
+		let traced = self
+			.transformed()?
+			.into_iter()
+			.filter_map(|manifest| {
+				let (path, value): (PathBuf, Value) = manifest.into();
+
+				trackings.remove(&path).map(|tracking| (tracking, value))
+			})
+			.collect();
+
+		Ok(Plan::new(traced))
 	}
 
 	pub async fn install(self) -> Result<()> {
@@ -125,6 +235,13 @@ pub struct Builder {
 	value: Option<Value>,
 	only: Vec<PathBuf>,
 	except: Vec<PathBuf>,
+	identical: bool,
+	schemas: Option<Schemas>,
+	policies: Option<Policies>,
+	deprecations: Option<Version>,
+	patches: Patches,
+	transformers: Transformers,
+	redactor: Redactor,
 }
 
 impl Builder {
@@ -151,10 +268,74 @@ impl Builder {
 		self
 	}
 
+	/// Allows the same object to be rendered at different paths as long as the copies are equal
+	pub fn allow_identical(mut self, identical: bool) -> Self {
+		self.identical = identical;
+
+		self
+	}
+
+	/// Validates the rendered objects against their schemas
+	pub fn schemas(mut self, schemas: Option<Schemas>) -> Self {
+		self.schemas = schemas;
+
+		self
+	}
+
+	/// Reviews the rendered objects against organization rules
+	pub fn policies(mut self, policies: Option<Policies>) -> Self {
+		self.policies = policies;
+
+		self
+	}
+
+	/// Flags the objects using APIs that are deprecated or removed in the version
+	pub fn deprecations(mut self, version: Option<Version>) -> Self {
+		self.deprecations = version;
+
+		self
+	}
+
+	/// Changes the rendered objects before they're validated and reviewed
+	pub fn patches(mut self, patches: Patches) -> Self {
+		self.patches = patches;
+
+		self
+	}
+
+	/// Sets the namespace, affixes, labels, and annotations of the patched objects
+	pub fn transformers(mut self, transformers: Transformers) -> Self {
+		self.transformers = transformers;
+
+		self
+	}
+
+	/// Masks the sensitive values in the warnings printed while rendering
+	pub fn redactor(mut self, redactor: Redactor) -> Self {
+		self.redactor = redactor;
+
+		self
+	}
+
 	pub fn build(self) -> Result<Kube, Error> {
 		let value = self.value.ok_or(Error::MissingValue)?;
-		let ingestor = Ingestor::new(self.only, self.except);
-
-		Ok(Kube { ingestor, value })
+		let ingestor = Ingestor::new(self.only, self.except, self.identical);
+		let schemas = self.schemas;
+		let policies = self.policies;
+		let deprecations = self.deprecations;
+		let patches = self.patches;
+		let transformers = self.transformers;
+		let redactor = self.redactor;
+
+		Ok(Kube {
+			ingestor,
+			value,
+			schemas,
+			policies,
+			deprecations,
+			patches,
+			transformers,
+			redactor,
+		})
 	}
 }
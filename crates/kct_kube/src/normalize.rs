@@ -0,0 +1,123 @@
+use crate::error::Root as Error;
+use crate::{Identity, Manifest};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde_json::{Map, Value};
+
+/// Largest amount of data the API server accepts in a ConfigMap or Secret
+const SIZE_LIMIT: usize = 1024 * 1024;
+
+const BASE64: &str = "a base64 string";
+
+/// Brings ConfigMaps and Secrets to the shape the API server stores them in
+///
+/// The `stringData` of Secrets is encoded into `data`, taking precedence over existing keys as
+/// it does in the cluster, so comparing against live objects doesn't show spurious changes. The
+/// values in `data` have to be valid base64 and the total size has to be within the limit, which
+/// would otherwise only fail halfway through an install.
+pub fn apply(manifests: &mut [Manifest]) -> Result<(), Error> {
+	for manifest in manifests.iter_mut() {
+		let object = manifest.value_mut();
+
+		let size = match object.get("kind").and_then(|k| k.as_str()) {
+			Some("Secret") => secret(object)?,
+			Some("ConfigMap") => config_map(object)?,
+			_ => continue,
+		};
+
+		if size > SIZE_LIMIT {
+			let identity = Box::new(Identity::from(&*object));
+
+			return Err(Error::TooLarge(identity, size));
+		}
+	}
+
+	Ok(())
+}
+
+/// Moves `stringData` into `data` and returns the size of the decoded values
+fn secret(object: &mut Value) -> Result<usize, Error> {
+	let invalid = |object: &Value, field: &str, expected| {
+		Error::InvalidData(
+			Box::new(Identity::from(object)),
+			field.to_string(),
+			expected,
+		)
+	};
+
+	if let Some(strings) = object.as_object_mut().and_then(|o| o.remove("stringData")) {
+		let strings = match strings {
+			Value::Object(strings) => strings,
+			Value::Null => Map::new(),
+			_ => return Err(invalid(object, "stringData", "an object")),
+		};
+
+		let mut data = match object.get_mut("data").map(Value::take) {
+			Some(Value::Object(data)) => data,
+			None | Some(Value::Null) => Map::new(),
+			Some(_) => return Err(invalid(object, "data", "an object")),
+		};
+
+		for (key, value) in strings {
+			let text = match value {
+				Value::String(text) => text,
+				_ => return Err(invalid(object, &format!("stringData.{key}"), "a string")),
+			};
+
+			data.insert(key, Value::String(STANDARD.encode(text)));
+		}
+
+		let object = object.as_object_mut().unwrap();
+		if data.is_empty() {
+			object.remove("data");
+		} else {
+			object.insert(String::from("data"), Value::Object(data));
+		}
+	}
+
+	let mut size = 0;
+	for (key, value) in entries(object, "data") {
+		let decoded = value
+			.as_str()
+			.and_then(|text| STANDARD.decode(text).ok())
+			.ok_or_else(|| invalid(object, &format!("data.{key}"), BASE64))?;
+
+		size += decoded.len();
+	}
+
+	Ok(size)
+}
+
+/// Returns the size of the values in both `data` and `binaryData`
+fn config_map(object: &Value) -> Result<usize, Error> {
+	let invalid =
+		|field: String| Error::InvalidData(Box::new(Identity::from(object)), field, BASE64);
+
+	let mut size = 0;
+	// values of other types are left for the schemas to point out
+	for (_key, value) in entries(object, "data") {
+		if let Some(text) = value.as_str() {
+			size += text.len();
+		}
+	}
+
+	for (key, value) in entries(object, "binaryData") {
+		let decoded = value
+			.as_str()
+			.and_then(|text| STANDARD.decode(text).ok())
+			.ok_or_else(|| invalid(format!("binaryData.{key}")))?;
+
+		size += decoded.len();
+	}
+
+	Ok(size)
+}
+
+/// Entries of the field, where other shapes are left for the schemas to point out
+fn entries<'a>(object: &'a Value, field: &str) -> Vec<(&'a String, &'a Value)> {
+	match object.get(field) {
+		Some(Value::Object(entries)) => entries.iter().collect(),
+		_ => Vec::new(),
+	}
+}
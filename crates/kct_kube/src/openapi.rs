@@ -0,0 +1,346 @@
+use crate::error::{Reason, Root as Error};
+use crate::{Identity, Manifest, Version};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::{Draft, ValidationError, Validator};
+use k8s_openapi::api::admissionregistration::v1 as admissionregistration;
+use k8s_openapi::api::apps::v1 as apps;
+use k8s_openapi::api::autoscaling::{v1 as autoscaling_v1, v2 as autoscaling_v2};
+use k8s_openapi::api::batch::v1 as batch;
+use k8s_openapi::api::certificates::v1 as certificates;
+use k8s_openapi::api::coordination::v1 as coordination;
+use k8s_openapi::api::core::v1 as core;
+use k8s_openapi::api::discovery::v1 as discovery;
+use k8s_openapi::api::events::v1 as events;
+use k8s_openapi::api::networking::v1 as networking;
+use k8s_openapi::api::node::v1 as node;
+use k8s_openapi::api::policy::v1 as policy;
+use k8s_openapi::api::rbac::v1 as rbac;
+use k8s_openapi::api::scheduling::v1 as scheduling;
+use k8s_openapi::api::storage::v1 as storage;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1 as apiextensions;
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::kube_aggregator::pkg::apis::apiregistration::v1 as apiregistration;
+use k8s_openapi::Resource;
+use schemars::gen::SchemaSettings;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+use tracing::warn;
+
+/// Version of the schemas generated from the Kubernetes API types we're built against
+pub const BUNDLED: Version = Version::new(1, 26);
+
+type Key = (String, String);
+
+type Generate = fn() -> Value;
+
+macro_rules! registry {
+	($($kind:ty),* $(,)?) => {
+		vec![$((
+			<$kind as Resource>::API_VERSION,
+			<$kind as Resource>::KIND,
+			generate::<$kind> as Generate,
+		)),*]
+	};
+}
+
+fn bundled() -> Vec<(&'static str, &'static str, Generate)> {
+	registry![
+		admissionregistration::MutatingWebhookConfiguration,
+		admissionregistration::ValidatingWebhookConfiguration,
+		apiextensions::CustomResourceDefinition,
+		apiregistration::APIService,
+		apps::ControllerRevision,
+		apps::DaemonSet,
+		apps::Deployment,
+		apps::ReplicaSet,
+		apps::StatefulSet,
+		autoscaling_v1::HorizontalPodAutoscaler,
+		autoscaling_v2::HorizontalPodAutoscaler,
+		batch::CronJob,
+		batch::Job,
+		certificates::CertificateSigningRequest,
+		coordination::Lease,
+		core::ConfigMap,
+		core::Endpoints,
+		core::Event,
+		core::LimitRange,
+		core::Namespace,
+		core::Node,
+		core::PersistentVolume,
+		core::PersistentVolumeClaim,
+		core::Pod,
+		core::PodTemplate,
+		core::ReplicationController,
+		core::ResourceQuota,
+		core::Secret,
+		core::Service,
+		core::ServiceAccount,
+		discovery::EndpointSlice,
+		events::Event,
+		networking::Ingress,
+		networking::IngressClass,
+		networking::NetworkPolicy,
+		node::RuntimeClass,
+		policy::PodDisruptionBudget,
+		rbac::ClusterRole,
+		rbac::ClusterRoleBinding,
+		rbac::Role,
+		rbac::RoleBinding,
+		scheduling::PriorityClass,
+		storage::CSIDriver,
+		storage::CSINode,
+		storage::CSIStorageCapacity,
+		storage::StorageClass,
+		storage::VolumeAttachment,
+	]
+}
+
+fn generate<T: JsonSchema>() -> Value {
+	let root = SchemaSettings::draft07()
+		.into_generator()
+		.into_root_schema_for::<T>();
+	let mut schema = serde_json::to_value(root).unwrap();
+
+	if let Some(map) = schema.as_object_mut() {
+		map.remove("$schema");
+	}
+	let quantity = format!("/definitions/{}", Quantity::schema_name());
+	if let Some(quantity) = schema.pointer_mut(&quantity) {
+		quantity["type"] = json!(["string", "number"]);
+	}
+	loosen(&mut schema);
+
+	schema
+}
+
+/// Makes the generated schemas accept what the API server does, where `int-or-string` fields
+/// take both types instead of only strings
+fn loosen(schema: &mut Value) {
+	match schema {
+		Value::Object(map) => {
+			if map.get("format").and_then(|f| f.as_str()) == Some("int-or-string") {
+				map.remove("type");
+			}
+
+			map.values_mut().for_each(loosen);
+		}
+		Value::Array(items) => items.iter_mut().for_each(loosen),
+		_ => (),
+	}
+}
+
+/// An object that doesn't match the schema for its kind
+#[derive(Debug)]
+pub struct Violation {
+	pub path: PathBuf,
+	pub identity: Identity,
+	pub pointer: String,
+	pub message: String,
+}
+
+impl fmt::Display for Violation {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let pointer = if self.pointer.is_empty() {
+			"/"
+		} else {
+			&self.pointer
+		};
+
+		write!(
+			f,
+			"{} ({}) at {pointer}: {}",
+			self.path.display(),
+			self.identity,
+			self.message
+		)
+	}
+}
+
+#[derive(Debug)]
+pub struct Violations(pub Vec<Violation>);
+
+impl fmt::Display for Violations {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0.iter().try_for_each(|v| writeln!(f, "\t- {v}"))
+	}
+}
+
+/// Schemas used to validate objects without reaching for a cluster
+///
+/// Built-in kinds use the schemas bundled for the target version, while custom resources use the
+/// schemas from the CRDs rendered along with them or provided from a directory.
+pub struct Schemas {
+	version: Version,
+	custom: HashMap<Key, Value>,
+}
+
+impl Schemas {
+	/// Fails for versions other than [`BUNDLED`], as validating against the wrong schemas would
+	/// report errors the cluster doesn't have and miss the ones it does
+	pub fn new(version: Version) -> Result<Self, Error> {
+		if version != BUNDLED {
+			return Err(Error::Unbundled(version, BUNDLED));
+		}
+
+		Ok(Schemas {
+			version,
+			custom: HashMap::new(),
+		})
+	}
+
+	pub fn version(&self) -> &Version {
+		&self.version
+	}
+
+	/// Adds the schemas from CRDs in JSON or YAML files at the directory
+	pub fn with_crds(mut self, dir: &Path) -> Result<Self, Error> {
+		for file in files(dir)? {
+			let unreadable = |err: Reason| Error::Schemas(file.to_path_buf(), err);
+			let contents = fs::read_to_string(&file).map_err(|e| unreadable(e.into()))?;
+
+			for document in serde_yaml::Deserializer::from_str(&contents) {
+				let value = Value::deserialize(document).map_err(|e| unreadable(e.into()))?;
+
+				self.custom.extend(crd_schemas(&value));
+			}
+		}
+
+		Ok(self)
+	}
+
+	pub fn validate(&self, manifests: &[Manifest]) -> Result<(), Error> {
+		let mut custom = self.custom.clone();
+		for manifest in manifests {
+			custom.extend(crd_schemas(manifest.value()));
+		}
+
+		let bundled: HashMap<Key, Generate> = bundled()
+			.into_iter()
+			.map(|(api_version, kind, generate)| ((api_version.into(), kind.into()), generate))
+			.collect();
+
+		let mut compiled: HashMap<Key, Validator> = HashMap::new();
+		let mut violations = vec![];
+
+		for manifest in manifests {
+			let value = manifest.value();
+			let identity: Identity = value.into();
+			let key = (identity.api_version.clone(), identity.kind.clone());
+
+			if !compiled.contains_key(&key) {
+				let schema = match (custom.get(&key), bundled.get(&key)) {
+					(Some(schema), _) => schema.clone(),
+					(None, Some(generate)) => generate(),
+					(None, None) => {
+						warn!("No schema found for {identity}, skipping its validation");
+						continue;
+					}
+				};
+
+				let validator = jsonschema::options()
+					.with_draft(Draft::Draft7)
+					.build(&schema)
+					.map_err(|err| {
+						Error::InvalidSchema(key.0.clone(), key.1.clone(), Box::new(err.to_owned()))
+					})?;
+
+				compiled.insert(key.clone(), validator);
+			}
+
+			violations.extend(compiled[&key].iter_errors(value).map(|err| Violation {
+				path: manifest.path().to_path_buf(),
+				identity: identity.clone(),
+				pointer: pointer(&err),
+				message: err.to_string(),
+			}));
+		}
+
+		if violations.is_empty() {
+			Ok(())
+		} else {
+			Err(Error::Invalid(Violations(violations)))
+		}
+	}
+}
+
+/// Where the object breaks the schema, with missing properties pointed at directly instead of at
+/// the object lacking them
+pub(crate) fn pointer(err: &ValidationError) -> String {
+	let pointer = err.instance_path.to_string();
+
+	match &err.kind {
+		ValidationErrorKind::Required {
+			property: Value::String(property),
+		} => {
+			let escaped = property.replace('~', "~0").replace('/', "~1");
+
+			format!("{pointer}/{escaped}")
+		}
+		_ => pointer,
+	}
+}
+
+fn files(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+	let unreadable = |err: std::io::Error| Error::Schemas(dir.to_path_buf(), err.into());
+	let entries = fs::read_dir(dir).map_err(unreadable)?;
+
+	let mut found = vec![];
+	for entry in entries {
+		let path = entry.map_err(unreadable)?.path();
+
+		let extension = path.extension().and_then(|e| e.to_str());
+		if path.is_dir() {
+			found.extend(files(&path)?);
+		} else if matches!(extension, Some("json" | "yaml" | "yml")) {
+			found.push(path);
+		}
+	}
+
+	found.sort();
+	Ok(found)
+}
+
+fn crd_schemas(value: &Value) -> Vec<(Key, Value)> {
+	let is_crd = value.get("apiVersion").and_then(|v| v.as_str())
+		== Some("apiextensions.k8s.io/v1")
+		&& value.get("kind").and_then(|v| v.as_str()) == Some("CustomResourceDefinition");
+	if !is_crd {
+		return vec![];
+	}
+
+	let spec = value.get("spec");
+	let group = spec.and_then(|s| s.get("group")).and_then(|g| g.as_str());
+	let kind = spec
+		.and_then(|s| s.get("names"))
+		.and_then(|n| n.get("kind"))
+		.and_then(|k| k.as_str());
+	let versions = spec
+		.and_then(|s| s.get("versions"))
+		.and_then(|v| v.as_array());
+
+	let (group, kind, versions) = match (group, kind, versions) {
+		(Some(group), Some(kind), Some(versions)) => (group, kind, versions),
+		_ => return vec![],
+	};
+
+	versions
+		.iter()
+		.filter_map(|version| {
+			let name = version.get("name").and_then(|n| n.as_str())?;
+			let schema = version
+				.get("schema")
+				.and_then(|s| s.get("openAPIV3Schema"))
+				.cloned()
+				.unwrap_or_else(|| Value::Object(Map::new()));
+
+			Some(((format!("{group}/{name}"), kind.to_string()), schema))
+		})
+		.collect()
+}
@@ -0,0 +1,388 @@
+use crate::error::Root as Error;
+use crate::{Identity, Manifest};
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use json_patch::Patch as JsonPatch;
+use serde::Deserialize;
+use serde_json::Value;
+use tracing::warn;
+
+const DIRECTIVE: &str = "$patch";
+
+/// Which objects a patch applies to, every field given must match
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Target {
+	path: Option<PathBuf>,
+	api_version: Option<String>,
+	kind: Option<String>,
+	name: Option<String>,
+	namespace: Option<String>,
+}
+
+impl Target {
+	/// Target taken from the identity fields present in a strategic merge patch
+	fn from_patch(patch: &Value) -> Self {
+		let text = |v: Option<&Value>| v.and_then(|v| v.as_str()).map(String::from);
+		let metadata = patch.get("metadata");
+
+		Target {
+			path: None,
+			api_version: text(patch.get("apiVersion")),
+			kind: text(patch.get("kind")),
+			name: text(metadata.and_then(|m| m.get("name"))),
+			namespace: text(metadata.and_then(|m| m.get("namespace"))),
+		}
+	}
+
+	fn is_empty(&self) -> bool {
+		self.path.is_none()
+			&& self.api_version.is_none()
+			&& self.kind.is_none()
+			&& self.name.is_none()
+			&& self.namespace.is_none()
+	}
+
+	fn matches(&self, path: &Path, object: &Value) -> bool {
+		let identity = Identity::from(object);
+		let equals = |expected: &Option<String>, actual: Option<&str>| match expected {
+			Some(expected) => actual == Some(expected.as_str()),
+			None => true,
+		};
+
+		self.path.as_ref().is_none_or(|p| path.starts_with(p))
+			&& equals(&self.api_version, Some(&identity.api_version))
+			&& equals(&self.kind, Some(&identity.kind))
+			&& equals(&self.name, identity.name.as_deref())
+			&& equals(&self.namespace, identity.namespace.as_deref())
+	}
+}
+
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Type {
+	#[default]
+	Strategic,
+	Merge,
+	Json,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Envelope {
+	#[serde(default)]
+	target: Target,
+	#[serde(default, rename = "type")]
+	kind: Type,
+	patch: Value,
+}
+
+enum Operation {
+	Strategic(Value),
+	Merge(Value),
+	Json(JsonPatch),
+}
+
+struct Patch {
+	source: PathBuf,
+	target: Target,
+	operation: Operation,
+}
+
+impl Patch {
+	fn new(source: &Path, document: Value) -> Result<Self, Error> {
+		let invalid = |reason: String| Error::Patch(source.to_path_buf(), reason);
+
+		if document.get("patch").is_none() {
+			return Patch::strategic(source, Target::from_patch(&document), document);
+		}
+
+		let envelope: Envelope =
+			serde_json::from_value(document).map_err(|e| invalid(e.to_string()))?;
+
+		let target = match (envelope.kind, envelope.target.is_empty()) {
+			(Type::Strategic, true) => Target::from_patch(&envelope.patch),
+			_ => envelope.target,
+		};
+
+		let operation = match envelope.kind {
+			Type::Strategic => return Patch::strategic(source, target, envelope.patch),
+			Type::Merge => Operation::Merge(envelope.patch),
+			Type::Json => {
+				let operations =
+					serde_json::from_value(envelope.patch).map_err(|e| invalid(e.to_string()))?;
+
+				Operation::Json(operations)
+			}
+		};
+
+		Ok(Patch {
+			source: source.to_path_buf(),
+			target,
+			operation,
+		})
+	}
+
+	/// Strategic patches pick their merge keys by kind, so they must target a single kind and name
+	/// instead of spreading over whatever objects happen to match
+	fn strategic(source: &Path, target: Target, patch: Value) -> Result<Self, Error> {
+		if target.kind.is_none() || target.name.is_none() {
+			return Err(Error::Patch(
+				source.to_path_buf(),
+				String::from("strategic patches need a kind and a metadata.name"),
+			));
+		}
+
+		Ok(Patch {
+			source: source.to_path_buf(),
+			target,
+			operation: Operation::Strategic(patch),
+		})
+	}
+
+	fn apply(&self, object: &mut Value) -> Result<(), Error> {
+		match &self.operation {
+			Operation::Strategic(patch) => {
+				let kind = builtin(object)
+					.then(|| object.get("kind").and_then(|k| k.as_str()))
+					.flatten()
+					.map(String::from);
+
+				strategic(object, patch, &mut vec![], kind.as_deref());
+			}
+			Operation::Merge(patch) => json_patch::merge(object, patch),
+			Operation::Json(patch) => json_patch::patch(object, patch)
+				.map_err(|e| Error::Patch(self.source.clone(), e.to_string()))?,
+		}
+
+		Ok(())
+	}
+}
+
+/// Changes made to the rendered objects that the package doesn't expose as inputs
+///
+/// Patches are read from JSON or YAML files, where each document is either a partial object to
+/// merge strategically into the object it identifies, or a `patch` along with its `type` and the
+/// `target` selecting objects by tracking path, `apiVersion`, `kind`, `name`, and `namespace`.
+/// The types are `strategic`, `merge` for RFC 7386, and `json` for RFC 6902.
+#[derive(Default)]
+pub struct Patches(Vec<Patch>);
+
+impl Patches {
+	/// Adds the patches from the JSON or YAML files at the directory
+	pub fn with_dir(self, dir: &Path) -> Result<Self, Error> {
+		let entries = fs::read_dir(dir).map_err(|_err| Error::Patches(dir.to_path_buf()))?;
+
+		let mut paths = vec![];
+		for entry in entries {
+			let path = entry
+				.map_err(|_err| Error::Patches(dir.to_path_buf()))?
+				.path();
+
+			let extension = path.extension().and_then(|e| e.to_str());
+			if path.is_file() && matches!(extension, Some("json" | "yaml" | "yml")) {
+				paths.push(path);
+			}
+		}
+		paths.sort();
+
+		paths
+			.iter()
+			.try_fold(self, |patches, path| patches.with_file(path))
+	}
+
+	pub fn with_file(mut self, file: &Path) -> Result<Self, Error> {
+		let invalid = |reason: String| Error::Patch(file.to_path_buf(), reason);
+		let contents = fs::read_to_string(file).map_err(|e| invalid(e.to_string()))?;
+
+		for document in serde_yaml::Deserializer::from_str(&contents) {
+			let value = Value::deserialize(document).map_err(|e| invalid(e.to_string()))?;
+			if value.is_null() {
+				continue;
+			}
+
+			self.0.push(Patch::new(file, value)?);
+		}
+
+		Ok(self)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Applies the patches in order, the ones without a match are skipped since the objects could
+	/// have been filtered out
+	pub fn apply(&self, manifests: &mut [Manifest]) -> Result<(), Error> {
+		for patch in &self.0 {
+			let mut matched = false;
+
+			for manifest in manifests.iter_mut() {
+				if patch.target.matches(manifest.path(), manifest.value()) {
+					patch.apply(manifest.value_mut())?;
+					matched = true;
+				}
+			}
+
+			if !matched {
+				warn!(
+					"Patch from {} doesn't match any object",
+					patch.source.display()
+				);
+			}
+		}
+
+		Ok(())
+	}
+}
+
+/// Whether the object belongs to Kubernetes itself, so its lists have known merge keys
+fn builtin(object: &Value) -> bool {
+	let api_version = object
+		.get("apiVersion")
+		.and_then(|v| v.as_str())
+		.unwrap_or_default();
+
+	match api_version.split_once('/') {
+		None => true,
+		Some((group, _)) => !group.contains('.') || group.ends_with(".k8s.io"),
+	}
+}
+
+/// Where the built-in kinds keep the spec of their pods
+fn pod_spec(kind: &str) -> Option<&'static [&'static str]> {
+	match kind {
+		"Pod" => Some(&["spec"]),
+		"PodTemplate" => Some(&["template", "spec"]),
+		"Deployment"
+		| "ReplicaSet"
+		| "StatefulSet"
+		| "DaemonSet"
+		| "ReplicationController"
+		| "Job" => Some(&["spec", "template", "spec"]),
+		"CronJob" => Some(&["spec", "jobTemplate", "spec", "template", "spec"]),
+		_ => None,
+	}
+}
+
+/// Key identifying the elements of a list at the path of a built-in kind, lists without one are
+/// replaced
+fn merge_key(kind: &str, path: &[String]) -> Option<&'static str> {
+	let path: Vec<&str> = path.iter().map(String::as_str).collect();
+
+	if kind == "Service" && path == ["spec", "ports"] {
+		return Some("port");
+	}
+
+	let within = pod_spec(kind).and_then(|prefix| path.strip_prefix(prefix))?;
+	match within {
+		["containers"
+		| "initContainers"
+		| "ephemeralContainers"
+		| "volumes"
+		| "imagePullSecrets"] => Some("name"),
+		["hostAliases"] => Some("ip"),
+		["topologySpreadConstraints"] => Some("topologyKey"),
+		["containers" | "initContainers" | "ephemeralContainers", field] => match *field {
+			"env" => Some("name"),
+			"ports" => Some("containerPort"),
+			"volumeMounts" => Some("mountPath"),
+			"volumeDevices" => Some("devicePath"),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+fn directive(value: &Value) -> Option<&str> {
+	value.get(DIRECTIVE).and_then(|d| d.as_str())
+}
+
+/// Removes the directives from a patch before it's placed into an object
+fn clean(patch: &Value) -> Value {
+	match patch {
+		Value::Object(map) => Value::Object(
+			map.iter()
+				.filter(|(key, _)| key.as_str() != DIRECTIVE)
+				.map(|(key, value)| (key.clone(), clean(value)))
+				.collect(),
+		),
+		Value::Array(items) => Value::Array(
+			items
+				.iter()
+				.filter(|item| directive(item) != Some("delete"))
+				.map(clean)
+				.collect(),
+		),
+		other => other.clone(),
+	}
+}
+
+/// Simplified strategic merge following the `patchMergeKey` of the most common built-in lists
+///
+/// Maps are merged with `null` removing keys and `$patch: replace` replacing them, lists with a
+/// merge key are merged by it with `$patch: delete` removing elements, and other lists are
+/// replaced. The path holds the fields leading to the value, leaving out list positions, and the
+/// kind is only given for built-in objects.
+fn strategic(target: &mut Value, patch: &Value, path: &mut Vec<String>, kind: Option<&str>) {
+	match patch {
+		Value::Object(changes) => {
+			if directive(patch) == Some("replace") || !target.is_object() {
+				*target = clean(patch);
+
+				return;
+			}
+
+			let map = target.as_object_mut().unwrap();
+			for (key, value) in changes {
+				if key == DIRECTIVE {
+					continue;
+				}
+
+				if value.is_null() {
+					map.remove(key);
+				} else {
+					let entry = map.entry(key.clone()).or_insert(Value::Null);
+
+					path.push(key.clone());
+					strategic(entry, value, path, kind);
+					path.pop();
+				}
+			}
+		}
+		Value::Array(items) => {
+			let key = kind.and_then(|kind| merge_key(kind, path));
+
+			match (key, target.as_array_mut()) {
+				(Some(key), Some(existing)) => merge_list(existing, items, key, path, kind),
+				_ => *target = clean(patch),
+			}
+		}
+		other => *target = other.clone(),
+	}
+}
+
+fn merge_list(
+	existing: &mut Vec<Value>,
+	items: &[Value],
+	key: &str,
+	path: &mut Vec<String>,
+	kind: Option<&str>,
+) {
+	for item in items {
+		let position = item
+			.get(key)
+			.and_then(|id| existing.iter().position(|e| e.get(key) == Some(id)));
+
+		match (directive(item), position) {
+			(Some("delete"), Some(position)) => {
+				existing.remove(position);
+			}
+			(Some("delete"), None) => (),
+			(_, Some(position)) => strategic(&mut existing[position], item, path, kind),
+			(_, None) => existing.push(clean(item)),
+		}
+	}
+}
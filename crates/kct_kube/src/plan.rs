@@ -0,0 +1,126 @@
+use crate::{Identity, Tracking};
+
+use std::fmt;
+use std::path::PathBuf;
+
+use serde_json::Value;
+
+const CRD_API_VERSION: &str = "apiextensions.k8s.io/v1";
+const CRD_KIND: &str = "CustomResourceDefinition";
+
+/// Which pass of the client applies an object
+///
+/// CRDs go first and on their own because the discovery needs to be refreshed before applying
+/// the custom resources that depend on them, everything else goes afterwards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Pass {
+	Crds,
+	Dynamics,
+}
+
+impl Pass {
+	pub fn all() -> [Pass; 2] {
+		[Pass::Crds, Pass::Dynamics]
+	}
+
+	pub fn as_str(&self) -> &str {
+		match self {
+			Pass::Crds => "crds",
+			Pass::Dynamics => "dynamics",
+		}
+	}
+}
+
+impl From<&Value> for Pass {
+	fn from(value: &Value) -> Self {
+		let api_version = value.get("apiVersion").and_then(|v| v.as_str());
+		let kind = value.get("kind").and_then(|v| v.as_str());
+
+		match (api_version, kind) {
+			(Some(CRD_API_VERSION), Some(CRD_KIND)) => Pass::Crds,
+			_ => Pass::Dynamics,
+		}
+	}
+}
+
+/// An object in the order it will be applied along with the reasons for its position
+#[derive(Debug)]
+pub struct Step {
+	pub path: PathBuf,
+	pub identity: Identity,
+	pub tracking: Tracking,
+	pub pass: Pass,
+	pub wave: usize,
+}
+
+/// The sequence of steps taken to install a package
+///
+/// Objects within a pass are applied concurrently, so each pass with objects becomes a wave, and
+/// the waves are applied one after the other.
+#[derive(Debug, Default)]
+pub struct Plan(Vec<Step>);
+
+impl Plan {
+	pub fn new(traced: Vec<(Tracking, Value)>) -> Self {
+		let passes: Vec<Pass> = traced.iter().map(|(_, value)| value.into()).collect();
+		let waves: Vec<Pass> = Pass::all()
+			.into_iter()
+			.filter(|pass| passes.contains(pass))
+			.collect();
+
+		let steps = traced
+			.into_iter()
+			.zip(passes)
+			.map(|((tracking, value), pass)| Step {
+				path: (&tracking).into(),
+				identity: (&value).into(),
+				tracking,
+				pass,
+				wave: waves.iter().position(|p| *p == pass).unwrap() + 1,
+			})
+			.collect();
+
+		Plan(steps)
+	}
+
+	pub fn steps(&self) -> &[Step] {
+		&self.0
+	}
+}
+
+impl fmt::Display for Step {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(
+			f,
+			"{} ({}) [wave {}: {}]",
+			self.path.display(),
+			self.identity,
+			self.wave,
+			self.pass.as_str()
+		)?;
+
+		for track in self.tracking.tracks() {
+			write!(
+				f,
+				"\t{}: depth={} order={}",
+				track.field, track.depth, track.order
+			)?;
+
+			match &track.kind {
+				Some(kind) => writeln!(f, " kind={} priority={}", kind.name(), kind.priority())?,
+				None => writeln!(f)?,
+			}
+		}
+
+		Ok(())
+	}
+}
+
+impl fmt::Display for Plan {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0
+			.iter()
+			.enumerate()
+			.try_for_each(|(i, step)| write!(f, "{}. {step}", i + 1))
+	}
+}
@@ -0,0 +1,283 @@
+use crate::error::{Reason, Root as Error};
+use crate::openapi;
+use crate::{Identity, Manifest};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use jsonschema::{Draft, Validator};
+use kct_jsonnet::property::Property;
+use kct_jsonnet::Executable;
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Name of the external variable holding the object under review in Jsonnet rules
+const OBJECT_VAR: &str = "object";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+	Warn,
+	Deny,
+}
+
+impl FromStr for Severity {
+	type Err = ();
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"warn" => Ok(Severity::Warn),
+			"deny" => Ok(Severity::Deny),
+			_ => Err(()),
+		}
+	}
+}
+
+impl fmt::Display for Severity {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Severity::Warn => write!(f, "warn"),
+			Severity::Deny => write!(f, "deny"),
+		}
+	}
+}
+
+enum Check {
+	Jsonnet(PathBuf),
+	Schema(Box<Validator>),
+}
+
+struct Rule {
+	name: String,
+	severity: Severity,
+	kinds: Vec<String>,
+	message: Option<String>,
+	check: Check,
+}
+
+/// A rule broken by an object
+#[derive(Debug)]
+pub struct Finding {
+	pub rule: String,
+	pub severity: Severity,
+	pub path: PathBuf,
+	pub identity: Identity,
+	pub pointer: Option<String>,
+	pub message: String,
+}
+
+impl fmt::Display for Finding {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"[{}] {}: {} ({})",
+			self.severity,
+			self.rule,
+			self.path.display(),
+			self.identity
+		)?;
+
+		match &self.pointer {
+			Some(pointer) if !pointer.is_empty() => write!(f, " at {pointer}: {}", self.message),
+			_ => write!(f, ": {}", self.message),
+		}
+	}
+}
+
+#[derive(Debug, Default)]
+pub struct Findings(pub Vec<Finding>);
+
+impl Findings {
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+
+	/// Whether any of the findings should stop the objects from reaching the cluster
+	pub fn denied(&self) -> bool {
+		self.0.iter().any(|f| f.severity == Severity::Deny)
+	}
+}
+
+impl fmt::Display for Findings {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		self.0.iter().try_for_each(|v| writeln!(f, "\t- {v}"))
+	}
+}
+
+#[derive(Deserialize)]
+struct SchemaRule {
+	severity: Option<String>,
+	#[serde(default)]
+	kinds: Vec<String>,
+	message: Option<String>,
+	schema: Value,
+}
+
+#[derive(Deserialize)]
+struct JsonnetOutput {
+	severity: Option<String>,
+	message: Option<String>,
+	#[serde(default)]
+	violations: Vec<String>,
+}
+
+/// Organization rules enforced on the rendered objects
+///
+/// Each file in the policy directory is a rule named after it. JSON and YAML files hold a JSON
+/// Schema that the objects of the selected kinds must match, while Jsonnet files receive every
+/// object through `std.extVar("kct.io/object")` and list the violations they find. Both choose
+/// whether breaking them warns or denies, which defaults to deny.
+pub struct Policies(Vec<Rule>);
+
+impl Policies {
+	pub fn load(dir: &Path) -> Result<Self, Error> {
+		let entries = fs::read_dir(dir).map_err(|_err| Error::Policies(dir.to_path_buf()))?;
+
+		let mut paths = vec![];
+		for entry in entries {
+			let path = entry
+				.map_err(|_err| Error::Policies(dir.to_path_buf()))?
+				.path();
+
+			if path.is_file() {
+				paths.push(path);
+			}
+		}
+		paths.sort();
+
+		let mut rules = vec![];
+		for path in paths {
+			let extension = path.extension().and_then(|e| e.to_str());
+			let name = path
+				.file_stem()
+				.map(|s| s.to_string_lossy().to_string())
+				.unwrap_or_default();
+
+			let rule = match extension {
+				Some("jsonnet") => Rule {
+					name,
+					severity: Severity::Deny,
+					kinds: vec![],
+					message: None,
+					check: Check::Jsonnet(path),
+				},
+				Some("json" | "yaml" | "yml") => {
+					let invalid = |err: Reason| Error::Rule(name.clone(), err);
+
+					let contents = fs::read_to_string(&path).map_err(|e| invalid(e.into()))?;
+					let rule: SchemaRule =
+						serde_yaml::from_str(&contents).map_err(|e| invalid(e.into()))?;
+					let validator = jsonschema::options()
+						.with_draft(Draft::Draft7)
+						.build(&rule.schema)
+						.map_err(|e| invalid(e.to_owned().into()))?;
+
+					Rule {
+						severity: severity(&name, rule.severity.as_deref())?,
+						kinds: rule.kinds,
+						message: rule.message,
+						check: Check::Schema(Box::new(validator)),
+						name,
+					}
+				}
+				_ => continue,
+			};
+
+			rules.push(rule);
+		}
+
+		Ok(Policies(rules))
+	}
+
+	pub fn check(&self, manifests: &[Manifest]) -> Result<Findings, Error> {
+		let mut findings = vec![];
+
+		for rule in &self.0 {
+			for manifest in manifests {
+				let value = manifest.value();
+				if !rule.applies(value) {
+					continue;
+				}
+
+				let finding = |pointer: Option<String>, message: String| Finding {
+					rule: rule.name.clone(),
+					severity: rule.severity,
+					path: manifest.path().to_path_buf(),
+					identity: value.into(),
+					pointer,
+					message,
+				};
+
+				match &rule.check {
+					Check::Schema(validator) => {
+						findings.extend(validator.iter_errors(value).map(|err| {
+							let message = rule.message.clone().unwrap_or_else(|| err.to_string());
+
+							finding(Some(openapi::pointer(&err)), message)
+						}));
+					}
+					Check::Jsonnet(path) => {
+						let output = rule.evaluate(path, value)?;
+						let severity = severity(&rule.name, output.severity.as_deref())?;
+						let message = output.message;
+
+						findings.extend(output.violations.into_iter().map(|violation| {
+							let message = match &message {
+								Some(message) => format!("{message} ({violation})"),
+								None => violation,
+							};
+
+							Finding {
+								severity,
+								..finding(None, message)
+							}
+						}));
+					}
+				}
+			}
+		}
+
+		Ok(Findings(findings))
+	}
+}
+
+impl Rule {
+	fn applies(&self, object: &Value) -> bool {
+		let kind = object
+			.get("kind")
+			.and_then(|k| k.as_str())
+			.unwrap_or_default();
+
+		self.kinds.is_empty() || self.kinds.iter().any(|k| k == kind)
+	}
+
+	fn evaluate(&self, path: &Path, object: &Value) -> Result<JsonnetOutput, Error> {
+		let invalid = |err: Reason| Error::Rule(self.name.clone(), err);
+		let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+
+		let executable = Executable {
+			vendor: dir.clone(),
+			lib: dir,
+			main: path.to_path_buf(),
+			props: HashMap::from([(
+				String::from(OBJECT_VAR),
+				Property::Primitive(object.clone()),
+			)]),
+		};
+
+		let output = executable.run().map_err(|e| invalid(e.into()))?;
+
+		serde_json::from_value(output).map_err(|e| invalid(e.into()))
+	}
+}
+
+fn severity(rule: &str, severity: Option<&str>) -> Result<Severity, Error> {
+	match severity {
+		None => Ok(Severity::Deny),
+		Some(s) => s
+			.parse()
+			.map_err(|_| Error::Rule(rule.to_string(), format!("unknown severity \"{s}\"").into())),
+	}
+}
@@ -0,0 +1,105 @@
+use crate::Manifest;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+/// Amount of hexadecimal digits from the hash kept in the masks
+const HASH_SIZE: usize = 12;
+
+/// Length from which sensitive values are masked inside text, as shorter ones like `true` or `80`
+/// would mask unrelated parts of it, so they're only masked where they're a whole value
+const MIN_LENGTH: usize = 6;
+
+/// Masks secrets before they leave the tool
+///
+/// Values of Secrets, in both `data` and `stringData`, are replaced by a hash of their contents,
+/// as are the sensitive values found anywhere in objects or text. Short sensitive values are only
+/// masked in objects, where a string equal to them is replaced whole. Hashes are stable so
+/// changes still show up when comparing renders, but the values themselves don't.
+#[derive(Debug, Default, Clone)]
+pub struct Redactor {
+	sensitive: Vec<String>,
+}
+
+impl Redactor {
+	/// Adds values to mask wherever they appear, such as inputs the package marks as sensitive
+	pub fn with_sensitive(mut self, values: impl IntoIterator<Item = String>) -> Self {
+		self.sensitive
+			.extend(values.into_iter().filter(|v| !v.is_empty()));
+
+		// longer values go first so the ones containing others are masked whole
+		self.sensitive
+			.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+		self.sensitive.dedup();
+
+		self
+	}
+
+	/// Stable replacement for a secret value
+	pub fn mask(value: &str) -> String {
+		let digest = format!("{:x}", Sha256::digest(value.as_bytes()));
+
+		format!("<redacted:{}>", &digest[..HASH_SIZE])
+	}
+
+	pub fn apply(&self, manifests: &mut [Manifest]) {
+		for manifest in manifests.iter_mut() {
+			self.value(manifest.value_mut());
+		}
+	}
+
+	pub fn value(&self, object: &mut Value) {
+		let is_secret = object.get("kind").and_then(|k| k.as_str()) == Some("Secret");
+
+		if is_secret {
+			for field in ["data", "stringData"] {
+				let entries = object
+					.get_mut(field)
+					.and_then(|d| d.as_object_mut())
+					.into_iter()
+					.flat_map(|d| d.values_mut());
+
+				for entry in entries {
+					if let Value::String(value) = entry {
+						*value = Self::mask(value);
+					}
+				}
+			}
+		}
+
+		self.strings(object);
+	}
+
+	/// Masks the sensitive values long enough to be told apart inside the text
+	pub fn text(&self, text: &str) -> String {
+		self.sensitive
+			.iter()
+			.filter(|value| value.len() >= MIN_LENGTH)
+			.fold(text.to_string(), |text, value| match text.contains(value) {
+				true => text.replace(value, &Self::mask(value)),
+				false => text,
+			})
+	}
+
+	/// Masks the strings that are sensitive values, whatever their length, the ones holding
+	/// sensitive values, and the numbers rendered as long enough ones
+	fn strings(&self, value: &mut Value) {
+		if self.sensitive.is_empty() {
+			return;
+		}
+
+		match value {
+			Value::String(text) if self.sensitive.contains(text) => *text = Self::mask(text),
+			Value::String(text) => *text = self.text(text),
+			Value::Number(number) => {
+				let rendered = number.to_string();
+				if rendered.len() >= MIN_LENGTH && self.sensitive.contains(&rendered) {
+					*value = Value::String(Self::mask(&rendered));
+				}
+			}
+			Value::Array(items) => items.iter_mut().for_each(|item| self.strings(item)),
+			Value::Object(map) => map.values_mut().for_each(|item| self.strings(item)),
+			_ => (),
+		}
+	}
+}
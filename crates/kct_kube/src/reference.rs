@@ -0,0 +1,341 @@
+use serde_json::{Map, Value};
+
+const CLUSTER_KINDS: [&str; 1] = ["ClusterRole"];
+
+/// A pointer from one object to another by its kind and name
+///
+/// They're what binds objects together in the cluster, e.g. a Deployment mounting a ConfigMap or
+/// a RoleBinding granting permissions to a ServiceAccount. The namespace is the one where the
+/// target should live, which is usually the same as the referrer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reference {
+	pub kind: String,
+	pub namespace: Option<String>,
+	pub name: String,
+	pub via: &'static str,
+}
+
+impl Reference {
+	fn new(kind: &str, namespace: Option<&str>, name: &str, via: &'static str) -> Self {
+		let namespace = if CLUSTER_KINDS.contains(&kind) {
+			None
+		} else {
+			namespace.map(String::from)
+		};
+
+		Reference {
+			kind: kind.to_string(),
+			namespace,
+			name: name.to_string(),
+			via,
+		}
+	}
+
+	/// Whether the object is the one this reference points to
+	pub fn targets(&self, object: &Value) -> bool {
+		let kind = object.get("kind").and_then(|k| k.as_str());
+		let metadata = object.get("metadata");
+		let name = metadata
+			.and_then(|m| m.get("name"))
+			.and_then(|n| n.as_str());
+
+		kind == Some(&self.kind)
+			&& name == Some(&self.name)
+			&& (self.namespace.is_none() || namespace(object) == self.namespace.as_deref())
+	}
+}
+
+/// Where an object keeps the name of another one
+///
+/// The pointer locates the name inside the referrer, which allows renaming the target without
+/// losing track of who depends on it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Slot {
+	pub kind: String,
+	pub namespace: Option<String>,
+	pub pointer: String,
+	pub via: &'static str,
+}
+
+/// Finds the references made by an object through its pod template, RBAC bindings, and the
+/// fields pointing to services and scaled workloads
+pub fn references(object: &Value) -> Vec<Reference> {
+	let mut found: Vec<Reference> = slots(object)
+		.into_iter()
+		.filter_map(|slot| {
+			let name = object.pointer(&slot.pointer).and_then(|n| n.as_str())?;
+
+			Some(Reference::new(
+				&slot.kind,
+				slot.namespace.as_deref(),
+				name,
+				slot.via,
+			))
+		})
+		.collect();
+
+	found.dedup();
+	found
+}
+
+/// Finds the fields holding the names of the objects referenced by an object
+pub fn slots(object: &Value) -> Vec<Slot> {
+	let ns = namespace(object);
+	let mut found = vec![];
+
+	if let Some(path) = pod_path(object) {
+		let prefix: String = path.iter().map(|key| format!("/{key}")).collect();
+
+		if let Some(spec) = object.pointer(&format!("{prefix}/spec")) {
+			found.extend(pod_slots(spec, &format!("{prefix}/spec"), ns));
+		}
+	}
+
+	let kind = object
+		.get("kind")
+		.and_then(|k| k.as_str())
+		.unwrap_or_default();
+	match kind {
+		"RoleBinding" | "ClusterRoleBinding" => found.extend(binding_slots(object, ns)),
+		"StatefulSet" => found.push(Slot::new(
+			"Service",
+			ns,
+			String::from("/spec/serviceName"),
+			"service",
+		)),
+		"HorizontalPodAutoscaler" => {
+			let target = object
+				.pointer("/spec/scaleTargetRef/kind")
+				.and_then(|k| k.as_str());
+
+			if let Some(kind) = target {
+				let pointer = String::from("/spec/scaleTargetRef/name");
+
+				found.push(Slot::new(kind, ns, pointer, "scaleTarget"));
+			}
+		}
+		"Ingress" => found.extend(ingress_slots(object, ns)),
+		_ => (),
+	}
+
+	found
+		.into_iter()
+		.filter(|slot| object.pointer(&slot.pointer).is_some())
+		.collect()
+}
+
+impl Slot {
+	fn new(kind: &str, namespace: Option<&str>, pointer: String, via: &'static str) -> Self {
+		Slot {
+			kind: kind.to_string(),
+			namespace: namespace.map(String::from),
+			pointer,
+			via,
+		}
+	}
+}
+
+/// Whether a service selects the pods created by an object
+pub fn selects(service: &Value, object: &Value) -> bool {
+	let is_service = service.get("kind").and_then(|k| k.as_str()) == Some("Service");
+	let selector = service
+		.get("spec")
+		.and_then(|s| s.get("selector"))
+		.and_then(|s| s.as_object())
+		.filter(|s| !s.is_empty());
+
+	let (selector, labels) = match (is_service, selector, pod_labels(object)) {
+		(true, Some(selector), Some(labels)) => (selector, labels),
+		_ => return false,
+	};
+
+	namespace(service) == namespace(object)
+		&& selector
+			.iter()
+			.all(|(key, value)| labels.get(key) == Some(value))
+}
+
+pub fn namespace(object: &Value) -> Option<&str> {
+	object
+		.get("metadata")
+		.and_then(|m| m.get("namespace"))
+		.and_then(|n| n.as_str())
+}
+
+/// Where the pod spec lives for the objects that create pods
+pub fn pod_spec(object: &Value) -> Option<&Value> {
+	let path = pod_path(object)?;
+
+	path.iter()
+		.try_fold(object, |value, key| value.get(key))
+		.and_then(|template| template.get("spec"))
+}
+
+/// Mutable version of [`pod_spec`]
+pub fn pod_spec_mut(object: &mut Value) -> Option<&mut Value> {
+	let path = pod_path(object)?;
+
+	path.iter()
+		.try_fold(object, |value, key| value.get_mut(key))
+		.and_then(|template| template.get_mut("spec"))
+}
+
+/// Labels from the pod template of an object, or the object itself if it's a pod
+pub fn pod_labels(object: &Value) -> Option<&Map<String, Value>> {
+	let path = pod_path(object)?;
+
+	path.iter()
+		.try_fold(object, |value, key| value.get(key))
+		.and_then(|template| template.get("metadata"))
+		.and_then(|metadata| metadata.get("labels"))
+		.and_then(|labels| labels.as_object())
+}
+
+fn pod_path(object: &Value) -> Option<&'static [&'static str]> {
+	let kind = object.get("kind").and_then(|k| k.as_str())?;
+
+	match kind {
+		"Pod" => Some(&[]),
+		"Deployment"
+		| "StatefulSet"
+		| "DaemonSet"
+		| "ReplicaSet"
+		| "ReplicationController"
+		| "Job" => Some(&["spec", "template"]),
+		"CronJob" => Some(&["spec", "jobTemplate", "spec", "template"]),
+		_ => None,
+	}
+}
+
+fn pod_slots(spec: &Value, at: &str, ns: Option<&str>) -> Vec<Slot> {
+	let len = |value: &Value, key: &str| {
+		value
+			.get(key)
+			.and_then(|v| v.as_array())
+			.map_or(0, Vec::len)
+	};
+
+	let mut found = vec![];
+	let mut push = |kind: &str, pointer: String, via: &'static str| {
+		found.push(Slot::new(kind, ns, pointer, via));
+	};
+
+	push(
+		"ServiceAccount",
+		format!("{at}/serviceAccountName"),
+		"serviceAccount",
+	);
+
+	for i in 0..len(spec, "imagePullSecrets") {
+		push(
+			"Secret",
+			format!("{at}/imagePullSecrets/{i}/name"),
+			"imagePullSecret",
+		);
+	}
+
+	for i in 0..len(spec, "volumes") {
+		let volume = format!("{at}/volumes/{i}");
+
+		push("ConfigMap", format!("{volume}/configMap/name"), "volume");
+		push("Secret", format!("{volume}/secret/secretName"), "volume");
+		push(
+			"PersistentVolumeClaim",
+			format!("{volume}/persistentVolumeClaim/claimName"),
+			"volume",
+		);
+
+		let sources = spec
+			.pointer(&format!("/volumes/{i}/projected"))
+			.map_or(0, |p| len(p, "sources"));
+		for j in 0..sources {
+			let source = format!("{volume}/projected/sources/{j}");
+
+			push("ConfigMap", format!("{source}/configMap/name"), "volume");
+			push("Secret", format!("{source}/secret/name"), "volume");
+		}
+	}
+
+	for field in ["initContainers", "containers"] {
+		for i in 0..len(spec, field) {
+			let container = &spec[field][i];
+			let at = format!("{at}/{field}/{i}");
+
+			for j in 0..len(container, "envFrom") {
+				push(
+					"ConfigMap",
+					format!("{at}/envFrom/{j}/configMapRef/name"),
+					"env",
+				);
+				push("Secret", format!("{at}/envFrom/{j}/secretRef/name"), "env");
+			}
+
+			for j in 0..len(container, "env") {
+				let from = format!("{at}/env/{j}/valueFrom");
+
+				push("ConfigMap", format!("{from}/configMapKeyRef/name"), "env");
+				push("Secret", format!("{from}/secretKeyRef/name"), "env");
+			}
+		}
+	}
+
+	found
+}
+
+fn binding_slots(binding: &Value, ns: Option<&str>) -> Vec<Slot> {
+	let mut found = vec![];
+
+	let subjects = binding
+		.get("subjects")
+		.and_then(|s| s.as_array())
+		.cloned()
+		.unwrap_or_default();
+	for (i, subject) in subjects.iter().enumerate() {
+		let kind = subject.get("kind").and_then(|k| k.as_str());
+
+		if kind == Some("ServiceAccount") {
+			let namespace = subject.get("namespace").and_then(|n| n.as_str()).or(ns);
+			let pointer = format!("/subjects/{i}/name");
+
+			found.push(Slot::new("ServiceAccount", namespace, pointer, "subject"));
+		}
+	}
+
+	let kind = binding.pointer("/roleRef/kind").and_then(|k| k.as_str());
+	if let Some(kind) = kind {
+		let pointer = String::from("/roleRef/name");
+
+		found.push(Slot::new(kind, ns, pointer, "roleRef"));
+	}
+
+	found
+}
+
+fn ingress_slots(ingress: &Value, ns: Option<&str>) -> Vec<Slot> {
+	let mut found = vec![Slot::new(
+		"Service",
+		ns,
+		String::from("/spec/defaultBackend/service/name"),
+		"backend",
+	)];
+
+	let rules = ingress
+		.pointer("/spec/rules")
+		.and_then(|r| r.as_array())
+		.cloned()
+		.unwrap_or_default();
+	for (i, rule) in rules.iter().enumerate() {
+		let paths = rule
+			.pointer("/http/paths")
+			.and_then(|p| p.as_array())
+			.map_or(0, Vec::len);
+
+		for j in 0..paths {
+			let pointer = format!("/spec/rules/{i}/http/paths/{j}/backend/service/name");
+
+			found.push(Slot::new("Service", ns, pointer, "backend"));
+		}
+	}
+
+	found
+}
@@ -0,0 +1,235 @@
+use crate::error::Root as Error;
+use crate::{Identity, Manifest};
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use kct_helper::crypt::{self, Recipients};
+use openssl::encrypt::Encrypter;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Public};
+use openssl::rand::rand_bytes;
+use openssl::rsa::Padding;
+use openssl::symm::{encrypt_aead, Cipher};
+use openssl::x509::X509;
+use serde_json::{json, Map, Value};
+
+/// Fields of a Secret holding its values
+const DATA_FIELDS: [&str; 2] = ["data", "stringData"];
+
+/// Size of the AES-GCM key encrypting each value of a SealedSecret
+const SESSION_KEY_SIZE: usize = 32;
+
+const GCM_NONCE_SIZE: usize = 12;
+const GCM_TAG_SIZE: usize = 16;
+
+/// Where a SealedSecret can be unsealed, which is bound to its encryption
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Scope {
+	/// Only with the same name and namespace
+	#[default]
+	Strict,
+	/// With any name in the same namespace
+	NamespaceWide,
+	/// With any name in any namespace
+	ClusterWide,
+}
+
+impl FromStr for Scope {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		match s {
+			"strict" => Ok(Scope::Strict),
+			"namespace-wide" => Ok(Scope::NamespaceWide),
+			"cluster-wide" => Ok(Scope::ClusterWide),
+			_ => Err(Error::Seal(format!("unknown scope \"{s}\""))),
+		}
+	}
+}
+
+impl fmt::Display for Scope {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Scope::Strict => write!(f, "strict"),
+			Scope::NamespaceWide => write!(f, "namespace-wide"),
+			Scope::ClusterWide => write!(f, "cluster-wide"),
+		}
+	}
+}
+
+/// Encrypts the rendered Secrets so they can be stored along with the other objects
+///
+/// They either become SealedSecrets, encrypted with the public certificate of the controller as
+/// `kubeseal` does, or stay Secrets with their values encrypted by SOPS to age recipients. None
+/// of them needs access to the cluster.
+pub enum Sealer {
+	Certificate { key: PKey<Public>, scope: Scope },
+	Sops(Recipients),
+}
+
+impl Sealer {
+	/// Seals into SealedSecrets with the PEM certificate of the controller
+	pub fn certificate(pem: &[u8], scope: Scope) -> Result<Self, Error> {
+		let invalid = |reason: String| Error::Seal(format!("invalid certificate, {reason}"));
+
+		let certificate = X509::from_pem(pem).map_err(|e| invalid(e.to_string()))?;
+		let key = certificate
+			.public_key()
+			.map_err(|e| invalid(e.to_string()))?;
+
+		if key.rsa().is_err() {
+			return Err(invalid(String::from("it doesn't hold an RSA key")));
+		}
+
+		Ok(Sealer::Certificate { key, scope })
+	}
+
+	/// Seals into Secrets with their values encrypted by SOPS to the age recipients
+	pub fn sops(recipients: &[String]) -> Result<Self, Error> {
+		let recipients = Recipients::parse(recipients).map_err(|e| Error::Seal(e.to_string()))?;
+
+		Ok(Sealer::Sops(recipients))
+	}
+
+	pub fn apply(&self, manifests: &mut [Manifest]) -> Result<(), Error> {
+		for manifest in manifests.iter_mut() {
+			let object = manifest.value_mut();
+			if object.get("kind").and_then(|k| k.as_str()) != Some("Secret") {
+				continue;
+			}
+
+			let identity = Identity::from(&*object);
+			let failed = |reason: String| Error::Seal(format!("{identity}, {reason}"));
+
+			let sealed = match self {
+				Sealer::Certificate { key, scope } => {
+					sealed_secret(object, key, *scope).map_err(failed)?
+				}
+				Sealer::Sops(recipients) => {
+					crypt::encrypt_sops(object.clone(), recipients, &DATA_FIELDS)
+						.map_err(|e| failed(e.to_string()))?
+				}
+			};
+
+			*object = sealed;
+		}
+
+		Ok(())
+	}
+}
+
+fn sealed_secret(secret: &Value, key: &PKey<Public>, scope: Scope) -> Result<Value, String> {
+	let identity = Identity::from(secret);
+	let name = identity.name.ok_or("it has no name")?;
+
+	let label = match (scope, identity.namespace) {
+		(Scope::Strict, Some(namespace)) => format!("{namespace}/{name}"),
+		(Scope::NamespaceWide, Some(namespace)) => namespace,
+		(Scope::ClusterWide, _) => String::new(),
+		(_, None) => return Err(format!("it needs a namespace for the {scope} scope")),
+	};
+
+	// stringData wins over data, as it does in the cluster
+	let mut values: BTreeMap<String, Vec<u8>> = BTreeMap::new();
+	for field in DATA_FIELDS {
+		let entries = secret.get(field).and_then(|d| d.as_object()).into_iter();
+
+		for (key, value) in entries.flatten() {
+			let text = value
+				.as_str()
+				.ok_or(format!("{field}.{key} isn't a string"))?;
+			let bytes = match field {
+				"data" => STANDARD
+					.decode(text)
+					.map_err(|_err| format!("data.{key} isn't valid base64"))?,
+				_ => text.as_bytes().to_vec(),
+			};
+
+			values.insert(key.clone(), bytes);
+		}
+	}
+
+	let mut encrypted = Map::new();
+	for (entry, value) in values {
+		let sealed = hybrid_encrypt(key, &value, label.as_bytes())
+			.map_err(|e| format!("unable to encrypt {entry}: {e}"))?;
+
+		encrypted.insert(entry, Value::String(STANDARD.encode(sealed)));
+	}
+
+	let metadata = secret.get("metadata").cloned().unwrap_or(json!({}));
+	let mut template = json!({ "metadata": metadata.clone() });
+	for field in ["type", "immutable"] {
+		if let Some(value) = secret.get(field) {
+			template[field] = value.clone();
+		}
+	}
+
+	let mut sealed = json!({
+		"apiVersion": "bitnami.com/v1alpha1",
+		"kind": "SealedSecret",
+		"metadata": metadata,
+		"spec": {
+			"encryptedData": encrypted,
+			"template": template,
+		}
+	});
+
+	let annotation = match scope {
+		Scope::Strict => None,
+		Scope::NamespaceWide => Some("sealedsecrets.bitnami.com/namespace-wide"),
+		Scope::ClusterWide => Some("sealedsecrets.bitnami.com/cluster-wide"),
+	};
+	if let Some(annotation) = annotation {
+		sealed["metadata"]["annotations"][annotation] = Value::String(String::from("true"));
+	}
+
+	Ok(sealed)
+}
+
+/// Encrypts the value with a fresh AES-GCM key, which is itself encrypted with RSA-OAEP and
+/// prefixed by its length, following the format of the sealed secrets controller
+fn hybrid_encrypt(
+	key: &PKey<Public>,
+	value: &[u8],
+	label: &[u8],
+) -> Result<Vec<u8>, openssl::error::ErrorStack> {
+	let mut session = [0u8; SESSION_KEY_SIZE];
+	rand_bytes(&mut session)?;
+
+	let mut encrypter = Encrypter::new(key)?;
+	encrypter.set_rsa_padding(Padding::PKCS1_OAEP)?;
+	encrypter.set_rsa_oaep_md(MessageDigest::sha256())?;
+	encrypter.set_rsa_mgf1_md(MessageDigest::sha256())?;
+	// openssl refuses empty labels, which are the same as no label
+	if !label.is_empty() {
+		encrypter.set_rsa_oaep_label(label)?;
+	}
+
+	let mut wrapped = vec![0u8; encrypter.encrypt_len(&session)?];
+	let length = encrypter.encrypt(&session, &mut wrapped)?;
+	wrapped.truncate(length);
+
+	// the key is only used once, so a zeroed nonce is safe
+	let nonce = [0u8; GCM_NONCE_SIZE];
+	let mut tag = [0u8; GCM_TAG_SIZE];
+	let data = encrypt_aead(
+		Cipher::aes_256_gcm(),
+		&session,
+		Some(&nonce),
+		&[],
+		value,
+		&mut tag,
+	)?;
+
+	let mut sealed = (wrapped.len() as u16).to_be_bytes().to_vec();
+	sealed.extend(wrapped);
+	sealed.extend(data);
+	sealed.extend(tag);
+
+	Ok(sealed)
+}
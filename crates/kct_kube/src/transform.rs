@@ -0,0 +1,311 @@
+use crate::error::Root as Error;
+use crate::reference;
+use crate::{Identity, Image, Manifest};
+
+use std::collections::{BTreeMap, HashSet};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use tracing::warn;
+
+/// Kinds that don't live in a namespace, custom resources are found through their CRDs
+const CLUSTER_KINDS: [&str; 19] = [
+	"APIService",
+	"CertificateSigningRequest",
+	"ClusterRole",
+	"ClusterRoleBinding",
+	"ComponentStatus",
+	"CSIDriver",
+	"CSINode",
+	"CustomResourceDefinition",
+	"IngressClass",
+	"MutatingWebhookConfiguration",
+	"Namespace",
+	"Node",
+	"PersistentVolume",
+	"PodSecurityPolicy",
+	"PriorityClass",
+	"RuntimeClass",
+	"StorageClass",
+	"ValidatingWebhookConfiguration",
+	"VolumeAttachment",
+];
+
+/// Kinds whose names are meaningful to the API server, so they're never prefixed nor suffixed
+const FIXED_NAMES: [&str; 3] = ["APIService", "CustomResourceDefinition", "Namespace"];
+
+/// Kinds whose selectors pick the pods of their template
+const SELECTING: [&str; 4] = ["Deployment", "StatefulSet", "DaemonSet", "ReplicaSet"];
+
+type Key = (String, Option<String>, String);
+
+/// Changes every package would otherwise reimplement in Jsonnet
+///
+/// They set the namespace of namespaced objects, decorate names with a prefix and a suffix, and
+/// add labels and annotations to metadata and pod templates. Labels only reach the selectors when
+/// `include_selectors` is set, as the selectors of workloads can't change once they're created.
+/// References between objects follow the renames and namespace changes. Images of containers are
+/// overridden last.
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Transformers {
+	pub namespace: Option<String>,
+	pub name_prefix: Option<String>,
+	pub name_suffix: Option<String>,
+	#[serde(default)]
+	pub labels: BTreeMap<String, String>,
+	#[serde(default)]
+	pub include_selectors: bool,
+	#[serde(default)]
+	pub annotations: BTreeMap<String, String>,
+	#[serde(default)]
+	pub images: Vec<Image>,
+}
+
+impl TryFrom<Value> for Transformers {
+	type Error = Error;
+
+	fn try_from(value: Value) -> Result<Self, Self::Error> {
+		serde_json::from_value(value).map_err(|e| Error::Transformers(e.to_string()))
+	}
+}
+
+impl Transformers {
+	/// Layers other transformers on top of these, which replace the namespace and affixes and
+	/// take precedence on the labels and annotations
+	pub fn layer(mut self, other: Transformers) -> Self {
+		self.namespace = other.namespace.or(self.namespace);
+		self.name_prefix = other.name_prefix.or(self.name_prefix);
+		self.name_suffix = other.name_suffix.or(self.name_suffix);
+		self.labels.extend(other.labels);
+		self.include_selectors |= other.include_selectors;
+		self.annotations.extend(other.annotations);
+		self.images.extend(other.images);
+
+		self
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.namespace.is_none()
+			&& self.name_prefix.is_none()
+			&& self.name_suffix.is_none()
+			&& self.labels.is_empty()
+			&& self.annotations.is_empty()
+			&& self.images.is_empty()
+	}
+
+	pub fn apply(&self, manifests: &mut [Manifest]) {
+		if self.is_empty() {
+			return;
+		}
+
+		let cluster = cluster_kinds(manifests);
+		let renames: Vec<(Key, String)> = manifests
+			.iter()
+			.filter_map(|manifest| {
+				let identity = Identity::from(manifest.value());
+				let renamed = self.rename(&identity)?;
+
+				Some((
+					key(&identity.kind, identity.namespace, identity.name?),
+					renamed,
+				))
+			})
+			.collect();
+		let accounts: Vec<Key> = manifests
+			.iter()
+			.map(|manifest| Identity::from(manifest.value()))
+			.filter(|identity| identity.kind == "ServiceAccount")
+			.filter_map(|identity| Some(key(&identity.kind, identity.namespace, identity.name?)))
+			.collect();
+
+		for manifest in manifests.iter_mut() {
+			let object = manifest.value_mut();
+			let kind = object
+				.get("kind")
+				.and_then(|k| k.as_str())
+				.unwrap_or_default()
+				.to_string();
+
+			self.follow(object, &cluster, &renames, &accounts);
+
+			if let Some(name) = self.rename(&Identity::from(&*object)) {
+				object["metadata"]["name"] = Value::String(name);
+			}
+
+			if let Some(namespace) = &self.namespace {
+				if !cluster.contains(&kind) {
+					object["metadata"]["namespace"] = Value::String(namespace.clone());
+				}
+			}
+
+			self.decorate(object, &kind);
+		}
+
+		for image in &self.images {
+			let mut matched = false;
+			for manifest in manifests.iter_mut() {
+				matched |= image.apply(manifest.value_mut());
+			}
+
+			if !matched {
+				warn!("Image override {image} doesn't match any container");
+			}
+		}
+	}
+
+	fn rename(&self, identity: &Identity) -> Option<String> {
+		let name = identity.name.as_ref()?;
+		if FIXED_NAMES.contains(&identity.kind.as_str()) {
+			return None;
+		}
+
+		match (&self.name_prefix, &self.name_suffix) {
+			(None, None) => None,
+			(prefix, suffix) => Some(format!(
+				"{}{name}{}",
+				prefix.as_deref().unwrap_or_default(),
+				suffix.as_deref().unwrap_or_default()
+			)),
+		}
+	}
+
+	/// Updates the references to renamed objects and to service accounts moved to the namespace
+	fn follow(
+		&self,
+		object: &mut Value,
+		cluster: &HashSet<String>,
+		renames: &[(Key, String)],
+		accounts: &[Key],
+	) {
+		for slot in reference::slots(object) {
+			let namespace = match cluster.contains(&slot.kind) {
+				true => None,
+				false => slot.namespace.clone(),
+			};
+			let name = object.pointer(&slot.pointer).and_then(|n| n.as_str());
+			let target = match name {
+				Some(name) => key(&slot.kind, namespace, name.to_string()),
+				None => continue,
+			};
+
+			let moved = slot.via == "subject" && accounts.iter().any(|a| reaches(&target, a));
+			let renamed = renames.iter().find(|(object, _)| reaches(&target, object));
+			if let Some((_, renamed)) = renamed {
+				*object.pointer_mut(&slot.pointer).unwrap() = Value::String(renamed.clone());
+			}
+
+			if let (true, Some(namespace)) = (moved, &self.namespace) {
+				let pointer = slot.pointer.trim_end_matches("/name").to_string();
+				let subject = object.pointer_mut(&pointer).unwrap();
+
+				subject["namespace"] = Value::String(namespace.clone());
+			}
+		}
+	}
+
+	fn decorate(&self, object: &mut Value, kind: &str) {
+		let labels = to_map(&self.labels);
+		let annotations = to_map(&self.annotations);
+
+		let template = match kind {
+			"CronJob" => Some("/spec/jobTemplate/spec/template"),
+			"Job" | "ReplicationController" => Some("/spec/template"),
+			k if SELECTING.contains(&k) => Some("/spec/template"),
+			_ => None,
+		};
+
+		let mut metadata = vec![String::from("/metadata")];
+		if let Some(at) = template.filter(|at| object.pointer(at).is_some()) {
+			metadata.push(format!("{at}/metadata"));
+		}
+
+		for at in metadata {
+			insert(object, &format!("{at}/labels"), &labels);
+			insert(object, &format!("{at}/annotations"), &annotations);
+		}
+
+		let selector = match kind {
+			"Service" | "ReplicationController" => Some("/spec/selector"),
+			"PodDisruptionBudget" => Some("/spec/selector/matchLabels"),
+			k if SELECTING.contains(&k) => Some("/spec/selector/matchLabels"),
+			_ => None,
+		};
+
+		let exists = selector
+			.and_then(|at| object.pointer(at.trim_end_matches("/matchLabels")))
+			.is_some();
+		if let (Some(at), true, true) = (selector, exists, self.include_selectors) {
+			insert(object, at, &labels);
+		}
+	}
+}
+
+fn key(kind: &str, namespace: Option<String>, name: String) -> Key {
+	(kind.to_string(), namespace, name)
+}
+
+/// Whether the reference points to the object, where a missing namespace on either side means
+/// the object is applied wherever the other one is
+fn reaches(reference: &Key, object: &Key) -> bool {
+	let (kind, namespace, name) = reference;
+
+	*kind == object.0
+		&& *name == object.2
+		&& (namespace.is_none() || object.1.is_none() || *namespace == object.1)
+}
+
+fn to_map(entries: &BTreeMap<String, String>) -> Map<String, Value> {
+	entries
+		.iter()
+		.map(|(k, v)| (k.clone(), Value::String(v.clone())))
+		.collect()
+}
+
+/// Adds the entries to the map at the pointer, creating the intermediate objects on the way
+fn insert(object: &mut Value, pointer: &str, entries: &Map<String, Value>) {
+	if entries.is_empty() {
+		return;
+	}
+
+	let target = pointer.split('/').skip(1).try_fold(object, |value, key| {
+		if value.is_null() {
+			*value = Value::Object(Map::new());
+		}
+
+		value
+			.as_object_mut()
+			.map(|map| map.entry(key).or_insert(Value::Null))
+	});
+
+	if let Some(target) = target {
+		if target.is_null() {
+			*target = Value::Object(Map::new());
+		}
+
+		if let Some(map) = target.as_object_mut() {
+			map.extend(entries.clone());
+		}
+	}
+}
+
+/// Cluster scoped kinds, including custom resources from the CRDs in the render
+fn cluster_kinds(manifests: &[Manifest]) -> HashSet<String> {
+	let custom = manifests.iter().filter_map(|manifest| {
+		let value = manifest.value();
+		let is_crd = value.get("kind").and_then(|k| k.as_str()) == Some("CustomResourceDefinition");
+		let scope = value.pointer("/spec/scope").and_then(|s| s.as_str());
+		let kind = value.pointer("/spec/names/kind").and_then(|k| k.as_str());
+
+		match (is_crd, scope, kind) {
+			(true, Some("Cluster"), Some(kind)) => Some(kind.to_string()),
+			_ => None,
+		}
+	});
+
+	CLUSTER_KINDS
+		.iter()
+		.map(|k| k.to_string())
+		.chain(custom)
+		.collect()
+}
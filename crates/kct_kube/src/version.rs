@@ -0,0 +1,52 @@
+use crate::error::Root as Error;
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Kubernetes version targeted by checks that don't need a cluster
+///
+/// Only the major and minor parts matter because APIs don't change in patch releases, so both
+/// `1.26` and `v1.26.3` are accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Version {
+	pub major: u32,
+	pub minor: u32,
+}
+
+impl Version {
+	pub const fn new(major: u32, minor: u32) -> Self {
+		Version { major, minor }
+	}
+}
+
+impl FromStr for Version {
+	type Err = Error;
+
+	fn from_str(s: &str) -> Result<Self, Self::Err> {
+		let invalid = || Error::Version(s.to_string());
+
+		let trimmed = s.trim().trim_start_matches('v');
+		let mut parts = trimmed.split('.');
+
+		let major = parts
+			.next()
+			.and_then(|p| p.parse().ok())
+			.ok_or_else(invalid)?;
+		let minor = parts
+			.next()
+			.and_then(|p| p.parse().ok())
+			.ok_or_else(invalid)?;
+
+		match parts.next() {
+			Some(patch) if patch.parse::<u32>().is_err() => Err(invalid()),
+			_ if parts.next().is_some() => Err(invalid()),
+			_ => Ok(Version { major, minor }),
+		}
+	}
+}
+
+impl fmt::Display for Version {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{}.{}", self.major, self.minor)
+	}
+}
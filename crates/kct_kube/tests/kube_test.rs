@@ -3,7 +3,10 @@ use std::path::PathBuf;
 
 use anyhow::Result;
 use assert_matches::assert_matches;
-use kct_kube::{error, Error, Kube, Manifest};
+use kct_kube::{
+	error, Change, Diff, Error, Format, Graph, Image, Kube, Manifest, Pass, Patches, Plan,
+	Policies, Redactor, Schemas, Sealer, Severity, Transformers, Version, BUNDLED,
+};
 use kct_testing::compile;
 use serde_json::{json, Value};
 
@@ -26,7 +29,7 @@ fn assert_manifests(ok: Return, times: usize) {
 	assert!(ok.is_ok());
 
 	let manifest = manifest();
-	let manifests: Vec<Value> = iter::repeat(manifest).take(times).collect();
+	let manifests: Vec<Value> = iter::repeat_n(manifest, times).collect();
 
 	let rendered: Vec<Value> = ok
 		.unwrap()
@@ -513,3 +516,1920 @@ mod filter {
 		assert_manifests(found, 0);
 	}
 }
+
+mod diff {
+	use super::*;
+
+	fn object(kind: &str, name: &str, replicas: u8) -> Value {
+		json!({
+			"kind": kind,
+			"apiVersion": "apps/v1",
+			"metadata": {"name": name},
+			"spec": {"replicas": replicas}
+		})
+	}
+
+	fn diff(before: Value, after: Value) -> Diff {
+		let before = find_from(before).unwrap();
+		let after = find_from(after).unwrap();
+
+		Diff::new(before, after).unwrap()
+	}
+
+	#[test]
+	fn is_empty_for_same_render() {
+		let json = json!({"a": object("Deployment", "a", 1)});
+
+		assert!(diff(json.clone(), json).is_empty());
+	}
+
+	#[test]
+	fn matches_objects_by_identity() {
+		let before =
+			json!({"a": object("Deployment", "web", 1), "b": object("Deployment", "gone", 1)});
+		let after = json!({"c": {"d": object("Deployment", "web", 2)}, "e": object("Deployment", "new", 1)});
+
+		let diff = diff(before, after);
+		let changes = diff.changes();
+
+		assert_eq!(changes.len(), 3);
+		assert_matches!(&changes[0], Change::Modified { before, after } => {
+			assert_eq!(before.path(), PathBuf::from("/a"));
+			assert_eq!(after.path(), PathBuf::from("/c/d"));
+		});
+		assert_matches!(&changes[1], Change::Added(added) => {
+			assert_eq!(added.path(), PathBuf::from("/e"));
+		});
+		assert_matches!(&changes[2], Change::Removed(removed) => {
+			assert_eq!(removed.path(), PathBuf::from("/b"));
+		});
+	}
+
+	#[test]
+	fn ignores_moved_objects_without_changes() {
+		let before = json!({"a": object("Deployment", "web", 1)});
+		let after = json!({"b": object("Deployment", "web", 1)});
+
+		assert!(diff(before, after).is_empty());
+	}
+
+	#[test]
+	fn rejects_different_duplicates() {
+		let first = find_from(json!({"a": object("Deployment", "web", 1)})).unwrap();
+		let second = find_from(json!({"b": object("Deployment", "web", 2)})).unwrap();
+		let before = first.into_iter().chain(second).collect();
+
+		let diff = Diff::new(before, vec![]);
+
+		assert_matches!(diff, Err(Error::Object(error::Object::Duplicate { .. })));
+	}
+
+	#[test]
+	fn distinguishes_kinds_with_same_name() {
+		let before = json!({"a": object("Deployment", "web", 1)});
+		let after = json!({"a": object("StatefulSet", "web", 1)});
+
+		let diff = diff(before, after);
+		let changes = diff.changes();
+
+		assert_matches!(changes, [Change::Added(_), Change::Removed(_)]);
+		assert_eq!(changes[0].identity().to_string(), "apps/v1/StatefulSet web");
+	}
+
+	#[test]
+	fn modifies_objects_moved_to_other_version() {
+		let mut previous = object("Deployment", "web", 1);
+		previous["apiVersion"] = json!("apps/v1beta2");
+		let before = json!({"a": previous});
+		let after = json!({"a": object("Deployment", "web", 1)});
+
+		let diff = diff(before, after);
+		let changes = diff.changes();
+
+		assert_matches!(changes, [Change::Modified { .. }]);
+		assert!(diff
+			.to_string()
+			.starts_with("~ /a (apps/v1beta2/Deployment web -> apps/v1/Deployment web)"));
+	}
+}
+
+mod plan {
+	use super::*;
+
+	fn plan_from(val: Value) -> Plan {
+		let kube = Kube::builder().value(val).build().unwrap();
+
+		kube.plan().unwrap()
+	}
+
+	fn crd() -> Value {
+		json!({
+			"kind": "CustomResourceDefinition",
+			"apiVersion": "apiextensions.k8s.io/v1",
+			"metadata": {"name": "crontabs.stable.example.com"}
+		})
+	}
+
+	#[test]
+	fn follows_render_order() {
+		let json = render(
+			r#"sdk.inOrder(['b'], {a: manifest('Namespace'), b: {c: manifest(), d: manifest('Secret')}})"#,
+		);
+		let plan = plan_from(json.clone());
+
+		let paths: Vec<PathBuf> = plan.steps().iter().map(|s| s.path.clone()).collect();
+		let rendered: Vec<PathBuf> = find_from(json)
+			.unwrap()
+			.iter()
+			.map(|m| m.path().to_path_buf())
+			.collect();
+
+		assert_eq!(paths, rendered);
+	}
+
+	#[test]
+	fn breaks_down_tracking() {
+		let json =
+			render(r#"sdk.inOrder(['b', 'a'], {a: manifest(), b: {c: manifest('Secret')}})"#);
+		let plan = plan_from(json);
+
+		let step = &plan.steps()[0];
+		let tracks = step.tracking.tracks();
+
+		assert_eq!(step.path, PathBuf::from("/b/c"));
+		assert_eq!(tracks.len(), 2);
+		assert_eq!(
+			(tracks[0].field.as_str(), tracks[0].depth, tracks[0].order),
+			("b", 1, 0)
+		);
+		assert_eq!(
+			(tracks[1].field.as_str(), tracks[1].depth, tracks[1].order),
+			("c", 2, 1)
+		);
+		assert!(tracks[0].kind.is_none());
+
+		let kind = tracks[1].kind.as_ref().unwrap();
+		assert_eq!(kind.name(), "Secret");
+		assert_eq!(kind.priority(), 7);
+	}
+
+	#[test]
+	fn splits_crds_into_first_wave() {
+		let namespace = json!({"kind": "Namespace", "apiVersion": "v1"});
+		let json = json!({"a": namespace, "b": crd()});
+		let plan = plan_from(json);
+
+		let waves: Vec<(Pass, usize)> = plan.steps().iter().map(|s| (s.pass, s.wave)).collect();
+
+		assert_eq!(waves, vec![(Pass::Dynamics, 2), (Pass::Crds, 1)]);
+	}
+
+	#[test]
+	fn shows_transformed_objects() {
+		let config = json!({"kind": "ConfigMap", "apiVersion": "v1", "metadata": {"name": "web"}});
+		let transformers = Transformers {
+			namespace: Some(String::from("prod")),
+			name_prefix: Some(String::from("team-")),
+			..Default::default()
+		};
+		let kube = Kube::builder()
+			.value(json!({"a": config}))
+			.transformers(transformers)
+			.build()
+			.unwrap();
+
+		let plan = kube.plan().unwrap();
+		let step = &plan.steps()[0];
+
+		assert_eq!(step.path, PathBuf::from("/a"));
+		assert_eq!(step.identity.to_string(), "v1/ConfigMap prod/team-web");
+		assert_eq!(step.tracking.tracks()[0].field, "a");
+	}
+
+	#[test]
+	fn starts_on_first_wave_without_crds() {
+		let plan = plan_from(json!({"a": manifest()}));
+
+		assert_eq!(plan.steps()[0].wave, 1);
+		assert_eq!(plan.steps()[0].pass, Pass::Dynamics);
+	}
+}
+
+mod graph {
+	use super::*;
+
+	fn tree() -> Value {
+		json!({
+			"app": {
+				"deployment": {
+					"kind": "Deployment",
+					"apiVersion": "apps/v1",
+					"metadata": {"name": "web"},
+					"spec": {"template": {
+						"metadata": {"labels": {"app": "web"}},
+						"spec": {
+							"volumes": [{"name": "config", "configMap": {"name": "settings"}}],
+							"containers": [{"name": "web", "image": "nginx"}]
+						}
+					}}
+				},
+				"service": {
+					"kind": "Service",
+					"apiVersion": "v1",
+					"metadata": {"name": "web"},
+					"spec": {"selector": {"app": "web"}}
+				}
+			},
+			"config": {
+				"kind": "ConfigMap",
+				"apiVersion": "v1",
+				"metadata": {"name": "settings"}
+			}
+		})
+	}
+
+	#[test]
+	fn clusters_intermediate_fields() {
+		let manifests = find_from(tree()).unwrap();
+		let dot = Graph::new(&manifests).render(Format::Dot);
+
+		assert!(dot.starts_with("digraph kct {"));
+		assert!(dot.contains("subgraph cluster_0 {\n\t\tlabel=\"app\";"));
+		assert!(dot.contains("[label=\"Deployment\\nweb\"]"));
+		assert!(!dot.contains("label=\"config\""));
+	}
+
+	#[test]
+	fn links_references() {
+		let manifests = find_from(tree()).unwrap();
+		let mermaid = Graph::new(&manifests).render(Format::Mermaid);
+
+		let index = |kind: &str| {
+			manifests
+				.iter()
+				.position(|m| m.value()["kind"] == kind)
+				.unwrap()
+		};
+		let (config, deployment, service) =
+			(index("ConfigMap"), index("Deployment"), index("Service"));
+
+		assert!(mermaid.starts_with("flowchart LR"));
+		assert!(mermaid.contains(&format!("n{service} -->|selector| n{deployment}")));
+		assert!(mermaid.contains(&format!("n{deployment} -->|volume| n{config}")));
+	}
+
+	#[test]
+	fn rejects_unknown_formats() {
+		assert_matches!("png".parse::<Format>(), Err(Error::GraphFormat(_)));
+	}
+}
+
+mod duplicates {
+	use super::*;
+
+	fn named(name: &str, replicas: u8) -> Value {
+		json!({
+			"kind": "Deployment",
+			"apiVersion": "apps/v1",
+			"metadata": {"name": name, "namespace": "default"},
+			"spec": {"replicas": replicas}
+		})
+	}
+
+	fn find_allowing_identical(val: Value) -> Return {
+		let kube = Kube::builder().value(val).allow_identical(true).build()?;
+
+		kube.try_into()
+	}
+
+	#[test]
+	fn fails_on_same_identity() {
+		let json = json!({"a": named("web", 1), "b": {"c": named("web", 1)}});
+		let error = find_from(json).unwrap_err();
+
+		assert_matches!(error, Error::Object(error::Object::Duplicate { identity, first, second }) => {
+			assert_eq!(identity.to_string(), "apps/v1/Deployment default/web");
+			assert_eq!(first, PathBuf::from("/a"));
+			assert_eq!(second, PathBuf::from("/b/c"));
+		});
+	}
+
+	#[test]
+	fn fails_on_same_object_at_other_version() {
+		let mut other = named("web", 1);
+		other["apiVersion"] = json!("apps/v1beta2");
+		let json = json!({"a": named("web", 1), "b": other});
+
+		assert_matches!(
+			find_from(json).unwrap_err(),
+			Error::Object(error::Object::Duplicate { .. })
+		);
+	}
+
+	#[test]
+	fn keeps_missing_namespace_apart_from_default() {
+		let mut other = named("web", 1);
+		other["metadata"]
+			.as_object_mut()
+			.unwrap()
+			.remove("namespace");
+		let json = json!({"a": named("web", 1), "b": other});
+
+		assert!(find_from(json).is_ok());
+	}
+
+	#[test]
+	fn fails_on_same_unscoped_object() {
+		let role = json!({
+			"kind": "ClusterRole",
+			"apiVersion": "rbac.authorization.k8s.io/v1",
+			"metadata": {"name": "reader"}
+		});
+		let mut other = role.clone();
+		other["rules"] = json!([]);
+		let json = json!({"a": role, "b": other});
+
+		assert_matches!(
+			find_from(json).unwrap_err(),
+			Error::Object(error::Object::Duplicate { .. })
+		);
+	}
+
+	#[test]
+	fn allows_different_names_and_unnamed() {
+		let json =
+			json!({"a": named("web", 1), "b": named("api", 1), "c": manifest(), "d": manifest()});
+
+		assert_eq!(find_from(json).unwrap().len(), 4);
+	}
+
+	#[test]
+	fn keeps_first_of_identical_when_allowed() {
+		let json = json!({"a": named("web", 1), "b": {"c": named("web", 1)}});
+
+		assert_paths(find_allowing_identical(json), vec!["/a"]);
+	}
+
+	#[test]
+	fn fails_on_different_copies_even_when_allowed() {
+		let json = json!({"a": named("web", 1), "b": named("web", 2)});
+		let error = find_allowing_identical(json).unwrap_err();
+
+		assert_matches!(error, Error::Object(error::Object::Duplicate { .. }));
+	}
+
+	#[test]
+	fn ignores_filtered_copies() {
+		let json = json!({"a": named("web", 1), "b": named("web", 2)});
+		let kube = Kube::builder()
+			.value(json)
+			.except(vec![PathBuf::from("/b")])
+			.build()
+			.unwrap();
+
+		let found: Return = kube.try_into();
+		assert_paths(found, vec!["/a"]);
+	}
+}
+
+mod validation {
+	use super::*;
+
+	fn validate(val: Value) -> Return {
+		let schemas = Schemas::new(BUNDLED)?;
+		let kube = Kube::builder().value(val).schemas(Some(schemas)).build()?;
+
+		kube.try_into()
+	}
+
+	fn config_map(data: Value) -> Value {
+		json!({
+			"kind": "ConfigMap",
+			"apiVersion": "v1",
+			"metadata": {"name": "config"},
+			"data": data
+		})
+	}
+
+	fn crd() -> Value {
+		json!({
+			"kind": "CustomResourceDefinition",
+			"apiVersion": "apiextensions.k8s.io/v1",
+			"metadata": {"name": "crontabs.stable.example.com"},
+			"spec": {
+				"group": "stable.example.com",
+				"scope": "Namespaced",
+				"names": {"kind": "CronTab", "plural": "crontabs", "singular": "crontab"},
+				"versions": [{
+					"name": "v1",
+					"served": true,
+					"storage": true,
+					"schema": {"openAPIV3Schema": {
+						"type": "object",
+						"properties": {
+							"spec": {
+								"type": "object",
+								"properties": {"replicas": {"type": "integer"}}
+							}
+						}
+					}}
+				}]
+			}
+		})
+	}
+
+	#[test]
+	fn accepts_valid_objects() {
+		let json = json!({"config": config_map(json!({"key": "value"}))});
+
+		assert_eq!(validate(json).unwrap().len(), 1);
+	}
+
+	#[test]
+	fn points_at_invalid_fields() {
+		let json = json!({"app": {"config": config_map(json!({"key": 1}))}});
+		let error = validate(json).unwrap_err();
+
+		assert_matches!(error, Error::Invalid(violations) => {
+			assert_eq!(violations.0.len(), 1);
+
+			let violation = &violations.0[0];
+			assert_eq!(violation.path, PathBuf::from("/app/config"));
+			assert_eq!(violation.pointer, "/data/key");
+		});
+	}
+
+	#[test]
+	fn accepts_numbers_as_quantities() {
+		let pod = |cpu: Value| {
+			json!({
+				"kind": "Pod",
+				"apiVersion": "v1",
+				"metadata": {"name": "web"},
+				"spec": {"containers": [{
+					"name": "web",
+					"image": "nginx",
+					"resources": {"requests": {"cpu": cpu, "memory": "64Mi"}, "limits": {"cpu": 0.5}}
+				}]}
+			})
+		};
+
+		assert_eq!(validate(json!({ "pod": pod(json!(1)) })).unwrap().len(), 1);
+		assert_matches!(
+			validate(json!({ "pod": pod(json!(true)) })),
+			Err(Error::Invalid(_))
+		);
+	}
+
+	#[test]
+	fn accepts_fields_the_schemas_dont_know() {
+		let mut config = config_map(json!({}));
+		config["metadata"]["labels"] = json!({"team": "a"});
+		config["extra"] = json!({});
+
+		assert_eq!(validate(json!({ "config": config })).unwrap().len(), 1);
+	}
+
+	#[test]
+	fn uses_crds_from_the_render() {
+		let resource = |replicas: Value| {
+			json!({
+				"kind": "CronTab",
+				"apiVersion": "stable.example.com/v1",
+				"metadata": {"name": "tab"},
+				"spec": {"replicas": replicas}
+			})
+		};
+
+		let valid = json!({"crd": crd(), "tab": resource(json!(1))});
+		assert_eq!(validate(valid).unwrap().len(), 2);
+
+		let invalid = json!({"crd": crd(), "tab": resource(json!("one"))});
+		assert_matches!(validate(invalid).unwrap_err(), Error::Invalid(violations) => {
+			assert_eq!(violations.0[0].pointer, "/spec/replicas");
+		});
+	}
+
+	#[test]
+	fn skips_objects_without_schemas() {
+		let json = json!({"unknown": {"kind": "Unknown", "apiVersion": "example.com/v1"}});
+
+		assert_eq!(validate(json).unwrap().len(), 1);
+	}
+
+	#[test]
+	fn fails_on_unbundled_versions() {
+		let error = Schemas::new(Version::new(1, 10)).err().unwrap();
+
+		assert_matches!(error, Error::Unbundled(..));
+	}
+
+	#[test]
+	fn parses_versions() {
+		assert_eq!("1.26".parse::<Version>().unwrap(), Version::new(1, 26));
+		assert_eq!("v1.26.3".parse::<Version>().unwrap(), Version::new(1, 26));
+		assert!("1".parse::<Version>().is_err());
+		assert!("1.26.x".parse::<Version>().is_err());
+	}
+}
+
+mod policies {
+	use super::*;
+
+	use std::fs;
+
+	use kct_testing::dir::{self, TempDir};
+
+	const TEAM: &str = r#"{
+		"severity": "deny",
+		"kinds": ["Deployment"],
+		"message": "objects need a team label",
+		"schema": {
+			"type": "object",
+			"required": ["metadata"],
+			"properties": {
+				"metadata": {"type": "object", "required": ["labels"]}
+			}
+		}
+	}"#;
+
+	const LATEST: &str = r#"
+		local object = std.extVar('kct.io/object');
+		local containers =
+			if object.kind == 'Deployment' then object.spec.template.spec.containers else [];
+
+		{
+			severity: 'warn',
+			violations: [
+				'container %s uses a latest image' % c.name
+				for c in containers
+				if std.endsWith(c.image, ':latest')
+			],
+		}
+	"#;
+
+	fn rules(files: &[(&str, &str)]) -> TempDir {
+		let dir = dir::tmp();
+		for (name, contents) in files {
+			fs::write(dir.path().join(name), contents).unwrap();
+		}
+
+		dir
+	}
+
+	fn deployment(image: &str, labels: Option<Value>) -> Value {
+		let mut metadata = json!({"name": "web"});
+		if let Some(labels) = labels {
+			metadata["labels"] = labels;
+		}
+
+		json!({
+			"kind": "Deployment",
+			"apiVersion": "apps/v1",
+			"metadata": metadata,
+			"spec": {"template": {"spec": {"containers": [{"name": "web", "image": image}]}}}
+		})
+	}
+
+	fn kube(dir: &TempDir, val: Value) -> Result<Kube, Error> {
+		let policies = Policies::load(dir.path())?;
+
+		Kube::builder().value(val).policies(Some(policies)).build()
+	}
+
+	#[test]
+	fn denies_objects_breaking_schema_rules() {
+		let dir = rules(&[("team.json", TEAM)]);
+		let json = json!({"app": {"deployment": deployment("nginx:1.25", None)}});
+
+		let error = kube(&dir, json).unwrap().render().unwrap_err();
+
+		assert_matches!(error, Error::Denied(findings) => {
+			assert_eq!(findings.0.len(), 1);
+
+			let finding = &findings.0[0];
+			assert_eq!(finding.rule, "team");
+			assert_eq!(finding.path, PathBuf::from("/app/deployment"));
+			assert_eq!(finding.pointer.as_deref(), Some("/metadata/labels"));
+			assert_eq!(finding.message, "objects need a team label");
+		});
+	}
+
+	#[test]
+	fn selects_objects_by_kind() {
+		let dir = rules(&[("team.json", TEAM)]);
+		let json =
+			json!({"config": {"kind": "ConfigMap", "apiVersion": "v1", "metadata": {"name": "c"}}});
+
+		assert_eq!(kube(&dir, json).unwrap().render().unwrap().len(), 1);
+	}
+
+	#[test]
+	fn renders_with_warnings_only() {
+		let dir = rules(&[("latest.jsonnet", LATEST)]);
+		let json = json!({"deployment": deployment("nginx:latest", Some(json!({"team": "a"})))});
+
+		let kube = kube(&dir, json).unwrap();
+		let findings = kube.lint().unwrap();
+
+		assert!(!findings.denied());
+		assert_eq!(findings.0.len(), 1);
+		assert_eq!(findings.0[0].severity, Severity::Warn);
+		assert_eq!(findings.0[0].message, "container web uses a latest image");
+		assert_eq!(kube.render().unwrap().len(), 1);
+	}
+
+	#[test]
+	fn lints_every_rule() {
+		let dir = rules(&[("latest.jsonnet", LATEST), ("team.yaml", TEAM)]);
+		let json = json!({"deployment": deployment("nginx:latest", None)});
+
+		let findings = kube(&dir, json).unwrap().lint().unwrap();
+		let rules: Vec<&str> = findings.0.iter().map(|f| f.rule.as_str()).collect();
+
+		assert!(findings.denied());
+		assert_eq!(rules, vec!["latest", "team"]);
+	}
+
+	#[test]
+	fn fails_on_invalid_rules() {
+		let dir = rules(&[("broken.json", r#"{"severity": "block", "schema": {}}"#)]);
+
+		let error = Policies::load(dir.path()).err().unwrap();
+
+		assert_matches!(error, Error::Rule(name, _) => assert_eq!(name, "broken"));
+	}
+
+	#[test]
+	fn fails_on_invalid_schemas_when_loading() {
+		let dir = rules(&[("broken.json", r#"{"schema": {"type": 5}}"#)]);
+
+		let error = Policies::load(dir.path()).err().unwrap();
+
+		assert_matches!(&error, Error::Rule(name, _) => assert_eq!(name, "broken"));
+		assert!(std::error::Error::source(&error).is_some());
+	}
+}
+
+mod deprecations {
+	use super::*;
+
+	fn hpa(api_version: &str) -> Value {
+		json!({
+			"kind": "HorizontalPodAutoscaler",
+			"apiVersion": api_version,
+			"metadata": {"name": "web"}
+		})
+	}
+
+	fn kube(val: Value, version: Version) -> Kube {
+		Kube::builder()
+			.value(val)
+			.deprecations(Some(version))
+			.build()
+			.unwrap()
+	}
+
+	#[test]
+	fn warns_on_deprecated_apis() {
+		let kube = kube(
+			json!({"hpa": hpa("autoscaling/v2beta2")}),
+			Version::new(1, 23),
+		);
+		let findings = kube.lint().unwrap();
+
+		assert_eq!(findings.0.len(), 1);
+
+		let finding = &findings.0[0];
+		assert_eq!(finding.severity, Severity::Warn);
+		assert_eq!(finding.path, PathBuf::from("/hpa"));
+		assert!(finding.message.contains("use autoscaling/v2 instead"));
+		assert_eq!(kube.render().unwrap().len(), 1);
+	}
+
+	#[test]
+	fn denies_removed_apis() {
+		let kube = kube(
+			json!({"hpa": hpa("autoscaling/v2beta2")}),
+			Version::new(1, 26),
+		);
+
+		assert_matches!(kube.render().unwrap_err(), Error::Denied(findings) => {
+			assert_eq!(findings.0[0].severity, Severity::Deny);
+			assert_eq!(findings.0[0].pointer.as_deref(), Some("/apiVersion"));
+		});
+	}
+
+	#[test]
+	fn ignores_current_apis() {
+		let mut new = hpa("autoscaling/v2");
+		new["metadata"]["name"] = json!("api");
+		let json = json!({"old": hpa("autoscaling/v2beta2"), "new": new});
+		let kube = kube(json, Version::new(1, 22));
+
+		assert!(kube.lint().unwrap().is_empty());
+	}
+
+	#[test]
+	fn matches_by_kind() {
+		let pdb = json!({"kind": "PodDisruptionBudget", "apiVersion": "policy/v1beta1"});
+		let psp = json!({"kind": "PodSecurityPolicy", "apiVersion": "policy/v1beta1"});
+		let other = json!({"kind": "Other", "apiVersion": "policy/v1beta1"});
+
+		let json = json!({"pdb": pdb, "psp": psp, "other": other});
+		let findings = kube(json, Version::new(1, 25)).lint().unwrap();
+
+		assert_eq!(findings.0.len(), 2);
+		assert!(findings
+			.0
+			.iter()
+			.any(|f| f.message.contains("there's no replacement")));
+	}
+
+	#[test]
+	fn ignores_kinds_added_after_removal() {
+		let policy = json!({
+			"kind": "ValidatingAdmissionPolicy",
+			"apiVersion": "admissionregistration.k8s.io/v1beta1"
+		});
+		let review =
+			json!({"kind": "SelfSubjectReview", "apiVersion": "authentication.k8s.io/v1beta1"});
+		let webhook = json!({
+			"kind": "ValidatingWebhookConfiguration",
+			"apiVersion": "admissionregistration.k8s.io/v1beta1"
+		});
+
+		let json = json!({"policy": policy, "review": review, "webhook": webhook});
+		let findings = kube(json, Version::new(1, 28)).lint().unwrap();
+
+		assert_eq!(findings.0.len(), 1);
+		assert_eq!(
+			findings.0[0].identity.kind,
+			"ValidatingWebhookConfiguration"
+		);
+	}
+}
+
+mod patches {
+	use super::*;
+
+	use std::fs;
+
+	use kct_testing::dir::{self, TempDir};
+
+	fn deployment() -> Value {
+		json!({
+			"kind": "Deployment",
+			"apiVersion": "apps/v1",
+			"metadata": {"name": "web"},
+			"spec": {"template": {"spec": {"containers": [
+				{"name": "web", "image": "nginx", "env": [{"name": "A", "value": "1"}]},
+				{"name": "proxy", "image": "envoy"}
+			]}}}
+		})
+	}
+
+	fn patched(val: Value, patches: &[&str]) -> Result<Vec<Value>, Error> {
+		let dir = dir::tmp();
+		let patches =
+			patches
+				.iter()
+				.enumerate()
+				.try_fold(Patches::default(), |patches, (i, contents)| {
+					let file = dir.path().join(format!("{i}.yaml"));
+					fs::write(&file, contents).unwrap();
+
+					patches.with_file(&file)
+				})?;
+
+		let kube = Kube::builder().value(val).patches(patches).build()?;
+		let manifests: Vec<Manifest> = kube.try_into()?;
+
+		Ok(manifests
+			.into_iter()
+			.map(|m| {
+				let (_, value) = m.into();
+
+				value
+			})
+			.collect())
+	}
+
+	fn write(files: &[(&str, &str)]) -> TempDir {
+		let dir = dir::tmp();
+		for (name, contents) in files {
+			fs::write(dir.path().join(name), contents).unwrap();
+		}
+
+		dir
+	}
+
+	#[test]
+	fn merges_lists_by_key_strategically() {
+		let patch = r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata: {name: web}
+spec:
+  template:
+    spec:
+      containers:
+        - name: web
+          image: nginx:1.25
+          env: [{name: B, value: "2"}]
+        - {name: proxy, $patch: delete}
+        - {name: sidecar, image: busybox}
+"#;
+
+		let rendered = patched(json!({ "web": deployment() }), &[patch]).unwrap();
+		let containers = &rendered[0]["spec"]["template"]["spec"]["containers"];
+
+		assert_eq!(
+			containers,
+			&json!([
+				{"name": "web", "image": "nginx:1.25", "env": [
+					{"name": "A", "value": "1"},
+					{"name": "B", "value": "2"}
+				]},
+				{"name": "sidecar", "image": "busybox"}
+			])
+		);
+	}
+
+	#[test]
+	fn merges_lists_only_at_known_paths() {
+		let service = json!({
+			"kind": "Service",
+			"apiVersion": "v1",
+			"metadata": {"name": "web"},
+			"spec": {"ports": [{"name": "http", "port": 80}]}
+		});
+		let mut web = deployment();
+		web["spec"]["template"]["spec"]["containers"][0]["ports"] = json!([{"containerPort": 80}]);
+		web["status"] = json!({"conditions": [{"type": "Available", "status": "True"}]});
+
+		let patches = [
+			r#"{"apiVersion": "v1", "kind": "Service", "metadata": {"name": "web"}, "spec": {"ports": [{"name": "https", "port": 443}]}}"#,
+			r#"
+apiVersion: apps/v1
+kind: Deployment
+metadata: {name: web}
+spec:
+  template:
+    spec:
+      containers: [{name: web, ports: [{containerPort: 443}]}]
+status:
+  conditions: [{type: Progressing, status: "True"}]
+"#,
+		];
+
+		let rendered = patched(json!({"service": service, "web": web}), &patches).unwrap();
+		let find = |kind: &str| rendered.iter().find(|v| v["kind"] == kind).unwrap();
+
+		assert_eq!(
+			find("Service")["spec"]["ports"],
+			json!([{"name": "http", "port": 80}, {"name": "https", "port": 443}])
+		);
+		assert_eq!(
+			find("Deployment")["spec"]["template"]["spec"]["containers"][0]["ports"],
+			json!([{"containerPort": 80}, {"containerPort": 443}])
+		);
+		assert_eq!(
+			find("Deployment")["status"]["conditions"],
+			json!([{"type": "Progressing", "status": "True"}])
+		);
+	}
+
+	#[test]
+	fn fails_on_strategic_patches_without_kind_or_name() {
+		let patches = [
+			r#"{"metadata": {"name": "web"}, "spec": {"replicas": 2}}"#,
+			r#"{"kind": "Deployment", "spec": {"replicas": 2}}"#,
+			r#"{"target": {"path": "/web"}, "patch": {"spec": {"replicas": 2}}}"#,
+		];
+
+		for patch in patches {
+			let error = patched(json!({ "web": deployment() }), &[patch]).unwrap_err();
+
+			assert_matches!(error, Error::Patch(..));
+		}
+	}
+
+	#[test]
+	fn replaces_lists_of_custom_resources() {
+		let resource = json!({
+			"kind": "Tab",
+			"apiVersion": "example.com/v1",
+			"metadata": {"name": "tab"},
+			"spec": {"containers": [{"name": "a"}, {"name": "b"}]}
+		});
+		let patch = r#"{"kind": "Tab", "metadata": {"name": "tab"}, "spec": {"containers": [{"name": "c"}]}}"#;
+
+		let rendered = patched(json!({ "tab": resource }), &[patch]).unwrap();
+
+		assert_eq!(rendered[0]["spec"]["containers"], json!([{"name": "c"}]));
+	}
+
+	#[test]
+	fn applies_merge_patches_by_path() {
+		let patch = r#"
+target: {path: /app}
+type: merge
+patch: {metadata: {labels: {team: a}}, spec: null}
+"#;
+
+		let mut other = deployment();
+		other["metadata"]["name"] = json!("api");
+
+		let json = json!({"app": {"web": deployment()}, "other": other});
+		let rendered = patched(json, &[patch]).unwrap();
+		let patched: Vec<&Value> = rendered
+			.iter()
+			.filter(|v| v["metadata"]["labels"]["team"] == "a")
+			.collect();
+
+		assert_eq!(patched.len(), 1);
+		assert_eq!(patched[0]["metadata"]["name"], json!("web"));
+		assert_eq!(patched[0].get("spec"), None);
+	}
+
+	#[test]
+	fn applies_json_patches_by_identity() {
+		let patch = r#"
+target: {kind: Deployment, name: web}
+type: json
+patch:
+  - {op: replace, path: /spec/template/spec/containers/1/image, value: envoy:1.27}
+  - {op: add, path: /spec/replicas, value: 3}
+"#;
+
+		let rendered = patched(json!({ "web": deployment() }), &[patch]).unwrap();
+
+		assert_eq!(rendered[0]["spec"]["replicas"], json!(3));
+		assert_eq!(
+			rendered[0]["spec"]["template"]["spec"]["containers"][1]["image"],
+			json!("envoy:1.27")
+		);
+	}
+
+	#[test]
+	fn fails_on_json_patches_that_dont_apply() {
+		let patch = r#"
+target: {kind: Deployment}
+type: json
+patch: [{op: remove, path: /spec/missing}]
+"#;
+
+		let error = patched(json!({ "web": deployment() }), &[patch]).unwrap_err();
+
+		assert_matches!(error, Error::Patch(..));
+	}
+
+	#[test]
+	fn skips_patches_without_matches() {
+		let patch =
+			r#"{"kind": "Deployment", "metadata": {"name": "api"}, "spec": {"replicas": 2}}"#;
+
+		let rendered = patched(json!({ "web": deployment() }), &[patch]).unwrap();
+
+		assert_eq!(rendered[0], deployment());
+	}
+
+	#[test]
+	fn loads_dirs_in_order() {
+		let first = r#"{"target": {"kind": "Deployment"}, "type": "merge", "patch": {"spec": {"replicas": 1}}}"#;
+		let second = r#"{"target": {"kind": "Deployment"}, "type": "merge", "patch": {"spec": {"replicas": 2}}}"#;
+		let dir = write(&[("b.json", second), ("a.json", first), ("ignored.txt", "")]);
+
+		let patches = Patches::default().with_dir(dir.path()).unwrap();
+		let kube = Kube::builder()
+			.value(json!({ "web": deployment() }))
+			.patches(patches)
+			.build()
+			.unwrap();
+		let manifests: Vec<Manifest> = kube.try_into().unwrap();
+
+		assert_eq!(manifests[0].value()["spec"]["replicas"], json!(2));
+	}
+}
+
+mod transformers {
+	use super::*;
+
+	use std::collections::BTreeMap;
+
+	fn transform(val: Value, transformers: Transformers) -> Vec<Value> {
+		let kube = Kube::builder()
+			.value(val)
+			.transformers(transformers)
+			.build()
+			.unwrap();
+		let manifests: Vec<Manifest> = kube.try_into().unwrap();
+
+		manifests
+			.into_iter()
+			.map(|m| {
+				let (_, value) = m.into();
+
+				value
+			})
+			.collect()
+	}
+
+	fn find<'a>(values: &'a [Value], kind: &str) -> &'a Value {
+		values.iter().find(|v| v["kind"] == kind).unwrap()
+	}
+
+	fn package() -> Value {
+		json!({
+			"config": {"kind": "ConfigMap", "apiVersion": "v1", "metadata": {"name": "cfg"}},
+			"account": {"kind": "ServiceAccount", "apiVersion": "v1", "metadata": {"name": "web"}},
+			"role": {
+				"kind": "ClusterRole",
+				"apiVersion": "rbac.authorization.k8s.io/v1",
+				"metadata": {"name": "reader"}
+			},
+			"binding": {
+				"kind": "RoleBinding",
+				"apiVersion": "rbac.authorization.k8s.io/v1",
+				"metadata": {"name": "web"},
+				"subjects": [{"kind": "ServiceAccount", "name": "web"}],
+				"roleRef": {"kind": "ClusterRole", "name": "reader"}
+			},
+			"deployment": {
+				"kind": "Deployment",
+				"apiVersion": "apps/v1",
+				"metadata": {"name": "web"},
+				"spec": {
+					"selector": {"matchLabels": {"app": "web"}},
+					"template": {
+						"metadata": {"labels": {"app": "web"}},
+						"spec": {
+							"serviceAccountName": "web",
+							"volumes": [{"name": "c", "configMap": {"name": "cfg"}}],
+							"containers": [{"name": "web", "image": "nginx"}]
+						}
+					}
+				}
+			},
+			"service": {
+				"kind": "Service",
+				"apiVersion": "v1",
+				"metadata": {"name": "web"},
+				"spec": {"selector": {"app": "web"}}
+			}
+		})
+	}
+
+	#[test]
+	fn sets_namespace_of_namespaced_objects() {
+		let crd = json!({
+			"kind": "CustomResourceDefinition",
+			"apiVersion": "apiextensions.k8s.io/v1",
+			"metadata": {"name": "globals.example.com"},
+			"spec": {"scope": "Cluster", "names": {"kind": "Global"}}
+		});
+		let global =
+			json!({"kind": "Global", "apiVersion": "example.com/v1", "metadata": {"name": "g"}});
+
+		let mut json = package();
+		json["crd"] = crd;
+		json["global"] = global;
+
+		let rendered = transform(
+			json,
+			Transformers {
+				namespace: Some(String::from("prod")),
+				..Default::default()
+			},
+		);
+
+		for kind in ["ClusterRole", "CustomResourceDefinition", "Global"] {
+			assert_eq!(find(&rendered, kind)["metadata"].get("namespace"), None);
+		}
+
+		assert_eq!(
+			find(&rendered, "Deployment")["metadata"]["namespace"],
+			"prod"
+		);
+		assert_eq!(
+			find(&rendered, "RoleBinding")["subjects"][0]["namespace"],
+			"prod"
+		);
+	}
+
+	#[test]
+	fn renames_objects_and_their_references() {
+		let rendered = transform(
+			package(),
+			Transformers {
+				name_prefix: Some(String::from("team-")),
+				name_suffix: Some(String::from("-v1")),
+				..Default::default()
+			},
+		);
+
+		let deployment = find(&rendered, "Deployment");
+		let spec = &deployment["spec"]["template"]["spec"];
+		assert_eq!(deployment["metadata"]["name"], "team-web-v1");
+		assert_eq!(spec["serviceAccountName"], "team-web-v1");
+		assert_eq!(spec["volumes"][0]["configMap"]["name"], "team-cfg-v1");
+
+		let binding = find(&rendered, "RoleBinding");
+		assert_eq!(binding["subjects"][0]["name"], "team-web-v1");
+		assert_eq!(binding["roleRef"]["name"], "team-reader-v1");
+	}
+
+	#[test]
+	fn keeps_references_to_objects_outside_the_render() {
+		let mut json = package();
+		json["binding"]["roleRef"]["name"] = json!("view");
+
+		let rendered = transform(
+			json,
+			Transformers {
+				name_prefix: Some(String::from("team-")),
+				..Default::default()
+			},
+		);
+
+		assert_eq!(find(&rendered, "RoleBinding")["roleRef"]["name"], "view");
+	}
+
+	#[test]
+	fn renames_objects_without_namespace_from_namespaced_references() {
+		let mut json = package();
+		json["deployment"]["metadata"]["namespace"] = json!("prod");
+		json["binding"]["metadata"]["namespace"] = json!("prod");
+
+		let rendered = transform(
+			json,
+			Transformers {
+				name_prefix: Some(String::from("team-")),
+				..Default::default()
+			},
+		);
+
+		let spec = &find(&rendered, "Deployment")["spec"]["template"]["spec"];
+		assert_eq!(spec["serviceAccountName"], "team-web");
+		assert_eq!(spec["volumes"][0]["configMap"]["name"], "team-cfg");
+		assert_eq!(
+			find(&rendered, "RoleBinding")["subjects"][0]["name"],
+			"team-web"
+		);
+	}
+
+	#[test]
+	fn fails_on_objects_moved_onto_each_other() {
+		let config = |namespace: &str| {
+			json!({
+				"kind": "ConfigMap",
+				"apiVersion": "v1",
+				"metadata": {"name": "cfg", "namespace": namespace}
+			})
+		};
+		let json = json!({"dev": config("dev"), "qa": config("qa")});
+
+		let kube = Kube::builder()
+			.value(json)
+			.transformers(Transformers {
+				namespace: Some(String::from("prod")),
+				..Default::default()
+			})
+			.build()
+			.unwrap();
+
+		assert_matches!(
+			kube.render(),
+			Err(Error::Object(error::Object::Duplicate { .. }))
+		);
+	}
+
+	#[test]
+	fn adds_labels_to_templates() {
+		let rendered = transform(
+			package(),
+			Transformers {
+				labels: BTreeMap::from([(String::from("team"), String::from("a"))]),
+				annotations: BTreeMap::from([(String::from("owner"), String::from("me"))]),
+				..Default::default()
+			},
+		);
+
+		let deployment = find(&rendered, "Deployment");
+		assert_eq!(deployment["metadata"]["labels"]["team"], "a");
+		assert_eq!(
+			deployment["spec"]["selector"]["matchLabels"],
+			json!({"app": "web"})
+		);
+		assert_eq!(
+			deployment["spec"]["template"]["metadata"]["labels"]["team"],
+			"a"
+		);
+		assert_eq!(
+			deployment["spec"]["template"]["metadata"]["annotations"]["owner"],
+			"me"
+		);
+
+		let service = find(&rendered, "Service");
+		assert_eq!(service["spec"]["selector"], json!({"app": "web"}));
+		assert_eq!(service["metadata"]["annotations"]["owner"], "me");
+
+		let config = find(&rendered, "ConfigMap");
+		assert_eq!(config["metadata"]["labels"]["team"], "a");
+		assert_eq!(config.get("spec"), None);
+	}
+
+	#[test]
+	fn adds_labels_to_selectors_when_included() {
+		let rendered = transform(
+			package(),
+			Transformers {
+				labels: BTreeMap::from([(String::from("team"), String::from("a"))]),
+				include_selectors: true,
+				..Default::default()
+			},
+		);
+
+		let deployment = find(&rendered, "Deployment");
+		assert_eq!(deployment["spec"]["selector"]["matchLabels"]["team"], "a");
+
+		let service = find(&rendered, "Service");
+		assert_eq!(
+			service["spec"]["selector"],
+			json!({"app": "web", "team": "a"})
+		);
+	}
+
+	#[test]
+	fn layers_transformers() {
+		let base = Transformers::try_from(json!({
+			"namespace": "dev",
+			"namePrefix": "team-",
+			"labels": {"team": "a", "tier": "web"}
+		}))
+		.unwrap();
+		let cli = Transformers {
+			namespace: Some(String::from("prod")),
+			labels: BTreeMap::from([(String::from("team"), String::from("b"))]),
+			..Default::default()
+		};
+
+		let layered = base.layer(cli);
+
+		assert_eq!(layered.namespace.as_deref(), Some("prod"));
+		assert_eq!(layered.name_prefix.as_deref(), Some("team-"));
+		assert_eq!(layered.labels["team"], "b");
+		assert_eq!(layered.labels["tier"], "web");
+	}
+
+	#[test]
+	fn fails_on_unknown_transformers() {
+		let error = Transformers::try_from(json!({"prefix": "team-"})).unwrap_err();
+
+		assert_matches!(error, Error::Transformers(_));
+	}
+}
+
+mod images {
+	use super::*;
+
+	fn render(val: Value, images: &[&str]) -> Vec<Value> {
+		let images = images.iter().map(|i| i.parse().unwrap()).collect();
+		let transformers = Transformers {
+			images,
+			..Default::default()
+		};
+
+		let kube = Kube::builder()
+			.value(val)
+			.transformers(transformers)
+			.build()
+			.unwrap();
+		let manifests: Vec<Manifest> = kube.try_into().unwrap();
+
+		manifests.iter().map(|m| m.value().clone()).collect()
+	}
+
+	fn cron_job(init: &str, main: &str) -> Value {
+		json!({
+			"kind": "CronJob",
+			"apiVersion": "batch/v1",
+			"metadata": {"name": "job"},
+			"spec": {"jobTemplate": {"spec": {"template": {"spec": {
+				"initContainers": [{"name": "init", "image": init}],
+				"containers": [{"name": "main", "image": main}]
+			}}}}}
+		})
+	}
+
+	fn images(value: &Value) -> Vec<&str> {
+		let spec = &value["spec"]["jobTemplate"]["spec"]["template"]["spec"];
+
+		["initContainers", "containers"]
+			.iter()
+			.map(|field| spec[field][0]["image"].as_str().unwrap())
+			.collect()
+	}
+
+	#[test]
+	fn replaces_images_by_name() {
+		let json = json!({ "job": cron_job("busybox", "app:1.0") });
+		let rendered = render(json, &["app=registry.io/app:2.0", "busybox:1.36"]);
+
+		assert_eq!(
+			images(&rendered[0]),
+			vec!["busybox:1.36", "registry.io/app:2.0"]
+		);
+	}
+
+	#[test]
+	fn pins_digests() {
+		let json = json!({ "job": cron_job("busybox", "localhost:5000/team/app:1.0") });
+		let rendered = render(json, &["localhost:5000/team/app@sha256:abc"]);
+
+		assert_eq!(
+			images(&rendered[0]),
+			vec!["busybox", "localhost:5000/team/app@sha256:abc"]
+		);
+	}
+
+	#[test]
+	fn ignores_other_names() {
+		let json = json!({ "job": cron_job("busybox", "registry.io/app:1.0") });
+		let rendered = render(json, &["app:2.0"]);
+
+		assert_eq!(images(&rendered[0]), vec!["busybox", "registry.io/app:1.0"]);
+	}
+
+	#[test]
+	fn parses_overrides() {
+		for image in ["app=other:1", "app:1", "registry.io:5000/app@sha256:abc"] {
+			let parsed: Image = image.parse().unwrap();
+
+			assert_eq!(parsed.to_string(), image);
+		}
+
+		for image in ["app", "=other", "app=", "app:"] {
+			assert_matches!(image.parse::<Image>(), Err(Error::Image(_)));
+		}
+	}
+}
+
+mod generated {
+	use super::*;
+
+	fn config_map(name: &str, original: &str) -> Value {
+		json!({
+			"kind": "ConfigMap",
+			"apiVersion": "v1",
+			"metadata": {
+				"name": name,
+				"annotations": {"kct.io/generated-from": original}
+			}
+		})
+	}
+
+	fn deployment(config: &str, namespace: Option<&str>) -> Value {
+		json!({
+			"kind": "Deployment",
+			"apiVersion": "apps/v1",
+			"metadata": {"name": "app", "namespace": namespace},
+			"spec": {"template": {"spec": {
+				"volumes": [{"name": "config", "configMap": {"name": config}}],
+				"containers": [{
+					"name": "app",
+					"envFrom": [{"configMapRef": {"name": config}}]
+				}]
+			}}}
+		})
+	}
+
+	fn render(val: Value) -> Vec<Value> {
+		let manifests = find_from(val).unwrap();
+
+		manifests.iter().map(|m| m.value().clone()).collect()
+	}
+
+	#[test]
+	fn references_follow_hashed_names() {
+		let json = json!({
+			"config": config_map("settings-abc123", "settings"),
+			"app": deployment("settings", None)
+		});
+		let rendered = render(json);
+		let spec = &rendered[1]["spec"]["template"]["spec"];
+
+		assert_eq!(spec["volumes"][0]["configMap"]["name"], "settings-abc123");
+		assert_eq!(
+			spec["containers"][0]["envFrom"][0]["configMapRef"]["name"],
+			"settings-abc123"
+		);
+	}
+
+	#[test]
+	fn keeps_references_to_other_namespaces() {
+		let mut config = config_map("settings-abc123", "settings");
+		config["metadata"]["namespace"] = json!("prod");
+
+		let json = json!({
+			"config": config,
+			"app": deployment("settings", Some("dev"))
+		});
+		let rendered = render(json);
+		let spec = &rendered[1]["spec"]["template"]["spec"];
+
+		assert_eq!(spec["volumes"][0]["configMap"]["name"], "settings");
+	}
+
+	#[test]
+	fn are_followed_by_transformers() {
+		let json = json!({
+			"config": config_map("settings-abc123", "settings"),
+			"app": deployment("settings", None)
+		});
+		let transformers = Transformers {
+			name_prefix: Some(String::from("team-")),
+			..Default::default()
+		};
+
+		let kube = Kube::builder()
+			.value(json)
+			.transformers(transformers)
+			.build()
+			.unwrap();
+		let manifests: Vec<Manifest> = kube.try_into().unwrap();
+		let deployment = manifests[1].value();
+
+		assert_eq!(
+			deployment["spec"]["template"]["spec"]["volumes"][0]["configMap"]["name"],
+			"team-settings-abc123"
+		);
+	}
+}
+
+mod redaction {
+	use super::*;
+
+	fn secret() -> Value {
+		json!({
+			"kind": "Secret",
+			"apiVersion": "v1",
+			"metadata": {"name": "db"},
+			"data": {"user": "YWRtaW4="},
+			"stringData": {"pass": "hunter22"}
+		})
+	}
+
+	#[test]
+	fn masks_secret_values() {
+		let mut object = secret();
+		Redactor::default().value(&mut object);
+
+		assert_eq!(object["data"]["user"], Redactor::mask("YWRtaW4="));
+		assert_eq!(object["stringData"]["pass"], Redactor::mask("hunter22"));
+		assert_eq!(object["metadata"]["name"], "db");
+	}
+
+	#[test]
+	fn masks_are_stable() {
+		assert_eq!(Redactor::mask("hunter22"), Redactor::mask("hunter22"));
+		assert_ne!(Redactor::mask("hunter22"), Redactor::mask("hunter23"));
+		assert!(!Redactor::mask("hunter22").contains("hunter22"));
+	}
+
+	#[test]
+	fn masks_sensitive_values_anywhere() {
+		let redactor = Redactor::default().with_sensitive(vec![String::from("hunter22")]);
+		let mut object = json!({
+			"kind": "ConfigMap",
+			"apiVersion": "v1",
+			"metadata": {"name": "db"},
+			"data": {"url": "postgres://admin:hunter22@db"}
+		});
+		redactor.value(&mut object);
+
+		let masked = format!("postgres://admin:{}@db", Redactor::mask("hunter22"));
+		assert_eq!(object["data"]["url"], Value::String(masked.clone()));
+		assert_eq!(redactor.text("postgres://admin:hunter22@db"), masked);
+	}
+
+	#[test]
+	fn masks_short_values_only_whole() {
+		let redactor =
+			Redactor::default().with_sensitive(vec![String::from("db"), String::from("1")]);
+		let mut object = json!({
+			"kind": "ConfigMap",
+			"apiVersion": "v1",
+			"metadata": {"name": "db-config"},
+			"data": {"host": "db", "replicas": 1}
+		});
+		redactor.value(&mut object);
+
+		assert_eq!(object["metadata"]["name"], "db-config");
+		assert_eq!(object["data"]["host"], Value::String(Redactor::mask("db")));
+		assert_eq!(object["data"]["replicas"], 1);
+		assert_eq!(redactor.text("db-config has 1 key"), "db-config has 1 key");
+	}
+
+	#[test]
+	fn masks_long_numbers() {
+		let redactor = Redactor::default().with_sensitive(vec![String::from("80443210")]);
+		let mut object =
+			json!({"kind": "ConfigMap", "apiVersion": "v1", "data": {"pin": 80443210}});
+		redactor.value(&mut object);
+
+		assert_eq!(
+			object["data"]["pin"],
+			Value::String(Redactor::mask("80443210"))
+		);
+		assert_eq!(
+			redactor.text("pin is 80443210"),
+			format!("pin is {}", Redactor::mask("80443210"))
+		);
+	}
+}
+
+mod sealing {
+	use super::*;
+
+	use base64::engine::general_purpose::STANDARD;
+	use base64::Engine;
+	use kct_helper::crypt::{self, Keys};
+	use kct_kube::seal::Scope;
+	use openssl::asn1::Asn1Time;
+	use openssl::encrypt::Decrypter;
+	use openssl::hash::MessageDigest;
+	use openssl::pkey::{PKey, Private};
+	use openssl::rsa::{Padding, Rsa};
+	use openssl::symm::{decrypt_aead, Cipher};
+	use openssl::x509::{X509Name, X509};
+
+	const AGE_KEY: &str =
+		"AGE-SECRET-KEY-18ED8FJAXP0LNE9JQJQFWC077YWADN6TYVCLHLRJGE9VM54TWXLJQHM35CN";
+	const AGE_RECIPIENT: &str = "age145qn8fx07xwy4vm48ur0sxk57d9329t9clpfdgdc63s8j0axu9rqd2h6qn";
+
+	fn secret() -> Vec<Manifest> {
+		let object = json!({
+			"kind": "Secret",
+			"apiVersion": "v1",
+			"metadata": {"name": "db", "namespace": "prod"},
+			"type": "Opaque",
+			"data": {"user": "YWRtaW4="},
+			"stringData": {"pass": "hunter22"}
+		});
+
+		vec![Manifest::from((PathBuf::from("db"), object))]
+	}
+
+	fn certificate() -> (PKey<Private>, Vec<u8>) {
+		let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+
+		let mut name = X509Name::builder().unwrap();
+		name.append_entry_by_text("CN", "sealed-secret").unwrap();
+		let name = name.build();
+
+		let mut builder = X509::builder().unwrap();
+		builder.set_version(2).unwrap();
+		builder.set_subject_name(&name).unwrap();
+		builder.set_issuer_name(&name).unwrap();
+		builder.set_pubkey(&key).unwrap();
+		builder
+			.set_not_before(&Asn1Time::days_from_now(0).unwrap())
+			.unwrap();
+		builder
+			.set_not_after(&Asn1Time::days_from_now(1).unwrap())
+			.unwrap();
+		builder.sign(&key, MessageDigest::sha256()).unwrap();
+
+		(key, builder.build().to_pem().unwrap())
+	}
+
+	/// Reverses the encryption of the controller, as it does to unseal
+	fn unseal(key: &PKey<Private>, sealed: &Value, label: &str) -> Vec<u8> {
+		let sealed = STANDARD.decode(sealed.as_str().unwrap()).unwrap();
+		let size = u16::from_be_bytes([sealed[0], sealed[1]]) as usize;
+		let (wrapped, rest) = sealed[2..].split_at(size);
+		let (data, tag) = rest.split_at(rest.len() - 16);
+
+		let mut decrypter = Decrypter::new(key).unwrap();
+		decrypter.set_rsa_padding(Padding::PKCS1_OAEP).unwrap();
+		decrypter.set_rsa_oaep_md(MessageDigest::sha256()).unwrap();
+		decrypter.set_rsa_mgf1_md(MessageDigest::sha256()).unwrap();
+		if !label.is_empty() {
+			decrypter.set_rsa_oaep_label(label.as_bytes()).unwrap();
+		}
+
+		let mut session = vec![0u8; decrypter.decrypt_len(wrapped).unwrap()];
+		let length = decrypter.decrypt(wrapped, &mut session).unwrap();
+		session.truncate(length);
+
+		let nonce = [0u8; 12];
+		decrypt_aead(
+			Cipher::aes_256_gcm(),
+			&session,
+			Some(&nonce),
+			&[],
+			data,
+			tag,
+		)
+		.unwrap()
+	}
+
+	#[test]
+	fn seals_into_sealed_secrets() {
+		let (key, pem) = certificate();
+		let mut manifests = secret();
+
+		let sealer = Sealer::certificate(&pem, Scope::Strict).unwrap();
+		sealer.apply(&mut manifests).unwrap();
+
+		let sealed = manifests[0].value();
+		assert_eq!(sealed["kind"], "SealedSecret");
+		assert_eq!(sealed["apiVersion"], "bitnami.com/v1alpha1");
+		assert_eq!(
+			sealed["metadata"],
+			json!({"name": "db", "namespace": "prod"})
+		);
+		assert_eq!(sealed["spec"]["template"]["type"], "Opaque");
+
+		let data = &sealed["spec"]["encryptedData"];
+		assert_eq!(unseal(&key, &data["user"], "prod/db"), b"admin");
+		assert_eq!(unseal(&key, &data["pass"], "prod/db"), b"hunter22");
+	}
+
+	#[test]
+	fn binds_sealed_secrets_to_their_scope() {
+		let (key, pem) = certificate();
+		let mut manifests = secret();
+
+		let sealer = Sealer::certificate(&pem, Scope::ClusterWide).unwrap();
+		sealer.apply(&mut manifests).unwrap();
+
+		let sealed = manifests[0].value();
+		let annotations = &sealed["metadata"]["annotations"];
+		assert_eq!(
+			annotations["sealedsecrets.bitnami.com/cluster-wide"],
+			"true"
+		);
+		assert_eq!(
+			unseal(&key, &sealed["spec"]["encryptedData"]["user"], ""),
+			b"admin"
+		);
+
+		let object = json!({"kind": "Secret", "apiVersion": "v1", "metadata": {"name": "db"}});
+		let mut manifests = vec![Manifest::from((PathBuf::from("db"), object))];
+		let sealer = Sealer::certificate(&pem, Scope::Strict).unwrap();
+		assert_matches!(sealer.apply(&mut manifests), Err(Error::Seal(_)));
+	}
+
+	#[test]
+	fn encrypts_secrets_with_sops() {
+		let mut manifests = secret();
+
+		let sealer = Sealer::sops(&[String::from(AGE_RECIPIENT)]).unwrap();
+		sealer.apply(&mut manifests).unwrap();
+
+		let encrypted = manifests[0].value().clone();
+		assert_eq!(encrypted["kind"], "Secret");
+		assert_eq!(encrypted["metadata"]["name"], "db");
+		assert!(encrypted["data"]["user"]
+			.as_str()
+			.unwrap()
+			.starts_with("ENC[AES256_GCM,"));
+
+		let keys = Keys::parse(AGE_KEY).unwrap();
+		let ordered = serde_json::from_value(encrypted).unwrap();
+		let decrypted = crypt::decrypt_sops(ordered, &keys).unwrap();
+		assert_eq!(decrypted["data"]["user"], "YWRtaW4=");
+		assert_eq!(decrypted["stringData"]["pass"], "hunter22");
+	}
+
+	#[test]
+	fn leaves_other_objects_alone() {
+		let object = json!({"kind": "ConfigMap", "apiVersion": "v1", "data": {"a": "b"}});
+		let mut manifests = vec![Manifest::from((PathBuf::from("cm"), object.clone()))];
+
+		let sealer = Sealer::sops(&[String::from(AGE_RECIPIENT)]).unwrap();
+		sealer.apply(&mut manifests).unwrap();
+
+		assert_eq!(manifests[0].value(), &object);
+	}
+}
+
+mod normalization {
+	use super::*;
+
+	fn secret(data: Value, string_data: Value) -> Value {
+		json!({
+			"kind": "Secret",
+			"apiVersion": "v1",
+			"metadata": {"name": "db"},
+			"data": data,
+			"stringData": string_data
+		})
+	}
+
+	fn config_map(data: Value) -> Value {
+		json!({
+			"kind": "ConfigMap",
+			"apiVersion": "v1",
+			"metadata": {"name": "settings"},
+			"data": data
+		})
+	}
+
+	#[test]
+	fn encodes_string_data() {
+		let json = json!({
+			"secret": secret(
+				json!({"user": "YWRtaW4=", "host": "ZGI="}),
+				json!({"user": "root", "pass": "hunter22"})
+			)
+		});
+		let manifests = find_from(json).unwrap();
+		let object = manifests[0].value();
+
+		assert_eq!(
+			object["data"],
+			json!({"user": "cm9vdA==", "host": "ZGI=", "pass": "aHVudGVyMjI="})
+		);
+		assert!(object.get("stringData").is_none());
+	}
+
+	#[test]
+	fn rejects_invalid_base64() {
+		let json = json!({"secret": secret(json!({"user": "admin!"}), Value::Null)});
+
+		assert_matches!(
+			find_from(json),
+			Err(Error::InvalidData(_, field, _)) if field == "data.user"
+		);
+	}
+
+	#[test]
+	fn rejects_objects_over_the_limit() {
+		let large = "a".repeat(1024 * 1024 + 1);
+		let json = json!({"config": config_map(json!({"large": large}))});
+
+		assert_matches!(find_from(json), Err(Error::TooLarge(_, size)) if size == 1024 * 1024 + 1);
+
+		let fitting = "a".repeat(1024 * 1024);
+		let json = json!({"config": config_map(json!({"large": fitting}))});
+
+		assert!(find_from(json).is_ok());
+	}
+}
+
+mod install {
+	use super::*;
+
+	use std::sync::{Arc, Mutex};
+
+	use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+	use tokio::net::{TcpListener, TcpStream};
+
+	type Requests = Arc<Mutex<Vec<String>>>;
+
+	const CORE: &str =
+		r#"{"kind": "APIVersions", "versions": ["v1"], "serverAddressByClientCIDRs": []}"#;
+	const GROUPS: &str = r#"{"kind": "APIGroupList", "apiVersion": "v1", "groups": []}"#;
+	const RESOURCES: &str = r#"{
+		"kind": "APIResourceList",
+		"groupVersion": "v1",
+		"resources": [
+			{"name": "namespaces", "singularName": "", "namespaced": false, "kind": "Namespace", "verbs": ["get", "patch"]},
+			{"name": "configmaps", "singularName": "", "namespaced": true, "kind": "ConfigMap", "verbs": ["get", "patch"]}
+		]
+	}"#;
+
+	/// Answers discovery with the core API and echoes the applied objects back, keeping the
+	/// method and path of every request
+	async fn serve(listener: TcpListener, requests: Requests) {
+		loop {
+			let (stream, _) = listener.accept().await.unwrap();
+			tokio::spawn(respond(stream, requests.clone()));
+		}
+	}
+
+	async fn respond(stream: TcpStream, requests: Requests) {
+		let mut reader = BufReader::new(stream);
+
+		loop {
+			let mut line = String::new();
+			if reader.read_line(&mut line).await.unwrap_or(0) == 0 {
+				return;
+			}
+
+			let mut length = 0;
+			loop {
+				let mut header = String::new();
+				reader.read_line(&mut header).await.unwrap();
+				if header.trim().is_empty() {
+					break;
+				}
+
+				if let Some((name, value)) = header.split_once(':') {
+					if name.eq_ignore_ascii_case("content-length") {
+						length = value.trim().parse().unwrap();
+					}
+				}
+			}
+
+			let mut body = vec![0; length];
+			reader.read_exact(&mut body).await.unwrap();
+
+			let mut parts = line.split_whitespace();
+			let method = parts.next().unwrap().to_string();
+			let path = parts.next().unwrap().split('?').next().unwrap().to_string();
+			requests.lock().unwrap().push(format!("{method} {path}"));
+
+			let response = match path.as_str() {
+				"/api" => CORE.to_string(),
+				"/apis" => GROUPS.to_string(),
+				"/api/v1" => RESOURCES.to_string(),
+				_ => String::from_utf8(body).unwrap(),
+			};
+
+			let reply = format!(
+				"HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{response}",
+				response.len()
+			);
+			reader.get_mut().write_all(reply.as_bytes()).await.unwrap();
+		}
+	}
+
+	fn config(server: &str) -> String {
+		format!(
+			r#"
+apiVersion: v1
+kind: Config
+current-context: fake
+clusters:
+  - name: fake
+    cluster:
+      server: {server}
+contexts:
+  - name: fake
+    context:
+      cluster: fake
+      user: fake
+users:
+  - name: fake
+    user: {{}}
+"#
+		)
+	}
+
+	#[tokio::test]
+	async fn applies_objects_in_their_namespace() {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let server = format!("http://{}", listener.local_addr().unwrap());
+		let requests = Requests::default();
+		tokio::spawn(serve(listener, requests.clone()));
+
+		let dir = kct_testing::dir::tmp();
+		let kubeconfig = dir.path().join("config");
+		std::fs::write(&kubeconfig, config(&server)).unwrap();
+		std::env::set_var("KUBECONFIG", &kubeconfig);
+
+		let config_map = |name: &str, namespace: Option<&str>| {
+			let mut object = json!({
+				"kind": "ConfigMap",
+				"apiVersion": "v1",
+				"metadata": {"name": name}
+			});
+			if let Some(namespace) = namespace {
+				object["metadata"]["namespace"] = json!(namespace);
+			}
+
+			object
+		};
+		let json = json!({
+			"a": {"kind": "Namespace", "apiVersion": "v1", "metadata": {"name": "team"}},
+			"b": config_map("placed", Some("team")),
+			"c": config_map("defaulted", None)
+		});
+
+		let kube = Kube::builder().value(json).build().unwrap();
+		kube.install().await.unwrap();
+
+		let mut patched: Vec<String> = requests
+			.lock()
+			.unwrap()
+			.iter()
+			.filter(|request| request.starts_with("PATCH"))
+			.cloned()
+			.collect();
+		patched.sort();
+
+		assert_eq!(
+			patched,
+			vec![
+				"PATCH /api/v1/namespaces/default/configmaps/defaulted",
+				"PATCH /api/v1/namespaces/team",
+				"PATCH /api/v1/namespaces/team/configmaps/placed",
+			]
+		);
+	}
+}
@@ -0,0 +1,99 @@
+use crate::error::Error;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use kct_compiler::{Context, TargetBuilder};
+use kct_helper::io;
+use kct_jsonnet::Executable;
+use serde_json::Value;
+
+/// Formats a document of the package can be written in, by their extensions
+const EXTENSIONS: [&str; 4] = ["json", "yaml", "yml", "jsonnet"];
+
+/// Finds the file for the document with the name, which has to be written in a single format
+pub fn find(root: &Path, name: &str) -> Result<Option<PathBuf>, Error> {
+	let mut found: Vec<PathBuf> = EXTENSIONS
+		.iter()
+		.map(|extension| root.join(format!("{name}.{extension}")))
+		.filter(|path| path.exists())
+		.collect();
+
+	match found.len() {
+		0 | 1 => Ok(found.pop()),
+		_ => Err(Error::Ambiguous(name.to_string())),
+	}
+}
+
+/// Files of the documents within the directory, by their names without the extension
+pub fn list(dir: &Path) -> Result<BTreeMap<String, PathBuf>, Error> {
+	let unreadable = |err| Error::UnreadableDir(dir.to_path_buf(), err);
+	let entries = fs::read_dir(dir).map_err(unreadable)?;
+
+	let mut found = BTreeMap::new();
+	for entry in entries {
+		let path = entry.map_err(unreadable)?.path();
+
+		let extension = path.extension().and_then(|e| e.to_str());
+		let name = path.file_stem().and_then(|n| n.to_str());
+		let name = match (extension, name) {
+			(Some(extension), Some(name)) if EXTENSIONS.contains(&extension) && path.is_file() => {
+				name.to_string()
+			}
+			_ => continue,
+		};
+
+		if found.insert(name.clone(), path).is_some() {
+			let dir = dir.file_name().and_then(|d| d.to_str()).unwrap_or_default();
+
+			return Err(Error::Ambiguous(format!("{dir}/{name}")));
+		}
+	}
+
+	Ok(found)
+}
+
+/// Reads the document as JSON or YAML, or evaluates it as Jsonnet with the `lib` and `vendor` of
+/// the package at the root
+pub fn read(root: &Path, file: &Path) -> Result<Value, String> {
+	let extension = file
+		.extension()
+		.and_then(|e| e.to_str())
+		.unwrap_or_default();
+
+	if extension == "jsonnet" {
+		return evaluate(root, file).map_err(|err| match err {
+			Error::Evaluation(_file, err) => err.to_string(),
+			err => err.to_string(),
+		});
+	}
+
+	let contents = io::from_file(file).map_err(|err| err.to_string())?;
+	match extension {
+		"yaml" | "yml" => serde_yaml::from_str(&contents).map_err(|err| err.to_string()),
+		_ => serde_json::from_str(&contents).map_err(|err| err.to_string()),
+	}
+}
+
+/// Evaluates the Jsonnet file with the `lib` and `vendor` of the package at the root available
+/// for imports, the same way for its documents and for inputs
+pub fn evaluate(root: &Path, file: &Path) -> Result<Value, Error> {
+	let context = Context::builder().root(root.to_path_buf()).build()?;
+	let target = TargetBuilder::default()
+		.dir(root.to_path_buf())
+		.main(file.to_path_buf())
+		.build()
+		.unwrap();
+
+	let executable = Executable {
+		vendor: context.vendor().to_path_buf(),
+		lib: target.lib().to_path_buf(),
+		main: file.to_path_buf(),
+		props: HashMap::new(),
+	};
+
+	executable
+		.run()
+		.map_err(|err| Error::Evaluation(file.to_path_buf(), err))
+}
@@ -1,24 +1,42 @@
-use kct_compiler::Error as CompilerError;
+use std::io;
+use std::path::PathBuf;
+
+use kct_compiler::{Error as CompilerError, Violations};
+use kct_jsonnet::Error as JsonnetError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum Error {
+	#[error("Package has more than one {0} file, keep only one of JSON, YAML, or Jsonnet")]
+	Ambiguous(String),
 	#[error("Package doesn't have a main template")]
 	NoMain,
 	#[error("Package is a directory")]
 	InvalidFormat,
+	#[error("Unable to read the directory {}", .0.display())]
+	UnreadableDir(PathBuf, #[source] io::Error),
 	#[error("Missing package file")]
 	NoSpec,
-	#[error("Invalid package file")]
-	InvalidSpec,
+	#[error("Invalid package file: {0}")]
+	InvalidSpec(String),
 	#[error("No schema file to validate your example")]
 	NoSchema,
-	#[error("Invalid schema file")]
-	InvalidSchema,
+	#[error("Invalid schema file: {0}")]
+	InvalidSchema(String),
+	#[error("Invalid merge strategy {0} in the schema, use replace, append, or merge")]
+	InvalidStrategy(String),
 	#[error("No example file")]
 	NoExample,
-	#[error("Invalid example file")]
-	InvalidExample,
+	#[error("Invalid example file: {0}")]
+	InvalidExample(String),
+	#[error("Invalid preset file for {0}: {1}")]
+	InvalidPreset(String, String),
+	#[error("The package requires an input, the defaults of its schema aren't enough:\n{0}")]
+	MissingInput(Violations),
+	#[error("Unable to run the generators: {0}")]
+	Generator(String),
+	#[error("Unable to evaluate {}", .0.display())]
+	Evaluation(PathBuf, #[source] JsonnetError),
 	#[error(transparent)]
 	Compilation(#[from] CompilerError),
 }
@@ -1,3 +1,4 @@
+mod document;
 mod error;
 mod property;
 mod schema;
@@ -5,24 +6,27 @@ mod spec;
 
 pub use crate::error::Error;
 
-use crate::property::{Files, Include};
+use crate::property::{Files, Generate, Include};
 use crate::schema::Schema;
 use crate::spec::Spec;
 
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::path::{Path, PathBuf};
 
 use anyhow::Result;
 use kct_compiler::property::{Name, Prop};
 use kct_compiler::{Compiler, Release, Target, TargetBuilder};
-use kct_compiler::{Context, Input};
-use kct_helper::io;
+use kct_compiler::{Context, Input, Validator, Violations};
+use kct_helper::json::Strategy;
 use serde_json::{Map, Value};
 
-const SCHEMA_FILE: &str = "schema.json";
-const SPEC_FILE: &str = "kcp.json";
-const EXAMPLE_FILE: &str = "example.json";
+const SCHEMA_FILE: &str = "schema";
+const SPEC_FILE: &str = "kcp";
+const EXAMPLE_FILE: &str = "example";
 const MAIN_FILE: &str = "templates/main.jsonnet";
+const PATCHES_DIR: &str = "patches";
+const PRESETS_DIR: &str = "presets";
 
 #[derive(Debug, Clone)]
 pub struct Package {
@@ -31,6 +35,8 @@ pub struct Package {
 	pub spec: Spec,
 	pub schema: Option<Schema>,
 	pub example: Option<Value>,
+	pub presets: BTreeMap<String, Value>,
+	pub patches: Option<PathBuf>,
 }
 
 impl TryFrom<&Path> for Package {
@@ -39,43 +45,42 @@ impl TryFrom<&Path> for Package {
 	fn try_from(root: &Path) -> Result<Self, Self::Error> {
 		let root = PathBuf::from(root);
 
-		let spec = {
-			let mut path = root.clone();
-			path.push(SPEC_FILE);
+		let spec = match document::find(&root, SPEC_FILE)? {
+			Some(path) => Spec::try_from(path)?,
+			None => return Err(Error::NoSpec),
+		};
 
-			if path.exists() {
-				Spec::try_from(path)?
-			} else {
-				return Err(Error::NoSpec);
-			}
+		let schema = match document::find(&root, SCHEMA_FILE)? {
+			Some(path) => Some(Schema::try_from(path)?),
+			None => None,
 		};
 
-		let schema = {
-			let mut path = root.clone();
-			path.push(SCHEMA_FILE);
+		let example = match document::find(&root, EXAMPLE_FILE)? {
+			Some(path) => {
+				let value = document::read(&root, &path).map_err(Error::InvalidExample)?;
 
-			if path.exists() {
-				Some(Schema::try_from(path)?)
-			} else {
-				None
+				Some(value)
 			}
+			None => None,
 		};
 
-		let example = {
+		let presets = {
 			let mut path = root.clone();
-			path.push(EXAMPLE_FILE);
+			path.push(PRESETS_DIR);
 
-			if path.exists() {
-				let value = io::from_file(&path)
-					.map_err(|_err| Error::InvalidExample)
-					.and_then(|contents| {
-						serde_json::from_str(&contents).map_err(|_err| Error::InvalidExample)
-					})?;
-
-				Some(value)
+			let files = if path.is_dir() {
+				document::list(&path)?
 			} else {
-				None
-			}
+				BTreeMap::new()
+			};
+
+			files
+				.into_iter()
+				.map(|(name, file)| match document::read(&root, &file) {
+					Ok(value) => Ok((name, value)),
+					Err(reason) => Err(Error::InvalidPreset(name, reason)),
+				})
+				.collect::<Result<BTreeMap<String, Value>, Error>>()?
 		};
 
 		let main = {
@@ -89,8 +94,20 @@ impl TryFrom<&Path> for Package {
 			}
 		};
 
+		let patches = {
+			let mut path = root.clone();
+			path.push(PATCHES_DIR);
+
+			if path.is_dir() {
+				Some(path)
+			} else {
+				None
+			}
+		};
+
 		match (&schema, &example) {
 			(None, Some(_)) => return Err(Error::NoSchema),
+			(None, None) if !presets.is_empty() => return Err(Error::NoSchema),
 			(Some(_), None) => return Err(Error::NoExample),
 			_ => (),
 		};
@@ -101,6 +118,8 @@ impl TryFrom<&Path> for Package {
 			spec,
 			schema,
 			example,
+			presets,
+			patches,
 		};
 
 		Ok(package)
@@ -115,7 +134,18 @@ impl Package {
 			.release(release)
 			.build()?;
 
-		let input = input.map(|v| (&Input(v)).into());
+		// packages with a schema start from an empty input, so its defaults apply without one, as
+		// long as they're enough to satisfy it
+		let input = match (input, &self.schema) {
+			(None, Some(_schema)) => {
+				let empty = Value::Object(Map::new());
+				self.validate(&empty).map_err(Error::MissingInput)?;
+
+				Some(empty)
+			}
+			(input, _schema) => input,
+		};
+		let input = input.map(|v| (&Input(self.with_defaults(v))).into());
 
 		let compiler = Compiler::new(&context)
 			.with_target(target)
@@ -124,8 +154,64 @@ impl Package {
 		self.compile_with(compiler)
 	}
 
+	/// Checks the input against the schema after filling in its defaults, as it would be compiled
+	pub fn validate(&self, input: &Value) -> Result<(), Violations> {
+		match &self.schema {
+			Some(schema) => Validator::from(schema.clone()).run(&self.with_defaults(input.clone())),
+			None => Ok(()),
+		}
+	}
+
+	/// Input with the defaults from the schema for the properties it doesn't have
+	pub fn with_defaults(&self, mut input: Value) -> Value {
+		if let Some(schema) = &self.schema {
+			schema.defaults(&mut input);
+		}
+
+		input
+	}
+
+	/// Values from the input that the schema marks with `writeOnly` or `sensitive`
+	pub fn sensitive(&self, input: Option<&Value>) -> Vec<String> {
+		match (&self.schema, input) {
+			(Some(schema), Some(input)) => schema.sensitive(input),
+			_ => vec![],
+		}
+	}
+
+	/// Types the schema declares for the input at the path, empty when it doesn't say
+	pub fn input_types(&self, path: &[String]) -> Vec<String> {
+		match &self.schema {
+			Some(schema) => schema.types(path),
+			None => vec![],
+		}
+	}
+
+	/// Evaluates a Jsonnet file with the `lib` and `vendor` of the package available for imports,
+	/// so inputs can be computed from the same libraries as the templates
+	pub fn evaluate(&self, file: &Path) -> Result<Value, Error> {
+		document::evaluate(&self.root, file)
+	}
+
+	/// Properties the schema declares for the input at the path
+	pub fn input_properties(&self, path: &[String]) -> Vec<String> {
+		match &self.schema {
+			Some(schema) => schema.properties(path),
+			None => vec![],
+		}
+	}
+
+	/// How inputs layer the array at the path, when the schema says
+	pub fn input_strategy(&self, path: &[String]) -> Option<Strategy> {
+		self.schema
+			.as_ref()
+			.and_then(|schema| schema.strategy(path))
+	}
+
 	pub fn compile_with(self, compiler: Compiler) -> Result<Value, Error> {
-		let compiler = self.augment(compiler);
+		let generated =
+			property::generated(&self.root, &self.spec.generators).map_err(Error::Generator)?;
+		let compiler = self.augment(compiler).with_static_prop(Some(generated));
 
 		let value = compiler.compile()?;
 
@@ -136,6 +222,7 @@ impl Package {
 		let mut compiler = compiler
 			.with_static_prop(Some((&self).into()))
 			.with_dynamic_prop(Some(Box::new(Files)))
+			.with_dynamic_prop(Some(Box::new(Generate)))
 			.with_dynamic_prop(Some(Box::new(Include)));
 
 		compiler = match self.schema {
@@ -56,24 +56,7 @@ impl Generator for Files {
 }
 
 fn compile_template(root: &Path, glob: &str, input: &Value) -> Result<Vec<String>, String> {
-	let mut templates_dir = root.to_path_buf();
-	templates_dir.push(TEMPLATES_FOLDER);
-
-	if !templates_dir.exists() {
-		return Err(String::from("No files folder to search for templates"));
-	}
-
-	let globwalker = GlobWalkerBuilder::new(templates_dir, glob)
-		.build()
-		.map_err(|err| format!("Invalid glob provided ({glob}): {err}"))?;
-
-	let entries: Vec<DirEntry> = globwalker
-		.collect::<Result<_, _>>()
-		.map_err(|err| format!("Unable to resolve globs: {err}"))?;
-
-	let mut paths: Vec<PathBuf> = entries.into_iter().map(DirEntry::into_path).collect();
-
-	paths.sort();
+	let paths = resolve(root, glob)?;
 
 	let contents: Vec<String> = paths
 		.into_iter()
@@ -94,3 +77,27 @@ fn compile_template(root: &Path, glob: &str, input: &Value) -> Result<Vec<String
 
 	Ok(compiled)
 }
+
+/// Finds the files matching the glob inside the files folder of the package, sorted by path
+pub(crate) fn resolve(root: &Path, glob: &str) -> Result<Vec<PathBuf>, String> {
+	let mut templates_dir = root.to_path_buf();
+	templates_dir.push(TEMPLATES_FOLDER);
+
+	if !templates_dir.exists() {
+		return Err(String::from("No files folder to search for templates"));
+	}
+
+	let globwalker = GlobWalkerBuilder::new(templates_dir, glob)
+		.build()
+		.map_err(|err| format!("Invalid glob provided ({glob}): {err}"))?;
+
+	let entries: Vec<DirEntry> = globwalker
+		.collect::<Result<_, _>>()
+		.map_err(|err| format!("Unable to resolve globs: {err}"))?;
+
+	let mut paths: Vec<PathBuf> = entries.into_iter().map(DirEntry::into_path).collect();
+
+	paths.sort();
+
+	Ok(paths)
+}
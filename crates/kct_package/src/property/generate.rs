@@ -0,0 +1,232 @@
+use crate::property::files;
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use kct_compiler::property::{Callback, Generator, Name, Prop};
+use kct_compiler::{Runtime, Trace};
+use kct_helper::annotation::GENERATED_FROM;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use sha2::{Digest, Sha256};
+
+/// Amount of hexadecimal digits from the content hash appended to the names
+const HASH_SIZE: usize = 10;
+
+pub struct Generate;
+
+#[derive(Trace)]
+struct Handler {
+	root: PathBuf,
+}
+
+impl Callback for Handler {
+	fn call(&self, params: HashMap<String, Value>) -> Result<Value, String> {
+		let spec = params.get("spec").cloned().unwrap_or(Value::Null);
+
+		generate(&self.root, spec)
+	}
+}
+
+impl Generator for Generate {
+	fn generate(&self, runtime: &Runtime) -> Prop {
+		let root = runtime.target().dir().to_path_buf();
+
+		let params = vec![String::from("spec")];
+		let handler = Handler { root };
+		Prop::callable(Name::Generate, params, handler)
+	}
+
+	fn name(&self) -> Name {
+		Name::Generate
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum Kind {
+	ConfigMap,
+	Secret,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Recipe {
+	kind: Kind,
+	name: String,
+	#[serde(default)]
+	files: Vec<String>,
+	#[serde(default)]
+	envs: Vec<String>,
+	#[serde(default)]
+	literals: BTreeMap<String, Value>,
+	#[serde(rename = "type")]
+	secret_type: Option<String>,
+	#[serde(default = "enabled")]
+	hash: bool,
+}
+
+fn enabled() -> bool {
+	true
+}
+
+/// Builds a ConfigMap or Secret from files, env files, and literals
+///
+/// Files and env files are globs inside the files folder of the package, the former keyed by
+/// their file name and the latter holding `KEY=VALUE` lines. Unless disabled, a hash of the
+/// contents is appended to the name so workloads roll out whenever they change, and the original
+/// name is kept in an annotation for the references to follow it.
+pub(crate) fn generate(root: &Path, spec: Value) -> Result<Value, String> {
+	let recipe: Recipe =
+		serde_json::from_value(spec).map_err(|err| format!("Invalid generator: {err}"))?;
+
+	let mut entries = BTreeMap::new();
+
+	for glob in &recipe.files {
+		for path in matching(root, glob)? {
+			let key = path
+				.file_name()
+				.map(|n| n.to_string_lossy().to_string())
+				.unwrap_or_default();
+			let contents = fs::read(&path)
+				.map_err(|err| format!("Unable to read {}: {err}", path.display()))?;
+
+			insert(&mut entries, &recipe.name, key, contents)?;
+		}
+	}
+
+	for glob in &recipe.envs {
+		for path in matching(root, glob)? {
+			let contents = fs::read_to_string(&path)
+				.map_err(|err| format!("Unable to read {}: {err}", path.display()))?;
+
+			for (key, value) in parse_env(&path, &contents)? {
+				insert(&mut entries, &recipe.name, key, value.into_bytes())?;
+			}
+		}
+	}
+
+	for (key, value) in &recipe.literals {
+		let value = match value {
+			Value::String(value) => value.clone(),
+			Value::Number(_) | Value::Bool(_) => value.to_string(),
+			_ => return Err(format!("Literal {key} should be a string")),
+		};
+
+		insert(&mut entries, &recipe.name, key.clone(), value.into_bytes())?;
+	}
+
+	let mut data = Map::new();
+	let mut binary = Map::new();
+	for (key, value) in entries {
+		let encoded = Value::String(STANDARD.encode(&value));
+		match (recipe.kind, String::from_utf8(value)) {
+			(Kind::Secret, _) => data.insert(key, encoded),
+			(Kind::ConfigMap, Ok(text)) => data.insert(key, Value::String(text)),
+			(Kind::ConfigMap, Err(_)) => binary.insert(key, encoded),
+		};
+	}
+
+	let mut object = Map::new();
+	object.insert(String::from("apiVersion"), json!("v1"));
+	object.insert(String::from("kind"), json!(recipe.kind));
+	object.insert(String::from("metadata"), json!({ "name": recipe.name }));
+
+	if recipe.kind == Kind::Secret {
+		let secret_type = recipe.secret_type.as_deref().unwrap_or("Opaque");
+		object.insert(String::from("type"), json!(secret_type));
+	}
+	if !data.is_empty() {
+		object.insert(String::from("data"), Value::Object(data));
+	}
+	if !binary.is_empty() {
+		object.insert(String::from("binaryData"), Value::Object(binary));
+	}
+
+	if recipe.hash {
+		let name = format!("{}-{}", recipe.name, hash(&object));
+
+		object["metadata"] = json!({
+			"name": name,
+			"annotations": { GENERATED_FROM: recipe.name },
+		});
+	}
+
+	Ok(Value::Object(object))
+}
+
+/// Builds the objects of the generators declared in the spec into the `generated` property,
+/// keyed by their names, so templates place them wherever they belong
+pub(crate) fn generated(root: &Path, specs: &[Value]) -> Result<Prop, String> {
+	let mut generated = Map::new();
+	for spec in specs {
+		let name = spec
+			.get("name")
+			.and_then(|n| n.as_str())
+			.ok_or_else(|| String::from("Invalid generator: missing field `name`"))?
+			.to_string();
+
+		if generated.contains_key(&name) {
+			return Err(format!("Generator {name} is declared more than once"));
+		}
+
+		let object = generate(root, spec.clone())?;
+		generated.insert(name, object);
+	}
+
+	Ok(Prop::primitive(Name::Generated, Value::Object(generated)))
+}
+
+fn matching(root: &Path, glob: &str) -> Result<Vec<PathBuf>, String> {
+	let paths = files::resolve(root, glob)?;
+
+	if paths.is_empty() {
+		Err(format!("No file found for glob {glob}"))
+	} else {
+		Ok(paths)
+	}
+}
+
+fn insert(
+	entries: &mut BTreeMap<String, Vec<u8>>,
+	name: &str,
+	key: String,
+	value: Vec<u8>,
+) -> Result<(), String> {
+	if entries.contains_key(&key) {
+		return Err(format!("Key {key} appears more than once in {name}"));
+	}
+
+	entries.insert(key, value);
+
+	Ok(())
+}
+
+/// Reads the `KEY=VALUE` lines of an env file, skipping blank lines and comments
+fn parse_env(path: &Path, contents: &str) -> Result<Vec<(String, String)>, String> {
+	contents
+		.lines()
+		.enumerate()
+		.map(|(number, line)| (number + 1, line.trim()))
+		.filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+		.map(|(number, line)| match line.split_once('=') {
+			Some((key, value)) if !key.trim().is_empty() => {
+				Ok((key.trim().to_string(), value.to_string()))
+			}
+			_ => Err(format!(
+				"Invalid line {number} in {}, expected KEY=VALUE",
+				path.display()
+			)),
+		})
+		.collect()
+}
+
+/// Hash of the object before the rename, which changes whenever its contents change
+fn hash(object: &Map<String, Value>) -> String {
+	let contents = serde_json::to_string(object).unwrap();
+	let digest = format!("{:x}", Sha256::digest(contents.as_bytes()));
+
+	digest[..HASH_SIZE].to_string()
+}
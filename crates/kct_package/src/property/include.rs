@@ -25,7 +25,10 @@ impl Callback for Handler {
 		let root = self.context.vendor().join(package);
 		let package = Package::try_from(root.as_path()).map_err(|err| err.to_string())?;
 
-		let prop = params.get("input").cloned().map(|v| (&Input(v)).into());
+		let prop = params
+			.get("input")
+			.cloned()
+			.map(|v| (&Input(package.with_defaults(v))).into());
 		let compiler = Compiler::new(&self.context)
 			.with_static_prop(prop)
 			.with_target((&package).into());
@@ -1,5 +1,8 @@
 mod files;
+mod generate;
 mod include;
 
 pub use self::files::Files;
+pub(crate) use self::generate::generated;
+pub use self::generate::Generate;
 pub use self::include::Include;
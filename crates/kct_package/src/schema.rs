@@ -1,26 +1,64 @@
+use crate::document;
 use crate::error::Error;
 
+use std::collections::HashMap;
 use std::convert::TryFrom;
-use std::path::PathBuf;
-use std::rc::Rc;
+use std::error::Error as StdError;
+use std::fmt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
 
 use anyhow::Result;
-use kct_compiler::Validator;
-use kct_helper::io;
-use serde_json::Value;
-use url::Url;
-use valico::json_schema::Scope;
+use jsonschema::error::ValidationErrorKind;
+use jsonschema::{
+	Draft, Retrieve, Uri, ValidationError, ValidationOptions, Validator as JsonValidator,
+};
+use kct_compiler::{Validator, Violation, Violations};
+use kct_helper::json::Strategy;
+use serde_json::{json, Value};
 
-#[derive(Debug)]
+/// Keyword marking inputs that shouldn't be shown, along with the standard `writeOnly`
+const SENSITIVE: &str = "sensitive";
+
+/// Keywords declaring how inputs layer arrays, either replace, append, or merge by a key
+const MERGE_STRATEGY: &str = "mergeStrategy";
+const MERGE_KEY: &str = "mergeKey";
+const DEFAULT_MERGE_KEY: &str = "name";
+
+/// Value of `$schema` meaning the latest draft, common in schemas written for older validators
+/// which only went up to draft 7
+const LATEST_DRAFT: &str = "http://json-schema.org/schema";
+
+/// Draft of schemas without `$schema`, as the newer ones change what existing keywords mean
+const DEFAULT_DRAFT: &str = "http://json-schema.org/draft-07/schema";
+
+/// Drafts supported along with the URIs that declare them, starting with their meta-schemas
+const DRAFTS: [(Draft, &[&str]); 5] = [
+	(
+		Draft::Draft202012,
+		&["https://json-schema.org/draft/2020-12/schema"],
+	),
+	(
+		Draft::Draft201909,
+		&["https://json-schema.org/draft/2019-09/schema"],
+	),
+	(Draft::Draft7, &[DEFAULT_DRAFT, LATEST_DRAFT]),
+	(Draft::Draft6, &["http://json-schema.org/draft-06/schema"]),
+	(Draft::Draft4, &["http://json-schema.org/draft-04/schema"]),
+];
+
+/// Compiled schema of the package, where clones share the compiled validator
+#[derive(Clone)]
 pub struct Schema {
-	schema: Rc<Value>,
-	scope: Scope,
-	id: Url,
+	documents: Arc<Documents>,
+	validator: Arc<JsonValidator>,
 }
 
-impl Clone for Schema {
-	fn clone(&self) -> Self {
-		Schema::try_from(self.schema.as_ref()).unwrap()
+impl fmt::Debug for Schema {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Schema")
+			.field("schema", self.documents.root().schema)
+			.finish_non_exhaustive()
 	}
 }
 
@@ -28,13 +66,7 @@ impl TryFrom<&Value> for Schema {
 	type Error = Error;
 
 	fn try_from(schema: &Value) -> Result<Self, Error> {
-		let mut scope = Scope::new();
-		let id = scope
-			.compile(schema.clone(), false)
-			.map_err(|_err| Error::InvalidSchema)?;
-		let schema = Rc::new(schema.to_owned());
-
-		Ok(Schema { schema, scope, id })
+		Self::compile(schema, None)
 	}
 }
 
@@ -42,35 +74,617 @@ impl TryFrom<PathBuf> for Schema {
 	type Error = Error;
 
 	fn try_from(path: PathBuf) -> Result<Self, Error> {
-		match io::from_file(&path) {
-			Ok(contents) => {
-				let schema: Value =
-					serde_json::from_str(&contents).map_err(|_err| Error::InvalidSchema)?;
+		let root = path.parent().unwrap_or(&path);
+		let schema = document::read(root, &path).map_err(Error::InvalidSchema)?;
+
+		Self::compile(&schema, Some(&path))
+	}
+}
+
+impl Schema {
+	/// Compiles the schema for the draft in its `$schema`, or draft 7 when missing, with relative
+	/// `$ref`s read from the files next to it
+	fn compile(schema: &Value, path: Option<&Path>) -> Result<Self, Error> {
+		let (draft, uri) = draft(schema)?;
+		let files = Files {
+			root: path.and_then(Path::parent).map(Path::to_path_buf),
+		};
+
+		// the validator looks the meta-schema up by the URI, which only exists for the drafts
+		let mut compiled = schema.clone();
+		if let Some(object) = compiled.as_object_mut() {
+			object.insert(String::from("$schema"), Value::from(uri));
+		}
+
+		let validator = jsonschema::options()
+			.with_draft(draft)
+			.with_retriever(files.clone())
+			.should_validate_formats(true)
+			.build(&compiled)
+			.map_err(|err| Error::InvalidSchema(err.to_string()))?;
+
+		// the root document is addressed by its file name, so branches can be checked on their own
+		let root = path
+			.and_then(Path::file_name)
+			.map(PathBuf::from)
+			.unwrap_or_else(|| PathBuf::from("schema.json"));
+		let options = jsonschema::options()
+			.with_draft(draft)
+			.with_retriever(files.clone())
+			.with_resource(address(&root, ""), draft.create_resource(compiled))
+			.should_validate_formats(true);
+		let mut documents = Documents {
+			root: root.clone(),
+			files: HashMap::from([(root.clone(), schema.to_owned())]),
+			branches: HashMap::new(),
+		};
+		documents.load(&files, &root, schema);
+		documents.files.values().try_for_each(check_strategies)?;
+		documents.compile(&options);
+
+		Ok(Schema {
+			documents: Arc::new(documents),
+			validator: Arc::new(validator),
+		})
+	}
+
+	/// Text values of the input at properties marked as sensitive
+	pub fn sensitive(&self, input: &Value) -> Vec<String> {
+		let mut found = vec![];
+		collect(&self.documents, self.documents.root(), input, &mut found);
+
+		found
+	}
+
+	/// Types declared for the input at the path, where array indices are given as numbers
+	pub fn types(&self, path: &[String]) -> Vec<String> {
+		let mut found: Vec<String> = at(&self.documents, self.documents.root(), path)
+			.into_iter()
+			.flat_map(|node| match node.schema.get("type") {
+				Some(Value::String(kind)) => vec![kind.clone()],
+				Some(Value::Array(kinds)) => kinds
+					.iter()
+					.filter_map(Value::as_str)
+					.map(String::from)
+					.collect(),
+				_ => vec![],
+			})
+			.collect();
+		found.sort();
+		found.dedup();
+
+		found
+	}
+
+	/// Fills the properties missing from the input with the `default` of their schemas
+	pub fn defaults(&self, input: &mut Value) {
+		fill(&self.documents, self.documents.root(), input);
+	}
+
+	/// Names of the properties declared for the input at the path
+	pub fn properties(&self, path: &[String]) -> Vec<String> {
+		at(&self.documents, self.documents.root(), path)
+			.into_iter()
+			.filter_map(|node| node.schema.get("properties").and_then(Value::as_object))
+			.flat_map(|properties| properties.keys().cloned())
+			.collect()
+	}
+
+	/// How inputs layer the array at the path, from the `mergeStrategy` keyword and the
+	/// `mergeKey` used by the merge strategy
+	pub fn strategy(&self, path: &[String]) -> Option<Strategy> {
+		at(&self.documents, self.documents.root(), path)
+			.into_iter()
+			.find_map(|node| strategy(node.schema).ok().flatten())
+	}
+}
+
+/// Documents the schema is made of, the root one and the files its `$ref`s point to, keyed by
+/// their path within the package, along with the validators of their `anyOf` and `oneOf`
+/// branches keyed by address
+struct Documents {
+	root: PathBuf,
+	files: HashMap<PathBuf, Value>,
+	branches: HashMap<String, JsonValidator>,
+}
+
+impl Documents {
+	fn root(&self) -> Node<'_> {
+		Node {
+			document: &self.root,
+			pointer: String::new(),
+			schema: &self.files[&self.root],
+		}
+	}
+
+	/// Whether the input is valid against the subschema, which is how the branches of `anyOf`
+	/// and `oneOf` applying to it are told apart
+	fn admits(&self, node: &Node, input: &Value) -> bool {
+		self.branches
+			.get(&address(node.document, &node.pointer))
+			.is_some_and(|validator| validator.is_valid(input))
+	}
+
+	/// Compiles the branches of every document once, as they're checked for each value of the
+	/// input, where the ones that don't compile admit nothing
+	fn compile(&mut self, options: &ValidationOptions) {
+		let mut addresses = vec![];
+		for (document, file) in &self.files {
+			let node = Node {
+				document,
+				pointer: String::new(),
+				schema: file,
+			};
+
+			combined(&node, &mut addresses);
+		}
+
+		for address in addresses {
+			if let Ok(validator) = options.build(&json!({ "$ref": address })) {
+				self.branches.insert(address, validator);
+			}
+		}
+	}
+
+	/// Reads the files referenced from the value, and the ones referenced from those in turn
+	fn load(&mut self, files: &Files, document: &Path, value: &Value) {
+		match value {
+			Value::Object(map) => {
+				let reference = map
+					.get("$ref")
+					.and_then(Value::as_str)
+					.and_then(|reference| target(document, reference))
+					.filter(|(path, _pointer)| !self.files.contains_key(path));
+
+				// references the validator resolved some other way, like through an `$id`, are
+				// left to it
+				if let Some((path, _pointer)) = reference {
+					if let Ok(file) = files.read(&path) {
+						self.files.insert(path.clone(), file.clone());
+						self.load(files, &path, &file);
+					}
+				}
+
+				map.values()
+					.for_each(|value| self.load(files, document, value));
+			}
+			Value::Array(items) => items
+				.iter()
+				.for_each(|value| self.load(files, document, value)),
+			_ => (),
+		}
+	}
+
+	/// Subschema the `$ref` of the node points to, in its document or another one
+	fn resolve<'a>(&'a self, node: &Node<'a>, reference: &str) -> Option<Node<'a>> {
+		let (path, pointer) = target(node.document, reference)?;
+		let (document, file) = self.files.get_key_value(&path)?;
+
+		Some(Node {
+			document,
+			pointer: pointer.to_string(),
+			schema: file.pointer(pointer)?,
+		})
+	}
+}
+
+/// Subschema along with the document it's in, which its `$ref`s are relative to, and its
+/// pointer within it
+#[derive(Clone)]
+struct Node<'a> {
+	document: &'a Path,
+	pointer: String,
+	schema: &'a Value,
+}
+
+impl<'a> Node<'a> {
+	/// Subschema under the keys, like `["properties", "name"]` or `["anyOf", "0"]`
+	fn get(&self, keys: &[&str]) -> Option<Node<'a>> {
+		let suffix: String = keys
+			.iter()
+			.map(|key| format!("/{}", key.replace('~', "~0").replace('/', "~1")))
+			.collect();
+		let schema = self.schema.pointer(&suffix).filter(|s| s.is_object())?;
+
+		Some(Node {
+			document: self.document,
+			pointer: format!("{}{suffix}", self.pointer),
+			schema,
+		})
+	}
+}
+
+/// Addresses of the `anyOf` and `oneOf` branches under the node, at any depth
+fn combined(node: &Node, found: &mut Vec<String>) {
+	let children: Vec<(String, &Value)> = match node.schema {
+		Value::Object(map) => map
+			.iter()
+			.map(|(key, value)| (key.clone(), value))
+			.collect(),
+		Value::Array(items) => items
+			.iter()
+			.enumerate()
+			.map(|(index, value)| (index.to_string(), value))
+			.collect(),
+		_ => return,
+	};
+
+	for (key, value) in children {
+		let child = Node {
+			document: node.document,
+			pointer: format!(
+				"{}/{}",
+				node.pointer,
+				key.replace('~', "~0").replace('/', "~1")
+			),
+			schema: value,
+		};
+
+		let parent = node.pointer.rsplit('/').next();
+		if node.schema.is_array() && value.is_object() && matches!(parent, Some("anyOf" | "oneOf"))
+		{
+			found.push(address(child.document, &child.pointer));
+		}
+
+		combined(&child, found);
+	}
+}
+
+/// URI the validator knows the subschema by, escaping what can't be in a URI
+fn address(document: &Path, pointer: &str) -> String {
+	let escape = |text: &str| -> String {
+		text.bytes()
+			.map(|byte| match byte {
+				b'A'..=b'Z'
+				| b'a'..=b'z'
+				| b'0'..=b'9'
+				| b'-'
+				| b'.'
+				| b'_'
+				| b'~'
+				| b'/'
+				| b'$' => char::from(byte).to_string(),
+				_ => format!("%{byte:02X}"),
+			})
+			.collect()
+	};
+
+	format!(
+		"json-schema:///{}#{}",
+		escape(&document.to_string_lossy()),
+		escape(pointer)
+	)
+}
+
+/// Path of the document a `$ref` points to, relative to the one holding it, along with the
+/// pointer within it, or nothing when it isn't a file of the package
+fn target<'r>(document: &Path, reference: &'r str) -> Option<(PathBuf, &'r str)> {
+	let (file, pointer) = reference.split_once('#').unwrap_or((reference, ""));
+	if file.is_empty() {
+		return Some((document.to_path_buf(), pointer));
+	}
+
+	if file.contains(':') {
+		return None;
+	}
+
+	let joined = document.parent().unwrap_or(Path::new("")).join(file);
+	let mut path = PathBuf::new();
+	for component in joined.components() {
+		match component {
+			Component::Normal(part) => path.push(part),
+			Component::ParentDir if path.pop() => (),
+			Component::RootDir => path = PathBuf::new(),
+			Component::CurDir => (),
+			_ => return None,
+		}
+	}
+
+	Some((path, pointer))
+}
+
+fn strategy(schema: &Value) -> Result<Option<Strategy>, Error> {
+	let strategy = match schema.get(MERGE_STRATEGY) {
+		None => return Ok(None),
+		Some(Value::String(strategy)) if strategy == "merge" => {
+			let key = schema.get(MERGE_KEY).and_then(Value::as_str);
+
+			Strategy::MergeBy(key.unwrap_or(DEFAULT_MERGE_KEY).to_string())
+		}
+		Some(Value::String(strategy)) => strategy
+			.parse()
+			.map_err(|_err| Error::InvalidStrategy(strategy.clone()))?,
+		Some(other) => return Err(Error::InvalidStrategy(other.to_string())),
+	};
+
+	Ok(Some(strategy))
+}
+
+/// Fails on the first strategy that can't be used, so mistakes don't go unnoticed while layering
+fn check_strategies(schema: &Value) -> Result<(), Error> {
+	strategy(schema)?;
+
+	let maps = ["properties", "$defs", "definitions"]
+		.iter()
+		.filter_map(|keyword| schema.get(keyword).and_then(Value::as_object))
+		.flat_map(|map| map.values());
+	let lists = ["allOf", "anyOf", "oneOf"]
+		.iter()
+		.filter_map(|keyword| schema.get(keyword).and_then(Value::as_array))
+		.flatten();
+	let single = ["items", "additionalProperties"]
+		.iter()
+		.filter_map(|keyword| schema.get(keyword));
+
+	maps.chain(lists)
+		.chain(single)
+		.filter(|subschema| subschema.is_object())
+		.try_for_each(check_strategies)
+}
+
+/// Subschemas applying to the same value, from the combinators and the `$ref` of the schema,
+/// which may be in another file
+///
+/// With an input, only the branches of `anyOf` and `oneOf` it's valid against are kept.
+fn branches<'a>(documents: &'a Documents, node: &Node<'a>, input: Option<&Value>) -> Vec<Node<'a>> {
+	let mut found = vec![];
+
+	for keyword in ["allOf", "anyOf", "oneOf"] {
+		let count = node
+			.schema
+			.get(keyword)
+			.and_then(Value::as_array)
+			.map_or(0, Vec::len);
+		let combined = (0..count)
+			.filter_map(|index| node.get(&[keyword, &index.to_string()]))
+			.filter(|branch| match (keyword, input) {
+				("allOf", _) | (_, None) => true,
+				(_, Some(input)) => documents.admits(branch, input),
+			});
+
+		found.extend(combined);
+	}
+
+	let referenced = node
+		.schema
+		.get("$ref")
+		.and_then(Value::as_str)
+		.and_then(|reference| documents.resolve(node, reference));
+	found.extend(referenced);
+
+	found
+}
 
-				let schema = Self::try_from(&schema)?;
+/// Subschemas describing the input at the path
+fn at<'a>(documents: &'a Documents, node: Node<'a>, path: &[String]) -> Vec<Node<'a>> {
+	let mut found = vec![];
 
-				Ok(schema)
+	for subschema in branches(documents, &node, None) {
+		found.extend(at(documents, subschema, path));
+	}
+
+	let (segment, rest) = match path.split_first() {
+		Some(split) => split,
+		None => {
+			found.insert(0, node);
+
+			return found;
+		}
+	};
+
+	let subschema = node
+		.get(&["properties", segment])
+		.or_else(|| {
+			segment
+				.parse::<usize>()
+				.ok()
+				.and_then(|_index| node.get(&["items"]))
+		})
+		.or_else(|| node.get(&["additionalProperties"]));
+
+	if let Some(subschema) = subschema {
+		found.extend(at(documents, subschema, rest));
+	}
+
+	found
+}
+
+fn fill(documents: &Documents, node: Node, input: &mut Value) {
+	for subschema in branches(documents, &node, Some(input)) {
+		fill(documents, subschema, input);
+	}
+
+	match input {
+		Value::Object(map) => {
+			let properties = node.schema.get("properties").and_then(Value::as_object);
+			let keys = properties
+				.into_iter()
+				.flat_map(|properties| properties.keys());
+			for (key, subschema) in
+				keys.filter_map(|key| Some((key, node.get(&["properties", key])?)))
+			{
+				let default = subschema.schema.get("default").cloned().or_else(|| {
+					branches(documents, &subschema, None)
+						.into_iter()
+						.find_map(|branch| branch.schema.get("default").cloned())
+				});
+				if let (false, Some(default)) = (map.contains_key(key), default) {
+					map.insert(key.clone(), default);
+				}
+
+				if let Some(value) = map.get_mut(key) {
+					fill(documents, subschema, value);
+				}
+			}
+		}
+		Value::Array(items) => {
+			if let Some(subschema) = node.get(&["items"]) {
+				items
+					.iter_mut()
+					.for_each(|item| fill(documents, subschema.clone(), item));
 			}
-			_ => Err(Error::InvalidSchema),
 		}
+		_ => (),
+	}
+}
+
+fn collect(documents: &Documents, node: Node, input: &Value, found: &mut Vec<String>) {
+	let marked = |keyword: &str| {
+		node.schema
+			.get(keyword)
+			.and_then(Value::as_bool)
+			.unwrap_or(false)
+	};
+
+	if marked("writeOnly") || marked(SENSITIVE) {
+		return texts(input, found);
+	}
+
+	for subschema in branches(documents, &node, None) {
+		collect(documents, subschema, input, found);
+	}
+
+	match input {
+		Value::Object(map) => {
+			for (key, value) in map {
+				let subschema = node
+					.get(&["properties", key])
+					.or_else(|| node.get(&["additionalProperties"]));
+
+				if let Some(subschema) = subschema {
+					collect(documents, subschema, value, found);
+				}
+			}
+		}
+		Value::Array(items) => {
+			if let Some(subschema) = node.get(&["items"]) {
+				for item in items {
+					collect(documents, subschema.clone(), item, found);
+				}
+			}
+		}
+		_ => (),
+	}
+}
+
+/// Values under a sensitive property as they're written in text, so numbers and booleans are
+/// rendered too
+fn texts(input: &Value, found: &mut Vec<String>) {
+	match input {
+		Value::String(text) => found.push(text.clone()),
+		Value::Number(number) => found.push(number.to_string()),
+		Value::Bool(boolean) => found.push(boolean.to_string()),
+		Value::Array(items) => items.iter().for_each(|item| texts(item, found)),
+		Value::Object(map) => map.values().for_each(|item| texts(item, found)),
+		_ => (),
 	}
 }
 
 impl From<Schema> for Validator {
 	fn from(schema: Schema) -> Self {
-		let predicate = move |input: &Value| -> Result<(), String> {
+		let predicate = move |input: &Value| -> Result<(), Violations> {
 			if !input.is_object() {
-				return Err("input is not an object".to_string());
+				return Err(Violations(vec![Violation {
+					pointer: String::new(),
+					keyword: String::from("type"),
+					message: String::from("The input must be an object"),
+				}]));
 			}
 
-			let schema = schema.scope.resolve(&schema.id).unwrap();
-			if schema.validate(input).is_strictly_valid() {
+			let violations: Vec<Violation> =
+				schema.validator.iter_errors(input).map(violation).collect();
+
+			if violations.is_empty() {
 				Ok(())
 			} else {
-				Err("input doesn't match your schema".to_string())
+				Err(Violations(violations))
 			}
 		};
 
 		Validator::new(Box::new(predicate))
 	}
 }
+
+/// Describes the error with the keyword that failed, masking the value in the message as it
+/// might be sensitive
+fn violation(err: ValidationError) -> Violation {
+	let keyword = err
+		.schema_path
+		.as_str()
+		.rsplit('/')
+		.next()
+		.unwrap_or_default()
+		.to_string();
+
+	// missing properties are pointed at directly instead of at the object lacking them
+	let mut pointer = err.instance_path.to_string();
+	if let ValidationErrorKind::Required {
+		property: Value::String(property),
+	} = &err.kind
+	{
+		let escaped = property.replace('~', "~0").replace('/', "~1");
+		pointer = format!("{pointer}/{escaped}");
+	}
+
+	Violation {
+		pointer,
+		keyword,
+		message: err.masked().to_string(),
+	}
+}
+
+/// Draft of the schema from its `$schema`, along with the URI of its meta-schema
+fn draft(schema: &Value) -> Result<(Draft, &'static str), Error> {
+	let uri = match schema.get("$schema") {
+		None => DEFAULT_DRAFT,
+		Some(Value::String(uri)) => uri.trim_end_matches('#'),
+		Some(_) => {
+			return Err(Error::InvalidSchema(String::from(
+				"$schema should be a URI",
+			)))
+		}
+	};
+
+	DRAFTS
+		.iter()
+		.find(|(_draft, uris)| uris.contains(&uri))
+		.map(|(draft, uris)| (*draft, uris[0]))
+		.ok_or_else(|| Error::InvalidSchema(format!("the draft {uri} isn't supported")))
+}
+
+/// Retrieves the schemas referenced by relative `$ref`s from the files of the package, in any of
+/// the formats its documents can be written in, refusing anything outside of it
+#[derive(Clone)]
+struct Files {
+	root: Option<PathBuf>,
+}
+
+impl Files {
+	/// Reads the document at the path within the package
+	fn read(&self, path: &Path) -> Result<Value, Box<dyn StdError + Send + Sync>> {
+		let root = match &self.root {
+			Some(root) => root.canonicalize()?,
+			None => {
+				return Err(format!("there's no package to read {} from", path.display()).into())
+			}
+		};
+
+		let file = root.join(path).canonicalize()?;
+		if !file.starts_with(&root) {
+			return Err(format!("{} is outside of the package", file.display()).into());
+		}
+
+		Ok(document::read(&root, &file)?)
+	}
+}
+
+impl Retrieve for Files {
+	fn retrieve(&self, uri: &Uri<String>) -> Result<Value, Box<dyn StdError + Send + Sync>> {
+		match (&self.root, uri.scheme().as_str()) {
+			(Some(_root), "json-schema") => {
+				self.read(Path::new(uri.path().as_str().trim_start_matches('/')))
+			}
+			_ => Err(format!("only files within the package can be referenced, not {uri}").into()),
+		}
+	}
+}
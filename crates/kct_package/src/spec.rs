@@ -1,10 +1,10 @@
+use crate::document;
 use crate::error::Error;
 
 use std::convert::TryFrom;
 use std::path::PathBuf;
 
 use anyhow::Result;
-use kct_helper::io;
 use semver::Version;
 use serde_json::Value;
 
@@ -12,31 +12,53 @@ use serde_json::Value;
 pub struct Spec {
 	pub name: String,
 	pub version: Version,
+	pub transformers: Option<Value>,
+	pub generators: Vec<Value>,
 }
 
 impl TryFrom<PathBuf> for Spec {
 	type Error = Error;
 
 	fn try_from(path: PathBuf) -> Result<Spec, Error> {
-		match io::from_file(&path) {
-			Ok(contents) => {
-				let json: Value =
-					serde_json::from_str(&contents).map_err(|_err| Error::InvalidSpec)?;
-				let name = json
-					.get("name")
-					.and_then(|v| v.as_str())
-					.map(|v| Ok(String::from(v)))
-					.unwrap_or(Err(Error::InvalidSpec))?;
-
-				let version = json
-					.get("version")
-					.and_then(|v| v.as_str())
-					.map(|v| Version::parse(v).map_err(|_err| Error::InvalidSpec))
-					.unwrap_or(Err(Error::InvalidSpec))?;
-
-				Ok(Spec { name, version })
+		let root = path.parent().unwrap_or(&path);
+		let json = document::read(root, &path).map_err(Error::InvalidSpec)?;
+
+		let name = json
+			.get("name")
+			.and_then(|v| v.as_str())
+			.map(|v| Ok(String::from(v)))
+			.unwrap_or_else(|| Err(Error::InvalidSpec(String::from("name should be a string"))))?;
+
+		let version = json
+			.get("version")
+			.and_then(|v| v.as_str())
+			.map(|v| {
+				Version::parse(v)
+					.map_err(|err| Error::InvalidSpec(format!("version {v} isn't semver, {err}")))
+			})
+			.unwrap_or_else(|| {
+				Err(Error::InvalidSpec(String::from(
+					"version should be a string",
+				)))
+			})?;
+
+		let transformers = json.get("transformers").cloned();
+
+		let generators = match json.get("generators") {
+			Some(Value::Array(generators)) => generators.clone(),
+			Some(_) => {
+				return Err(Error::InvalidSpec(String::from(
+					"generators should be a list",
+				)))
 			}
-			_ => Err(Error::InvalidSpec),
-		}
+			None => vec![],
+		};
+
+		Ok(Spec {
+			name,
+			version,
+			transformers,
+			generators,
+		})
 	}
 }
@@ -2,7 +2,8 @@ use std::convert::TryFrom;
 use std::panic::panic_any;
 
 use assert_matches::assert_matches;
-use kct_compiler::Release;
+use kct_compiler::{Error as CompilerError, Release};
+use kct_helper::json::Strategy;
 use kct_package::{Error, Package};
 use kct_testing::dir::TempDir;
 use kct_testing::{self as testing, Fixture};
@@ -24,6 +25,13 @@ fn compile_with_example(pkg: Package, rel: Option<Release>) -> Result<Value, Err
 mod try_from {
 	use super::*;
 
+	#[test]
+	fn can_be_shared_between_threads() {
+		fn shared<T: Send + Sync>() {}
+
+		shared::<Package>();
+	}
+
 	#[test]
 	fn can_be_created() {
 		let (package, _dir) = package(vec![], vec![]);
@@ -52,12 +60,309 @@ mod try_from {
 		assert_matches!(package.unwrap_err(), Error::NoSchema)
 	}
 
+	#[test]
+	fn reads_yaml_documents() {
+		let (package, _dir) = package(
+			vec![
+				("kcp.yaml", "name: fixture\nversion: 1.0.0\n"),
+				(
+					"schema.yml",
+					"type: object\nproperties:\n  name:\n    type: string\n",
+				),
+				("example.yaml", "name: app\n"),
+			],
+			vec!["kcp.json", "schema.json", "example.json"],
+		);
+		let package = package.unwrap();
+
+		assert_eq!(package.spec.version.to_string(), "1.0.0");
+		assert_eq!(package.example, Some(json!({"name": "app"})));
+		assert_eq!(package.input_types(&[String::from("name")]), vec!["string"]);
+	}
+
+	#[test]
+	fn evaluates_jsonnet_documents_with_lib() {
+		let (package, _dir) = package(
+			vec![
+				(
+					"lib/fragments.libsonnet",
+					"{ image: { type: 'object', properties: { tag: { type: 'string' } } } }",
+				),
+				(
+					"schema.jsonnet",
+					"local fragments = import 'fragments.libsonnet'; { type: 'object', properties: { image: fragments.image } }",
+				),
+				("example.jsonnet", "{ image: { tag: std.join('.', ['1', '0']) } }"),
+			],
+			vec!["schema.json", "example.json"],
+		);
+		let package = package.unwrap();
+
+		assert_eq!(package.example, Some(json!({"image": {"tag": "1.0"}})));
+		assert_eq!(
+			package.input_types(&[String::from("image"), String::from("tag")]),
+			vec!["string"]
+		);
+	}
+
+	#[test]
+	fn explains_invalid_documents() {
+		let spec = r#"{"name": "kcp", "version": "one"}"#;
+		let (spec, _dir) = package(vec![("kcp.json", spec)], vec![]);
+		assert_matches!(
+			spec.unwrap_err(),
+			Error::InvalidSpec(reason) if reason.contains("semver")
+		);
+
+		let (package, _dir) = package(
+			vec![("example.jsonnet", "{ image: std.missing('1.0') }")],
+			vec!["example.json"],
+		);
+		assert_matches!(
+			package.unwrap_err(),
+			Error::InvalidExample(reason) if reason.contains("missing")
+		);
+	}
+
+	#[test]
+	fn rejects_documents_in_many_formats() {
+		let (package, _dir) = package(vec![("example.yaml", "name: app\n")], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::Ambiguous(name) if name == "example");
+	}
+
+	#[test]
+	fn reads_presets() {
+		let (package, _dir) = package(
+			vec![
+				("presets/small.json", r#"{ "replicas": 1 }"#),
+				("presets/ha.yaml", "replicas: 3\n"),
+				("presets/README.md", "# Presets"),
+			],
+			vec![],
+		);
+		let package = package.unwrap();
+
+		let names: Vec<&String> = package.presets.keys().collect();
+		assert_eq!(names, vec!["ha", "small"]);
+		assert_eq!(package.presets["ha"], json!({"replicas": 3}));
+	}
+
+	#[test]
+	fn explains_invalid_presets() {
+		let (package, _dir) = package(vec![("presets/ha.json", "{ replicas: 3 }")], vec![]);
+
+		assert_matches!(
+			package.unwrap_err(),
+			Error::InvalidPreset(name, reason) if name == "ha" && reason.contains("line 1")
+		);
+	}
+
+	#[test]
+	fn validates_presets_against_schema() {
+		let (package, _dir) = package(
+			vec![("presets/broken.json", r#"{ "database": null }"#)],
+			vec![],
+		);
+		let package = package.unwrap();
+
+		let example = package.example.as_ref().unwrap();
+		assert!(package.validate(example).is_ok());
+		assert!(package.validate(&package.presets["broken"]).is_err());
+	}
+
+	#[test]
+	fn request_schema_for_presets() {
+		let (package, _dir) = package(
+			vec![("presets/small.json", "{}")],
+			vec!["schema.json", "example.json"],
+		);
+
+		assert_matches!(package.unwrap_err(), Error::NoSchema)
+	}
+
 	#[test]
 	fn needs_a_main_file() {
 		let (package, _dir) = package(vec![], vec!["templates/main.jsonnet"]);
 
 		assert_matches!(package.unwrap_err(), Error::NoMain);
 	}
+
+	#[test]
+	fn reads_transformers_from_spec() {
+		let spec = r#"{"name": "kcp", "version": "0.1.0", "transformers": {"namespace": "prod"}}"#;
+		let (package, _dir) = package(vec![("kcp.json", spec)], vec![]);
+
+		let transformers = package.unwrap().spec.transformers;
+
+		assert_eq!(transformers, Some(json!({"namespace": "prod"})));
+	}
+
+	#[test]
+	fn finds_sensitive_inputs() {
+		let schema = r#"{
+			"type": "object",
+			"properties": {
+				"host": {"type": "string"},
+				"credentials": {
+					"type": "object",
+					"properties": {"pass": {"type": "string", "writeOnly": true}}
+				},
+				"tokens": {"type": "array", "items": {"type": "string", "sensitive": true}},
+				"pin": {"type": "integer", "sensitive": true},
+				"debug": {"type": "boolean", "writeOnly": true}
+			}
+		}"#;
+		let (package, _dir) = package(vec![("schema.json", schema)], vec![]);
+		let input = json!({
+			"host": "db",
+			"credentials": {"pass": "hunter22"},
+			"tokens": ["abc", "def"],
+			"pin": 8044,
+			"debug": true
+		});
+
+		let mut sensitive = package.unwrap().sensitive(Some(&input));
+		sensitive.sort();
+
+		assert_eq!(sensitive, vec!["8044", "abc", "def", "hunter22", "true"]);
+	}
+
+	#[test]
+	fn finds_input_types() {
+		let schema = r#"{
+			"type": "object",
+			"properties": {
+				"tag": {"type": "string"},
+				"ports": {
+					"type": "array",
+					"items": {"type": "object", "properties": {"port": {"type": ["integer", "string"]}}}
+				},
+				"labels": {"type": "object", "additionalProperties": {"type": "string"}},
+				"replicas": {"anyOf": [{"type": "integer"}, {"type": "null"}]}
+			}
+		}"#;
+		let (package, _dir) = package(vec![("schema.json", schema)], vec![]);
+		let package = package.unwrap();
+		let types = |path: &[&str]| {
+			let path: Vec<String> = path.iter().map(|s| s.to_string()).collect();
+
+			package.input_types(&path)
+		};
+
+		assert_eq!(types(&["tag"]), vec!["string"]);
+		assert_eq!(types(&["ports", "0", "port"]), vec!["integer", "string"]);
+		assert_eq!(types(&["labels", "app"]), vec!["string"]);
+		assert_eq!(types(&["replicas"]), vec!["integer", "null"]);
+		assert!(types(&["unknown"]).is_empty());
+	}
+
+	#[test]
+	fn finds_input_properties() {
+		let schema = r#"{
+			"type": "object",
+			"properties": {
+				"image": {"type": "object", "properties": {"tag": {}, "pullPolicy": {}}},
+				"ports": {"type": "array", "items": {"properties": {"port": {}}}}
+			},
+			"allOf": [{"properties": {"replicas": {}}}]
+		}"#;
+		let (package, _dir) = package(vec![("schema.json", schema)], vec![]);
+		let package = package.unwrap();
+		let properties = |path: &[&str]| {
+			let path: Vec<String> = path.iter().map(|s| s.to_string()).collect();
+			let mut found = package.input_properties(&path);
+			found.sort();
+
+			found
+		};
+
+		assert_eq!(properties(&[]), vec!["image", "ports", "replicas"]);
+		assert_eq!(properties(&["image"]), vec!["pullPolicy", "tag"]);
+		assert_eq!(properties(&["ports", "0"]), vec!["port"]);
+		assert!(properties(&["image", "tag"]).is_empty());
+	}
+
+	#[test]
+	fn finds_input_strategies() {
+		let schema = r#"{
+			"type": "object",
+			"properties": {
+				"tags": {"type": "array", "mergeStrategy": "append"},
+				"ports": {"type": "array", "mergeStrategy": "merge", "mergeKey": "port"},
+				"env": {"type": "array", "mergeStrategy": "merge"},
+				"hosts": {"type": "array"}
+			}
+		}"#;
+		let (package, _dir) = package(vec![("schema.json", schema)], vec![]);
+		let package = package.unwrap();
+		let strategy = |key: &str| package.input_strategy(&[key.to_string()]);
+
+		assert_eq!(strategy("tags"), Some(Strategy::Append));
+		assert_eq!(
+			strategy("ports"),
+			Some(Strategy::MergeBy(String::from("port")))
+		);
+		assert_eq!(
+			strategy("env"),
+			Some(Strategy::MergeBy(String::from("name")))
+		);
+		assert_eq!(strategy("hosts"), None);
+	}
+
+	#[test]
+	fn follows_references_to_other_files() {
+		let schema = r#"{"type": "object", "properties": {"db": {"$ref": "schemas/db.json"}}}"#;
+		let db = r##"{
+			"type": "object",
+			"properties": {
+				"pass": {"type": "string", "writeOnly": true},
+				"port": {"type": "integer", "default": 5432},
+				"hosts": {"$ref": "hosts.json#/$defs/hosts"}
+			}
+		}"##;
+		let hosts = r#"{"$defs": {"hosts": {"type": "array", "mergeStrategy": "append"}}}"#;
+		let (package, _dir) = package(
+			vec![
+				("schema.json", schema),
+				("schemas/db.json", db),
+				("schemas/hosts.json", hosts),
+				("example.json", "{}"),
+			],
+			vec![],
+		);
+		let package = package.unwrap();
+		let path = |path: &[&str]| -> Vec<String> { path.iter().map(|s| s.to_string()).collect() };
+
+		let input = json!({"db": {"pass": "hunter22"}});
+		assert_eq!(package.sensitive(Some(&input)), vec!["hunter22"]);
+		assert_eq!(
+			package.with_defaults(input),
+			json!({"db": {"pass": "hunter22", "port": 5432}})
+		);
+		assert_eq!(package.input_types(&path(&["db", "port"])), vec!["integer"]);
+		assert_eq!(
+			package.input_strategy(&path(&["db", "hosts"])),
+			Some(Strategy::Append)
+		);
+	}
+
+	#[test]
+	fn rejects_unknown_strategies() {
+		let schema = r#"{"type": "object", "properties": {"tags": {"mergeStrategy": "prepend"}}}"#;
+		let (package, _dir) = package(vec![("schema.json", schema)], vec![]);
+
+		assert_matches!(package.unwrap_err(), Error::InvalidStrategy(_));
+	}
+
+	#[test]
+	fn finds_patches_dir() {
+		let (without, _dir) = package(vec![], vec![]);
+		assert_eq!(without.unwrap().patches, None);
+
+		let (with, dir) = package(vec![("patches/labels.yaml", "{}")], vec![]);
+		assert_eq!(with.unwrap().patches, Some(dir.path().join("patches")));
+	}
 }
 
 mod compile {
@@ -96,6 +401,147 @@ mod compile {
 			assert_eq!(rendered.unwrap(), input);
 		}
 
+		#[test]
+		fn fills_schema_defaults() {
+			let schema = r#"{
+				"type": "object",
+				"required": ["name"],
+				"properties": {
+					"name": { "type": "string" },
+					"replicas": { "type": "integer", "default": 1 },
+					"image": {
+						"type": "object",
+						"default": {},
+						"properties": { "tag": { "type": "string", "default": "latest" } }
+					},
+					"ports": {
+						"type": "array",
+						"items": {
+							"type": "object",
+							"properties": { "protocol": { "type": "string", "default": "TCP" } }
+						}
+					}
+				}
+			}"#;
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+					("schema.json", schema),
+					("example.json", r#"{ "name": "app" }"#),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let input = json!({"name": "app", "replicas": 3, "ports": [{"port": 80}]});
+			let rendered = package.compile(Some(input), None);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!({
+					"name": "app",
+					"replicas": 3,
+					"image": {"tag": "latest"},
+					"ports": [{"port": 80, "protocol": "TCP"}]
+				})
+			);
+		}
+
+		#[test]
+		fn fills_defaults_of_matching_branches() {
+			let schema = r#"{
+				"type": "object",
+				"properties": {
+					"storage": {
+						"oneOf": [
+							{
+								"required": ["kind"],
+								"properties": {
+									"kind": { "const": "s3" },
+									"bucket": { "type": "string", "default": "backups" }
+								}
+							},
+							{
+								"required": ["kind"],
+								"properties": {
+									"kind": { "const": "gcs" },
+									"project": { "type": "string", "default": "default" }
+								}
+							}
+						]
+					}
+				}
+			}"#;
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+					("schema.json", schema),
+					("example.json", r#"{ "storage": { "kind": "s3" } }"#),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = package.compile(Some(json!({"storage": {"kind": "gcs"}})), None);
+
+			assert_eq!(
+				rendered.unwrap(),
+				json!({"storage": {"kind": "gcs", "project": "default"}})
+			);
+		}
+
+		#[test]
+		fn fills_schema_defaults_without_input() {
+			let schema = r#"{
+				"type": "object",
+				"properties": { "replicas": { "type": "integer", "default": 1 } }
+			}"#;
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+					("schema.json", schema),
+					("example.json", "{}"),
+				],
+				vec![],
+			);
+
+			let rendered = package.unwrap().compile(None, None);
+
+			assert_eq!(rendered.unwrap(), json!({"replicas": 1}));
+		}
+
+		#[test]
+		fn fails_without_input_when_defaults_arent_enough() {
+			let schema = r#"{
+				"type": "object",
+				"required": ["name"],
+				"properties": {
+					"name": { "type": "string" },
+					"replicas": { "type": "integer", "default": 1 }
+				}
+			}"#;
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+					("schema.json", schema),
+					("example.json", r#"{ "name": "app" }"#),
+				],
+				vec![],
+			);
+
+			let rendered = package.unwrap().compile(None, None).unwrap_err();
+
+			let violations = match &rendered {
+				Error::MissingInput(violations) => &violations.0,
+				_ => panic!("It should be a missing input!"),
+			};
+			assert_eq!(violations.len(), 1);
+			assert_eq!(violations[0].pointer, "/name");
+			assert!(rendered
+				.to_string()
+				.starts_with("The package requires an input"));
+		}
+
 		#[test]
 		#[should_panic(expected = "input provided is invalid")]
 		fn validate_input() {
@@ -114,6 +560,194 @@ mod compile {
 				_ => panic!("It should be a validation issue!"),
 			}
 		}
+
+		#[test]
+		fn lists_every_violation() {
+			let schema = r#"{
+				"type": "object",
+				"required": ["name"],
+				"properties": {
+					"name": { "type": "string" },
+					"replicas": { "type": "integer", "maximum": 5 },
+					"policy": { "enum": ["Always", "Never"] }
+				}
+			}"#;
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+					("schema.json", schema),
+					("example.json", r#"{ "name": "app" }"#),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let input = json!({"replicas": 10, "policy": "Sometimes"});
+			let rendered = package.compile(Some(input), None).unwrap_err();
+
+			let violations = match rendered {
+				Error::Compilation(CompilerError::InvalidInput(violations)) => violations.0,
+				_ => panic!("It should be a validation issue!"),
+			};
+			let mut found: Vec<(&str, &str)> = violations
+				.iter()
+				.map(|v| (v.pointer.as_str(), v.keyword.as_str()))
+				.collect();
+			found.sort();
+
+			assert_eq!(
+				found,
+				vec![
+					("/name", "required"),
+					("/policy", "enum"),
+					("/replicas", "maximum")
+				]
+			);
+			assert!(violations
+				.iter()
+				.any(|v| v.message.ends_with("maximum of 5")));
+		}
+
+		#[test]
+		fn supports_modern_drafts() {
+			let schema = r##"{
+				"$schema": "https://json-schema.org/draft/2020-12/schema",
+				"type": "object",
+				"$defs": { "port": { "type": "integer", "maximum": 65535 } },
+				"properties": {
+					"port": { "$ref": "#/$defs/port" },
+					"tls": { "type": "boolean" },
+					"certificate": { "type": "string" },
+					"email": { "type": "string", "format": "email" }
+				},
+				"dependentRequired": { "tls": ["certificate"] },
+				"unevaluatedProperties": false
+			}"##;
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+					("schema.json", schema),
+					("example.json", r#"{ "port": 80 }"#),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let types = package.input_types(&[String::from("port")]);
+			assert_eq!(types, vec!["integer"]);
+
+			let input = json!({"port": 70000, "tls": true, "email": "nope", "extra": 1});
+			let rendered = package.compile(Some(input), None).unwrap_err();
+
+			let violations = match rendered {
+				Error::Compilation(CompilerError::InvalidInput(violations)) => violations.0,
+				_ => panic!("It should be a validation issue!"),
+			};
+			let mut keywords: Vec<&str> = violations.iter().map(|v| v.keyword.as_str()).collect();
+			keywords.sort();
+
+			assert_eq!(
+				keywords,
+				vec![
+					"dependentRequired",
+					"format",
+					"maximum",
+					"unevaluatedProperties"
+				]
+			);
+		}
+
+		#[test]
+		fn defaults_to_draft_7() {
+			let schema = r#"{
+				"type": "object",
+				"properties": { "tls": { "type": "boolean" } },
+				"dependentRequired": { "tls": ["certificate"] }
+			}"#;
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+					("schema.json", schema),
+					("example.json", "{}"),
+				],
+				vec![],
+			);
+
+			let rendered = package.unwrap().compile(Some(json!({"tls": true})), None);
+
+			assert!(rendered.is_ok());
+		}
+
+		#[test]
+		fn references_schemas_within_the_package() {
+			let schema = r#"{
+				"type": "object",
+				"properties": { "image": { "$ref": "schemas/image.json" } }
+			}"#;
+			let (package, _dir) = package(
+				vec![
+					("templates/main.jsonnet", "(import 'kct.libsonnet').input"),
+					("schema.json", schema),
+					(
+						"schemas/image.json",
+						r#"{ "type": "object", "required": ["tag"] }"#,
+					),
+					("example.json", r#"{ "image": { "tag": "1.0" } }"#),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let rendered = package.compile(Some(json!({"image": {}})), None);
+
+			assert_matches!(
+				rendered,
+				Err(Error::Compilation(CompilerError::InvalidInput(violations)))
+					if violations.0[0].pointer == "/image/tag"
+			);
+		}
+
+		#[test]
+		fn refuses_references_outside_the_package() {
+			let (package, _dir) = package(
+				vec![
+					("schema.json", r#"{ "$ref": "file:///etc/hostname" }"#),
+					("example.json", "{}"),
+				],
+				vec![],
+			);
+
+			assert_matches!(package, Err(Error::InvalidSchema(_)));
+		}
+
+		#[test]
+		fn evaluates_jsonnet_inputs() {
+			let (package, dir) = package(
+				vec![
+					("lib/cluster.libsonnet", "{ name: 'prod', replicas: 3 }"),
+					(
+						"values.jsonnet",
+						"local cluster = import 'cluster.libsonnet'; { replicas: cluster.replicas * 2 }",
+					),
+				],
+				vec![],
+			);
+			let package = package.unwrap();
+
+			let input = package.evaluate(&dir.path().join("values.jsonnet"));
+
+			assert_eq!(input.unwrap(), json!({"replicas": 6}));
+		}
+
+		#[test]
+		fn reports_jsonnet_input_errors() {
+			let (package, dir) = package(vec![("values.jsonnet", "{ a: error 'boom' }")], vec![]);
+			let package = package.unwrap();
+
+			let input = package.evaluate(&dir.path().join("values.jsonnet"));
+
+			assert_matches!(input, Err(Error::Evaluation(..)));
+		}
 	}
 
 	mod jsonnet {
@@ -358,6 +992,132 @@ mod compile {
 		}
 	}
 
+	mod generators {
+		use super::*;
+
+		fn generate(spec: &str, with: Vec<(&str, &str)>) -> Result<Value, Error> {
+			let main = format!("(import 'kct.libsonnet').generate({spec})");
+			let mut with = with;
+			with.push(("templates/main.jsonnet", &main));
+
+			let (package, _dir) = package(with, vec![]);
+
+			compile_with_example(package.unwrap(), None)
+		}
+
+		#[test]
+		fn appends_content_hash_to_name() {
+			let spec = "{kind: 'ConfigMap', name: 'settings', files: ['no-params.txt'], literals: {mode: 'prod'}}";
+			let rendered = generate(spec, vec![]).unwrap();
+
+			let name = rendered["metadata"]["name"].as_str().unwrap();
+			assert!(name.starts_with("settings-"));
+			assert_eq!(name.len(), "settings-".len() + 10);
+			assert_eq!(
+				rendered["metadata"]["annotations"]["kct.io/generated-from"],
+				"settings"
+			);
+			assert_eq!(
+				rendered["data"],
+				json!({
+					"mode": "prod",
+					"no-params.txt": Fixture::contents("files/no-params.txt")
+				})
+			);
+		}
+
+		#[test]
+		fn changes_hash_with_contents() {
+			let name = |mode: &str| {
+				let spec = format!(
+					"{{kind: 'ConfigMap', name: 'settings', literals: {{mode: '{mode}'}}}}"
+				);
+				let rendered = generate(&spec, vec![]).unwrap();
+
+				rendered["metadata"]["name"].clone()
+			};
+
+			assert_eq!(name("prod"), name("prod"));
+			assert_ne!(name("prod"), name("dev"));
+		}
+
+		#[test]
+		fn can_skip_hash() {
+			let spec =
+				"{kind: 'ConfigMap', name: 'settings', literals: {mode: 'prod'}, hash: false}";
+			let rendered = generate(spec, vec![]).unwrap();
+
+			assert_eq!(rendered["metadata"], json!({"name": "settings"}));
+		}
+
+		#[test]
+		fn encodes_secrets_from_env_files() {
+			let env = "# credentials\nUSER=admin\n\nPASSWORD=s3cr3t\n";
+			let spec = "{kind: 'Secret', name: 'credentials', envs: ['app.env']}";
+			let rendered = generate(spec, vec![("files/app.env", env)]).unwrap();
+
+			assert_eq!(rendered["type"], "Opaque");
+			assert_eq!(
+				rendered["data"],
+				json!({"USER": "YWRtaW4=", "PASSWORD": "czNjcjN0"})
+			);
+		}
+
+		#[test]
+		#[should_panic(expected = "Key mode appears more than once in settings")]
+		fn fails_on_repeated_keys() {
+			let env = "mode=dev";
+			let spec = "{kind: 'ConfigMap', name: 'settings', envs: ['app.env'], literals: {mode: 'prod'}}";
+
+			match generate(spec, vec![("files/app.env", env)]).unwrap_err() {
+				Error::Compilation(err) => panic_any(err.to_string()),
+				_ => panic!("It should be a compilation issue!"),
+			}
+		}
+
+		#[test]
+		fn exposes_generators_from_spec() {
+			let spec = r#"{
+				"name": "kcp",
+				"version": "0.1.0",
+				"generators": [{"kind": "ConfigMap", "name": "settings", "literals": {"mode": "prod"}}]
+			}"#;
+			let (package, _dir) = package(
+				vec![
+					("kcp.json", spec),
+					(
+						"templates/main.jsonnet",
+						"(import 'kct.libsonnet').generated",
+					),
+				],
+				vec![],
+			);
+
+			let rendered = compile_with_example(package.unwrap(), None).unwrap();
+			let generated = &rendered["settings"];
+
+			assert_eq!(generated["kind"], "ConfigMap");
+			assert_eq!(generated["data"], json!({"mode": "prod"}));
+		}
+
+		#[test]
+		fn leaves_output_untouched() {
+			let spec = r#"{
+				"name": "kcp",
+				"version": "0.1.0",
+				"generators": [{"kind": "ConfigMap", "name": "settings"}]
+			}"#;
+			let (package, _dir) = package(
+				vec![("kcp.json", spec), ("templates/main.jsonnet", "{}")],
+				vec![],
+			);
+
+			let rendered = compile_with_example(package.unwrap(), None).unwrap();
+
+			assert_eq!(rendered, json!({}));
+		}
+	}
+
 	mod release {
 		use super::*;
 
@@ -12,7 +12,7 @@ pub fn tmp() -> TempDir {
 	};
 
 	TempDir::new_in(&temproot)
-		.unwrap_or_else(|_| panic!("Unable to create tempdir at: {}", &temproot.display()))
+		.unwrap_or_else(|_| panic!("Unable to create tempdir at: {}", temproot.display()))
 }
 
 pub fn mk(at: &Path) {
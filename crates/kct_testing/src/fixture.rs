@@ -35,7 +35,7 @@ impl Fixture {
 			}
 
 			fs::write(&to_add, contents)
-				.unwrap_or_else(|_| panic!("Unable to write file at: {}", &to_add.display()));
+				.unwrap_or_else(|_| panic!("Unable to write file at: {}", to_add.display()));
 		}
 
 		for path in without {
@@ -52,6 +52,6 @@ impl Fixture {
 		at.push(path);
 
 		fs::read_to_string(&at)
-			.unwrap_or_else(|_| panic!("Unable to read contents from: {}", &at.display()))
+			.unwrap_or_else(|_| panic!("Unable to read contents from: {}", at.display()))
 	}
 }
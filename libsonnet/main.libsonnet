@@ -3,6 +3,8 @@ local package = std.extVar("kct.io/package");
 local release = std.extVar("kct.io/release");
 local files = std.extVar("kct.io/files");
 local include = std.extVar("kct.io/include");
+local generate = std.extVar("kct.io/generate");
+local generated = std.extVar("kct.io/generated");
 
 {
 	name: if release != null then '%s-%s' % [release.name, package.name] else package.name,
@@ -11,5 +13,7 @@ local include = std.extVar("kct.io/include");
 	release: release,
 	files(glob, input = inputs): files(glob, input),
 	include(dep, input = null): include(dep, input),
+	generate(spec): generate(spec),
+	generated: generated,
 	sdk: import 'sdk.libsonnet',
 }